@@ -1,11 +1,13 @@
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
 use std::ops::{AddAssign, SubAssign};
+use std::str::FromStr;
 use std::{
     cmp::Ordering,
     ops::{Add, Sub},
 };
 
+use anyhow::Context;
 #[cfg(feature = "bevy")]
 use bevy::log::warn;
 use num::{FromPrimitive, Rational32};
@@ -108,6 +110,44 @@ impl Debug for Beat {
     }
 }
 
+/// Parse a beat from `3`, `3+1/4`, `1/3` or `3.5`, decimals are approximated by a simple fraction
+impl FromStr for Beat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (whole, fraction) = match s.split_once('+') {
+            Some((whole, fraction)) => (whole.trim(), Some(fraction.trim())),
+            None if s.contains('/') => ("0", Some(s)),
+            None => (s, None),
+        };
+
+        let whole = match whole.parse::<i32>() {
+            Ok(whole) => Rational32::from_integer(whole),
+            Err(_) if fraction.is_none() => {
+                let value = whole.parse::<f32>().context("Invalid beat")?;
+                Rational32::approximate_float(value).context("Beat out of range")?
+            }
+            Err(err) => return Err(err).context("Invalid beat"),
+        };
+
+        let fraction = match fraction {
+            Some(fraction) => {
+                let (numer, denom) = fraction.split_once('/').context("Invalid fraction")?;
+                let numer = numer.trim().parse::<i32>().context("Invalid numerator")?;
+                let denom = denom.trim().parse::<i32>().context("Invalid denominator")?;
+                if denom == 0 {
+                    anyhow::bail!("Denominator cannot be zero");
+                }
+                Rational32::new(numer, denom)
+            }
+            None => Rational32::ZERO,
+        };
+
+        Ok(Beat::from(whole + fraction))
+    }
+}
+
 pub mod utils {
     use crate::beat;
     use crate::beat::Beat;
@@ -262,6 +302,16 @@ impl Ord for Beat {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_from_str() {
+        assert_eq!("3".parse::<Beat>().unwrap(), beat!(3));
+        assert_eq!("3+1/4".parse::<Beat>().unwrap(), beat!(3, 1, 4));
+        assert_eq!("1/3".parse::<Beat>().unwrap(), beat!(0, 1, 3));
+        assert_eq!("2.5".parse::<Beat>().unwrap(), beat!(2, 1, 2));
+        assert!("1/0".parse::<Beat>().is_err());
+        assert!("beat".parse::<Beat>().is_err());
+    }
+
     #[test]
     fn test_eq() {
         assert_eq!(beat!(1, 2, 1), beat!(1, 2, 1));
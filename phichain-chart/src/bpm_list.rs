@@ -1,11 +1,26 @@
 use crate::beat;
 use crate::beat::Beat;
+use crate::easing::Easing;
 use serde::{Deserialize, Deserializer, Serialize};
 
+/// Steps per beat used to approximate BPM ramps when exporting to formats without gradual BPM changes
+pub const RAMP_SAMPLE_DENSITY: u32 = 16;
+
+/// Number of intervals used to integrate eased BPM ramps numerically
+const RAMP_INTEGRATION_STEPS: usize = 64;
+/// Number of iterations used to find the beat at a given time on eased BPM ramps
+const RAMP_BISECTION_ITERATIONS: usize = 48;
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct BpmPoint {
     pub beat: Beat,
     pub bpm: f32,
+    /// If present, the BPM changes gradually from this point to the next point with the easing,
+    /// instead of changing at the next point
+    ///
+    /// A ramp on the last point has no effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp: Option<Easing>,
 
     #[serde(skip_serializing, default)]
     time: f32,
@@ -13,7 +28,7 @@ pub struct BpmPoint {
 
 impl PartialEq for BpmPoint {
     fn eq(&self, other: &Self) -> bool {
-        self.beat == other.beat && self.bpm == other.bpm
+        self.beat == other.beat && self.bpm == other.bpm && self.ramp == other.ramp
     }
 }
 
@@ -22,12 +37,21 @@ impl BpmPoint {
         Self {
             beat,
             bpm,
+            ramp: None,
             time: 0.0,
         }
     }
+
+    /// Create a point ramping to the BPM of the next point with the easing
+    pub fn ramp(beat: Beat, bpm: f32, easing: Easing) -> Self {
+        Self {
+            ramp: Some(easing),
+            ..Self::new(beat, bpm)
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[cfg_attr(feature = "bevy", derive(bevy::prelude::Resource))]
 pub struct BpmList(pub Vec<BpmPoint>);
 
@@ -63,15 +87,86 @@ impl BpmList {
 
     pub fn compute(&mut self) {
         let mut time = 0.0;
-        let mut last_beat = 0.0;
-        let mut last_bpm = -1.0;
-        for point in &mut self.0 {
-            if last_bpm != -1.0 {
-                time += (point.beat.value() - last_beat) * (60.0 / last_bpm);
+        for index in 0..self.0.len() {
+            if index > 0 {
+                time += self.segment_time(index - 1, self.0[index].beat.value());
+            }
+            self.0[index].time = time;
+        }
+    }
+
+    /// If any point ramps to its next point
+    pub fn has_ramps(&self) -> bool {
+        (0..self.0.len()).any(|index| self.ramp_of(index).is_some())
+    }
+
+    /// The easing and the end point of the ramp starting from the point at the index, if there is one
+    fn ramp_of(&self, index: usize) -> Option<(Easing, &BpmPoint)> {
+        let easing = self.0[index].ramp?;
+        let next = self.0.get(index + 1)?;
+        (next.beat > self.0[index].beat).then_some((easing, next))
+    }
+
+    /// Seconds from the point at the index to the beat, where the beat is not after the next point
+    fn segment_time(&self, index: usize, beat: f32) -> f32 {
+        let point = &self.0[index];
+        let delta = beat - point.beat.value();
+
+        match self.ramp_of(index) {
+            Some((easing, next)) if delta > 0.0 => {
+                let length = next.beat.value() - point.beat.value();
+                let slope = (next.bpm - point.bpm) / length;
+                match easing {
+                    _ if slope == 0.0 => delta * (60.0 / point.bpm),
+                    // bpm(b) = bpm0 + slope * b, integrating 60 / bpm(b) gives a logarithm
+                    Easing::Linear => 60.0 / slope * ((point.bpm + slope * delta) / point.bpm).ln(),
+                    _ => {
+                        // Simpson's rule
+                        let step = delta / RAMP_INTEGRATION_STEPS as f32;
+                        let f = |i: usize| {
+                            let beat = point.beat.value() + step * i as f32;
+                            60.0 / ramp_bpm(point, next, easing, beat)
+                        };
+                        let sum = (1..RAMP_INTEGRATION_STEPS)
+                            .map(|i| if i % 2 == 1 { 4.0 } else { 2.0 } * f(i))
+                            .sum::<f32>();
+                        (f(0) + sum + f(RAMP_INTEGRATION_STEPS)) * step / 3.0
+                    }
+                }
             }
-            last_beat = point.beat.value();
-            last_bpm = point.bpm;
-            point.time = time;
+            _ => delta * (60.0 / point.bpm),
+        }
+    }
+
+    /// Beat after the given seconds from the point at the index, where the time is not after the next point
+    fn segment_beat(&self, index: usize, elapsed: f32) -> f32 {
+        let point = &self.0[index];
+
+        match self.ramp_of(index) {
+            Some((easing, next)) if elapsed > 0.0 => {
+                let length = next.beat.value() - point.beat.value();
+                let slope = (next.bpm - point.bpm) / length;
+                match easing {
+                    _ if slope == 0.0 => point.beat.value() + elapsed * point.bpm / 60.0,
+                    Easing::Linear => {
+                        point.beat.value()
+                            + point.bpm * ((slope * elapsed / 60.0).exp() - 1.0) / slope
+                    }
+                    _ => {
+                        let (mut low, mut high) = (point.beat.value(), next.beat.value());
+                        for _ in 0..RAMP_BISECTION_ITERATIONS {
+                            let middle = (low + high) / 2.0;
+                            if self.segment_time(index, middle) < elapsed {
+                                low = middle;
+                            } else {
+                                high = middle;
+                            }
+                        }
+                        (low + high) / 2.0
+                    }
+                }
+            }
+            _ => point.beat.value() + elapsed * point.bpm / 60.0,
         }
     }
 
@@ -89,15 +184,8 @@ impl BpmList {
     }
 
     pub fn time_at(&self, beat: Beat) -> f32 {
-        let point = self
-            .0
-            .iter()
-            .take_while(|p| p.beat.value() < beat.value())
-            .last()
-            .or_else(|| self.0.first())
-            .expect("No bpm points available");
-
-        point.time + (beat.value() - point.beat.value()) * (60.0 / point.bpm)
+        let index = self.index_before(|p| p.beat.value() < beat.value());
+        self.0[index].time + self.segment_time(index, beat.value())
     }
 
     pub fn beat_at(&self, time: f32) -> Beat {
@@ -106,15 +194,53 @@ impl BpmList {
 
     /// Get the beat at the given time without converting the result to [`Beat`]
     pub fn beat_at_f32(&self, time: f32) -> f32 {
-        let point = self
-            .0
+        let index = self.index_before(|p| p.time <= time);
+        self.segment_beat(index, time - self.0[index].time)
+    }
+
+    /// Get the BPM at the given beat, taking ramps into account
+    pub fn bpm_at(&self, beat: Beat) -> f32 {
+        let index = self.index_before(|p| p.beat <= beat);
+        let point = &self.0[index];
+        match self.ramp_of(index) {
+            Some((easing, next)) if beat > point.beat => {
+                ramp_bpm(point, next, easing, beat.value())
+            }
+            _ => point.bpm,
+        }
+    }
+
+    /// Index of the last point satisfying the predicate, or the first point if there is none
+    fn index_before(&self, predicate: impl FnMut(&&BpmPoint) -> bool) -> usize {
+        assert!(!self.0.is_empty(), "No bpm points available");
+        self.0
             .iter()
-            .take_while(|p| p.time <= time)
-            .last()
-            .or_else(|| self.0.first())
-            .expect("No bpm points available");
+            .take_while(predicate)
+            .count()
+            .saturating_sub(1)
+    }
 
-        point.beat.value() + (time - point.time) * point.bpm / 60.0
+    /// Approximate ramps with step changes every `1 / density` beat, for formats without gradual BPM changes
+    ///
+    /// The BPM of each step is chosen to keep the time of each step exact
+    pub fn sampled(&self, density: u32) -> BpmList {
+        let mut points = vec![];
+        for (index, point) in self.0.iter().enumerate() {
+            let Some((_, next)) = self.ramp_of(index) else {
+                points.push(BpmPoint::new(point.beat, point.bpm));
+                continue;
+            };
+
+            let mut beat = point.beat;
+            while beat < next.beat {
+                let end = (beat + beat!(0, 1, density)).min(next.beat);
+                let duration = self.time_at(end) - self.time_at(beat);
+                points.push(BpmPoint::new(beat, 60.0 * (end - beat).value() / duration));
+                beat = end;
+            }
+        }
+
+        BpmList::new(points)
     }
 
     /// Normalize a [`Beat`] on this [`BpmList`] to a [`Beat`] on a fixed BPM
@@ -138,6 +264,12 @@ impl BpmList {
     }
 }
 
+/// BPM at the beat on the ramp from `point` to `next`
+fn ramp_bpm(point: &BpmPoint, next: &BpmPoint, easing: Easing, beat: f32) -> f32 {
+    let progress = (beat - point.beat.value()) / (next.beat.value() - point.beat.value());
+    point.bpm + (next.bpm - point.bpm) * easing.ease(progress.clamp(0.0, 1.0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,4 +350,68 @@ mod tests {
 
         assert_eq!(bpm_list.normalize_beat(60.0, beat!(4)), beat!(2));
     }
+
+    #[test]
+    fn test_linear_ramp() {
+        // 60 BPM ramps to 180 BPM in 4 beats
+        let bpm_list = BpmList::new(vec![
+            BpmPoint::ramp(beat!(0), 60.0, Easing::Linear),
+            BpmPoint::new(beat!(4), 180.0),
+        ]);
+
+        let expected = 60.0 / 30.0 * 3.0f32.ln();
+        assert!((bpm_list.time_at(beat!(4)) - expected).abs() < 1e-5);
+        assert!((bpm_list.time_at(beat!(5)) - expected - 60.0 / 180.0).abs() < 1e-5);
+        assert_eq!(bpm_list.bpm_at(beat!(2)), 120.0);
+        assert_eq!(bpm_list.bpm_at(beat!(6)), 180.0);
+
+        for beat in [0.5, 1.0, 2.5, 4.0, 6.0] {
+            let time = bpm_list.time_at(Beat::from(beat));
+            assert!((bpm_list.beat_at_f32(time) - beat).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_eased_ramp() {
+        let linear = BpmList::new(vec![
+            BpmPoint::ramp(beat!(0), 120.0, Easing::Linear),
+            BpmPoint::new(beat!(8), 240.0),
+        ]);
+        let eased = BpmList::new(vec![
+            BpmPoint::ramp(beat!(0), 120.0, Easing::Custom(0.0, 0.0, 1.0, 1.0)),
+            BpmPoint::new(beat!(8), 240.0),
+        ]);
+
+        // a linear custom curve matches the analytic solution
+        for beat in [1.0, 3.0, 8.0] {
+            let beat = Beat::from(beat);
+            assert!((linear.time_at(beat) - eased.time_at(beat)).abs() < 1e-3);
+        }
+
+        let quad = BpmList::new(vec![
+            BpmPoint::ramp(beat!(0), 120.0, Easing::EaseInQuad),
+            BpmPoint::new(beat!(8), 240.0),
+        ]);
+        // reaching the higher BPM later takes more time
+        assert!(quad.time_at(beat!(8)) > linear.time_at(beat!(8)));
+        let time = quad.time_at(beat!(3));
+        assert!((quad.beat_at_f32(time) - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_sampled() {
+        let bpm_list = BpmList::new(vec![
+            BpmPoint::ramp(beat!(0), 60.0, Easing::Linear),
+            BpmPoint::new(beat!(2), 120.0),
+        ]);
+        assert!(bpm_list.has_ramps());
+
+        let sampled = bpm_list.sampled(4);
+        assert!(!sampled.has_ramps());
+        assert_eq!(sampled.0.len(), 9);
+        for i in 0..=12 {
+            let beat = beat!(0, i, 4);
+            assert!((sampled.time_at(beat) - bpm_list.time_at(beat)).abs() < 1e-4);
+        }
+    }
 }
@@ -0,0 +1,844 @@
+//! Structural diff and three-way merge of [`PhichainChart`]s
+//!
+//! Lines are matched among their siblings by their ids, a line without an id falls back to its index.
+//! Notes and events of a matched line are matched by their content.
+//! A note or an event which is not found on the other side is paired with one with the same id,
+//! or one without a different id at the same position (the same beat for notes, the same kind and start beat for events)
+//! as a modification, or reported as added / removed otherwise
+
+use crate::beat::Beat;
+use crate::bpm_list::{BpmList, BpmPoint};
+use crate::event::{LineEvent, LineEventKind};
+use crate::id::Identifiable;
+use crate::line::Line;
+use crate::note::Note;
+use crate::serialization::{LineWrapper, PhichainChart};
+use crate::time_signature::TimeSignatureList;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Modified { from: T, to: T },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineDiff {
+    /// Index path of the line in the new chart, e.g. `[1, 0]` is the first child of the second line
+    pub path: Vec<usize>,
+    /// Properties of the line, e.g. name and texture, if changed
+    pub properties: Option<(Line, Line)>,
+    pub notes: Vec<Change<Note>>,
+    pub events: Vec<Change<LineEvent>>,
+}
+
+impl LineDiff {
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_none() && self.notes.is_empty() && self.events.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartDiff {
+    pub offset: Option<(f32, f32)>,
+    pub bpm_list: Option<(BpmList, BpmList)>,
+    pub time_signatures: Option<(TimeSignatureList, TimeSignatureList)>,
+    /// Index paths of lines only exist in the new chart
+    pub added_lines: Vec<Vec<usize>>,
+    /// Index paths of lines only exist in the old chart
+    pub removed_lines: Vec<Vec<usize>>,
+    /// Changes of lines exist in both charts, unchanged lines are omitted
+    pub lines: Vec<LineDiff>,
+}
+
+impl ChartDiff {
+    pub fn is_empty(&self) -> bool {
+        self.offset.is_none()
+            && self.bpm_list.is_none()
+            && self.time_signatures.is_none()
+            && self.added_lines.is_empty()
+            && self.removed_lines.is_empty()
+            && self.lines.is_empty()
+    }
+}
+
+/// Something that can be paired as a modification when it is not matched exactly
+trait Anchored: Clone + PartialEq + Identifiable {
+    type Anchor: PartialEq;
+
+    fn anchor(&self) -> Self::Anchor;
+}
+
+impl Anchored for Note {
+    type Anchor = Beat;
+
+    fn anchor(&self) -> Self::Anchor {
+        self.beat
+    }
+}
+
+impl Anchored for LineEvent {
+    type Anchor = (LineEventKind, Beat);
+
+    fn anchor(&self) -> Self::Anchor {
+        (self.kind, self.start_beat)
+    }
+}
+
+/// What happened to an item of the old side on the new side
+#[derive(Debug, Clone, PartialEq)]
+enum Fate<T> {
+    Unchanged,
+    Removed,
+    Modified(T),
+}
+
+/// Match items of two sides, returning the fate of each old item and the added items
+fn match_items<T: Anchored>(from: &[T], to: &[T]) -> (Vec<Fate<T>>, Vec<T>) {
+    let mut fates = vec![Fate::Removed; from.len()];
+    let mut matched = vec![false; to.len()];
+
+    for (index, item) in from.iter().enumerate() {
+        if let Some(position) = (0..to.len()).find(|i| !matched[*i] && to[*i] == *item) {
+            matched[position] = true;
+            fates[index] = Fate::Unchanged;
+        }
+    }
+
+    // items with the same id are the same item, no matter where they are
+    for (index, item) in from.iter().enumerate() {
+        if fates[index] != Fate::Removed || item.id().is_none() {
+            continue;
+        }
+        if let Some(position) = (0..to.len()).find(|i| !matched[*i] && to[*i].id() == item.id()) {
+            matched[position] = true;
+            fates[index] = Fate::Modified(to[position].clone());
+        }
+    }
+
+    // items with different ids are never paired by their anchors
+    let distinct = |a: &T, b: &T| matches!((a.id(), b.id()), (Some(a), Some(b)) if a != b);
+    for (index, item) in from.iter().enumerate() {
+        if fates[index] != Fate::Removed {
+            continue;
+        }
+        if let Some(position) = (0..to.len())
+            .find(|i| !matched[*i] && to[*i].anchor() == item.anchor() && !distinct(&to[*i], item))
+        {
+            matched[position] = true;
+            fates[index] = Fate::Modified(to[position].clone());
+        }
+    }
+
+    let added = to
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(item, _)| item.clone())
+        .collect();
+
+    (fates, added)
+}
+
+fn diff_items<T: Anchored>(from: &[T], to: &[T]) -> Vec<Change<T>> {
+    let (fates, added) = match_items(from, to);
+    let mut changes = vec![];
+    for (item, fate) in from.iter().zip(fates) {
+        match fate {
+            Fate::Unchanged => {}
+            Fate::Removed => changes.push(Change::Removed(item.clone())),
+            Fate::Modified(to) => changes.push(Change::Modified {
+                from: item.clone(),
+                to,
+            }),
+        }
+    }
+    changes.extend(added.into_iter().map(Change::Added));
+
+    changes
+}
+
+/// Compare two charts structurally
+pub fn diff(from: &PhichainChart, to: &PhichainChart) -> ChartDiff {
+    let mut diff = ChartDiff::default();
+
+    if from.offset.0 != to.offset.0 {
+        diff.offset = Some((from.offset.0, to.offset.0));
+    }
+    if from.bpm_list.0 != to.bpm_list.0 {
+        diff.bpm_list = Some((from.bpm_list.clone(), to.bpm_list.clone()));
+    }
+    if from.time_signatures != to.time_signatures {
+        diff.time_signatures = Some((from.time_signatures.clone(), to.time_signatures.clone()));
+    }
+
+    diff_lines(&from.lines, &to.lines, vec![], vec![], &mut diff);
+
+    diff
+}
+
+/// Pair sibling lines of two sides, returning the index on the new side of each old line
+///
+/// Lines with the same id are paired wherever they are, the remaining lines are paired by their index
+/// unless both of them have ids
+fn match_lines(from: &[LineWrapper], to: &[LineWrapper]) -> Vec<Option<usize>> {
+    let mut pairs = vec![None; from.len()];
+    let mut matched = vec![false; to.len()];
+
+    for (index, line) in from.iter().enumerate() {
+        let Some(id) = line.line.id else {
+            continue;
+        };
+        if let Some(position) = (0..to.len()).find(|i| !matched[*i] && to[*i].line.id == Some(id)) {
+            matched[position] = true;
+            pairs[index] = Some(position);
+        }
+    }
+
+    for (index, line) in from.iter().enumerate() {
+        if pairs[index].is_some() || index >= to.len() || matched[index] {
+            continue;
+        }
+        if line.line.id.is_none() || to[index].line.id.is_none() {
+            matched[index] = true;
+            pairs[index] = Some(index);
+        }
+    }
+
+    pairs
+}
+
+/// Indices of the lines on the new side not paired with any old line
+fn added_lines(pairs: &[Option<usize>], len: usize) -> Vec<usize> {
+    (0..len).filter(|i| !pairs.contains(&Some(*i))).collect()
+}
+
+fn child_path(path: &[usize], index: usize) -> Vec<usize> {
+    let mut path = path.to_vec();
+    path.push(index);
+    path
+}
+
+fn diff_lines(
+    from: &[LineWrapper],
+    to: &[LineWrapper],
+    from_path: Vec<usize>,
+    to_path: Vec<usize>,
+    diff: &mut ChartDiff,
+) {
+    let pairs = match_lines(from, to);
+
+    for (index, (line, pair)) in from.iter().zip(&pairs).enumerate() {
+        let Some(position) = *pair else {
+            diff.removed_lines.push(child_path(&from_path, index));
+            continue;
+        };
+        let other = &to[position];
+        let path = child_path(&to_path, position);
+
+        let line_diff = LineDiff {
+            path: path.clone(),
+            properties: (line.line != other.line).then(|| (line.line.clone(), other.line.clone())),
+            notes: diff_items(&line.notes, &other.notes),
+            events: diff_items(&line.events, &other.events),
+        };
+        if !line_diff.is_empty() {
+            diff.lines.push(line_diff);
+        }
+        diff_lines(
+            &line.children,
+            &other.children,
+            child_path(&from_path, index),
+            path,
+            diff,
+        );
+    }
+
+    for position in added_lines(&pairs, to.len()) {
+        diff.added_lines.push(child_path(&to_path, position));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictKind {
+    Offset,
+    BpmList,
+    TimeSignatures,
+    /// Both sides changed the properties of the line differently
+    LineProperties,
+    /// One side removed the line while the other side modified it
+    LineRemoved,
+    /// Both sides added different lines at the same position, the line of the other side is placed after ours
+    LineAdded,
+    /// Both sides changed the note at the beat differently
+    Note {
+        beat: Beat,
+    },
+    /// Both sides changed the event differently
+    Event {
+        kind: LineEventKind,
+        start_beat: Beat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// Index path of the line in the merged chart, empty for chart-level conflicts
+    pub path: Vec<usize>,
+    pub kind: ConflictKind,
+}
+
+pub struct MergeResult {
+    /// The merged chart, conflicting changes are resolved by taking our side
+    pub chart: PhichainChart,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Pick a value changed by either side, or our value with a conflict if both sides changed it differently
+fn merge_value<T: Clone + PartialEq>(base: &T, ours: &T, theirs: &T) -> (T, bool) {
+    if ours == theirs || theirs == base {
+        (ours.clone(), false)
+    } else if ours == base {
+        (theirs.clone(), false)
+    } else {
+        (ours.clone(), true)
+    }
+}
+
+fn merge_items<T: Anchored>(
+    base: &[T],
+    ours: &[T],
+    theirs: &[T],
+    mut conflict: impl FnMut(&T),
+) -> Vec<T> {
+    let (our_fates, our_added) = match_items(base, ours);
+    let (their_fates, their_added) = match_items(base, theirs);
+
+    let mut result = vec![];
+    for ((item, ours), theirs) in base.iter().zip(our_fates).zip(their_fates) {
+        match (ours, theirs) {
+            (Fate::Unchanged, Fate::Unchanged) => result.push(item.clone()),
+            (Fate::Removed, Fate::Removed)
+            | (Fate::Unchanged, Fate::Removed)
+            | (Fate::Removed, Fate::Unchanged) => {}
+            (Fate::Modified(to), Fate::Unchanged) | (Fate::Unchanged, Fate::Modified(to)) => {
+                result.push(to)
+            }
+            (Fate::Modified(ours), Fate::Modified(theirs)) => {
+                if ours != theirs {
+                    conflict(item);
+                }
+                result.push(ours);
+            }
+            (Fate::Removed, Fate::Modified(_)) => conflict(item),
+            (Fate::Modified(ours), Fate::Removed) => {
+                conflict(item);
+                result.push(ours);
+            }
+        }
+    }
+
+    // items added by both sides are only kept once
+    let mut their_added = their_added;
+    for item in our_added {
+        if let Some(index) = their_added.iter().position(|x| *x == item) {
+            their_added.remove(index);
+        }
+        result.push(item);
+    }
+    result.extend(their_added);
+
+    result
+}
+
+/// Merge changes of two charts derived from the same base chart
+pub fn merge(base: &PhichainChart, ours: &PhichainChart, theirs: &PhichainChart) -> MergeResult {
+    let mut conflicts = vec![];
+
+    let (offset, conflict) = merge_value(&base.offset.0, &ours.offset.0, &theirs.offset.0);
+    if conflict {
+        conflicts.push(Conflict {
+            path: vec![],
+            kind: ConflictKind::Offset,
+        });
+    }
+
+    let (bpm_points, conflict) =
+        merge_value(&base.bpm_list.0, &ours.bpm_list.0, &theirs.bpm_list.0);
+    if conflict {
+        conflicts.push(Conflict {
+            path: vec![],
+            kind: ConflictKind::BpmList,
+        });
+    }
+
+    let (time_signatures, conflict) = merge_value(
+        &base.time_signatures,
+        &ours.time_signatures,
+        &theirs.time_signatures,
+    );
+    if conflict {
+        conflicts.push(Conflict {
+            path: vec![],
+            kind: ConflictKind::TimeSignatures,
+        });
+    }
+
+    let lines = merge_lines(
+        &base.lines,
+        &ours.lines,
+        &theirs.lines,
+        vec![],
+        &mut conflicts,
+    );
+
+    let mut chart = PhichainChart::new(offset, BpmList::new(bpm_points), lines);
+    chart.time_signatures = time_signatures;
+
+    MergeResult { chart, conflicts }
+}
+
+/// A line of the merged chart, before the lines are merged
+enum Slot {
+    /// A base line kept by ours, with its index on our side
+    Base(usize, usize),
+    /// A base line removed by ours
+    Removed(usize),
+    /// A line added by ours
+    Ours(usize),
+    /// A line added by theirs, conflicting if ours added a different line at the same position
+    Theirs(usize, bool),
+}
+
+fn merge_lines(
+    base: &[LineWrapper],
+    ours: &[LineWrapper],
+    theirs: &[LineWrapper],
+    path: Vec<usize>,
+    conflicts: &mut Vec<Conflict>,
+) -> Vec<LineWrapper> {
+    let our_pairs = match_lines(base, ours);
+    let their_pairs = match_lines(base, theirs);
+
+    // lines added by theirs are placed after the nearest preceding line they share with ours
+    let mut their_base = vec![None; theirs.len()];
+    for (index, pair) in their_pairs.iter().enumerate() {
+        if let Some(position) = pair {
+            their_base[*position] = Some(index);
+        }
+    }
+    let mut their_added = vec![];
+    let mut anchor = None;
+    for (position, base_index) in their_base.iter().enumerate() {
+        match base_index {
+            Some(index) if our_pairs[*index].is_some() => anchor = Some(*index),
+            Some(_) => {}
+            None => their_added.push((anchor, position)),
+        }
+    }
+
+    let mut slots = vec![];
+    let mut our_added: Vec<usize> = vec![];
+    let mut removed = (0..base.len())
+        .filter(|i| our_pairs[*i].is_none())
+        .peekable();
+    let flush = |anchor: Option<usize>, our_added: &mut Vec<usize>, slots: &mut Vec<Slot>| {
+        for (_, position) in their_added.iter().filter(|(x, _)| *x == anchor) {
+            // lines added by both sides are only kept once
+            if let Some(index) = our_added.iter().position(|x| ours[*x] == theirs[*position]) {
+                our_added.remove(index);
+                continue;
+            }
+            slots.push(Slot::Theirs(*position, !our_added.is_empty()));
+        }
+        our_added.clear();
+    };
+
+    let mut anchor = None;
+    for position in 0..ours.len() {
+        match our_pairs.iter().position(|x| *x == Some(position)) {
+            Some(index) => {
+                flush(anchor, &mut our_added, &mut slots);
+                while let Some(removed_index) = removed.next_if(|x| *x < index) {
+                    slots.push(Slot::Removed(removed_index));
+                }
+                slots.push(Slot::Base(index, position));
+                anchor = Some(index);
+            }
+            None => {
+                slots.push(Slot::Ours(position));
+                our_added.push(position);
+            }
+        }
+    }
+    flush(anchor, &mut our_added, &mut slots);
+    slots.extend(removed.map(Slot::Removed));
+
+    let mut lines = vec![];
+    for slot in slots {
+        // path of the line in the merged chart
+        let line_path = child_path(&path, lines.len());
+
+        match slot {
+            Slot::Base(index, position) => match their_pairs[index] {
+                Some(their_position) => lines.push(merge_line(
+                    &base[index],
+                    &ours[position],
+                    &theirs[their_position],
+                    line_path,
+                    conflicts,
+                )),
+                None => {
+                    if ours[position] != base[index] {
+                        conflicts.push(Conflict {
+                            path: line_path,
+                            kind: ConflictKind::LineRemoved,
+                        });
+                        lines.push(ours[position].clone());
+                    }
+                }
+            },
+            Slot::Removed(index) => {
+                if their_pairs[index].is_some_and(|x| theirs[x] != base[index]) {
+                    conflicts.push(Conflict {
+                        path: line_path,
+                        kind: ConflictKind::LineRemoved,
+                    });
+                }
+            }
+            Slot::Ours(position) => lines.push(ours[position].clone()),
+            Slot::Theirs(position, conflict) => {
+                if conflict {
+                    conflicts.push(Conflict {
+                        path: line_path,
+                        kind: ConflictKind::LineAdded,
+                    });
+                }
+                lines.push(theirs[position].clone());
+            }
+        }
+    }
+
+    lines
+}
+
+fn merge_line(
+    base: &LineWrapper,
+    ours: &LineWrapper,
+    theirs: &LineWrapper,
+    path: Vec<usize>,
+    conflicts: &mut Vec<Conflict>,
+) -> LineWrapper {
+    let (line, conflict) = merge_value(&base.line, &ours.line, &theirs.line);
+    if conflict {
+        conflicts.push(Conflict {
+            path: path.clone(),
+            kind: ConflictKind::LineProperties,
+        });
+    }
+
+    let notes = merge_items(&base.notes, &ours.notes, &theirs.notes, |note| {
+        conflicts.push(Conflict {
+            path: path.clone(),
+            kind: ConflictKind::Note { beat: note.beat },
+        })
+    });
+    let events = merge_items(&base.events, &ours.events, &theirs.events, |event| {
+        conflicts.push(Conflict {
+            path: path.clone(),
+            kind: ConflictKind::Event {
+                kind: event.kind,
+                start_beat: event.start_beat,
+            },
+        })
+    });
+
+    let children = merge_lines(
+        &base.children,
+        &ours.children,
+        &theirs.children,
+        path,
+        conflicts,
+    );
+
+    LineWrapper::new(line, notes, events, children)
+}
+
+fn format_path(path: &[usize]) -> String {
+    path.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn format_bpm_point(point: &BpmPoint) -> String {
+    match point.ramp {
+        Some(easing) => format!("{:?}: {} ({} ramp)", point.beat, point.bpm, easing),
+        None => format!("{:?}: {}", point.beat, point.bpm),
+    }
+}
+
+fn format_note(note: &Note) -> String {
+    format!("{:?} at {:?}, x = {}", note.kind, note.beat, note.x)
+}
+
+fn format_event(event: &LineEvent) -> String {
+    format!(
+        "{:?} {:?} ~ {:?}: {:?}",
+        event.kind, event.start_beat, event.end_beat, event.value
+    )
+}
+
+impl Display for ChartDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some((from, to)) = self.offset {
+            writeln!(f, "offset: {} -> {}", from, to)?;
+        }
+        if let Some((from, to)) = &self.bpm_list {
+            writeln!(f, "bpm list:")?;
+            for point in &from.0 {
+                writeln!(f, "  - {}", format_bpm_point(point))?;
+            }
+            for point in &to.0 {
+                writeln!(f, "  + {}", format_bpm_point(point))?;
+            }
+        }
+        if let Some((from, to)) = &self.time_signatures {
+            writeln!(f, "time signatures:")?;
+            for point in &from.0 {
+                writeln!(f, "  - {:?}: {}", point.beat, point)?;
+            }
+            for point in &to.0 {
+                writeln!(f, "  + {:?}: {}", point.beat, point)?;
+            }
+        }
+        for path in &self.removed_lines {
+            writeln!(f, "line {}: removed", format_path(path))?;
+        }
+        for path in &self.added_lines {
+            writeln!(f, "line {}: added", format_path(path))?;
+        }
+        for line in &self.lines {
+            writeln!(f, "line {}:", format_path(&line.path))?;
+            if let Some((from, to)) = &line.properties {
+                writeln!(f, "  - {:?}", from)?;
+                writeln!(f, "  + {:?}", to)?;
+            }
+            for change in &line.notes {
+                match change {
+                    Change::Added(note) => writeln!(f, "  + note {}", format_note(note))?,
+                    Change::Removed(note) => writeln!(f, "  - note {}", format_note(note))?,
+                    Change::Modified { from, to } => {
+                        writeln!(f, "  ~ note {}", format_note(from))?;
+                        writeln!(f, "       -> {}", format_note(to))?;
+                    }
+                }
+            }
+            for change in &line.events {
+                match change {
+                    Change::Added(event) => writeln!(f, "  + event {}", format_event(event))?,
+                    Change::Removed(event) => writeln!(f, "  - event {}", format_event(event))?,
+                    Change::Modified { from, to } => {
+                        writeln!(f, "  ~ event {}", format_event(from))?;
+                        writeln!(f, "        -> {}", format_event(to))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "line {}: ", format_path(&self.path))?;
+        }
+        match &self.kind {
+            ConflictKind::Offset => f.write_str("offset changed on both sides"),
+            ConflictKind::BpmList => f.write_str("bpm list changed on both sides"),
+            ConflictKind::TimeSignatures => f.write_str("time signatures changed on both sides"),
+            ConflictKind::LineProperties => f.write_str("line properties changed on both sides"),
+            ConflictKind::LineRemoved => {
+                f.write_str("line removed on one side but modified on the other")
+            }
+            ConflictKind::LineAdded => {
+                f.write_str("different lines added on both sides, theirs is placed after ours")
+            }
+            ConflictKind::Note { beat } => write!(f, "note at {:?} changed on both sides", beat),
+            ConflictKind::Event { kind, start_beat } => write!(
+                f,
+                "{:?} event at {:?} changed on both sides",
+                kind, start_beat
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+    use crate::id::Id;
+    use crate::note::NoteKind;
+
+    fn note(beat: Beat, x: f32) -> Note {
+        Note::new(NoteKind::Tap, true, beat, x, 1.0)
+    }
+
+    fn chart(lines: Vec<Vec<Note>>) -> PhichainChart {
+        PhichainChart::new(
+            0.0,
+            BpmList::default(),
+            lines
+                .into_iter()
+                .map(|notes| LineWrapper {
+                    notes,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let from = chart(vec![vec![note(beat!(1), 0.0), note(beat!(2), 0.0)]]);
+        let mut to = chart(vec![
+            vec![note(beat!(2), 100.0), note(beat!(3), 0.0)],
+            vec![],
+        ]);
+        to.offset.0 = 10.0;
+
+        let diff = diff(&from, &to);
+        assert_eq!(diff.offset, Some((0.0, 10.0)));
+        assert_eq!(diff.added_lines, vec![vec![1]]);
+        assert_eq!(
+            diff.lines,
+            vec![LineDiff {
+                path: vec![0],
+                properties: None,
+                notes: vec![
+                    Change::Removed(note(beat!(1), 0.0)),
+                    Change::Modified {
+                        from: note(beat!(2), 0.0),
+                        to: note(beat!(2), 100.0)
+                    },
+                    Change::Added(note(beat!(3), 0.0)),
+                ],
+                events: vec![],
+            }]
+        );
+
+        assert!(super::diff(&from, &from).is_empty());
+    }
+
+    #[test]
+    fn test_diff_by_id() {
+        let id = Id::new();
+        let moved = Note {
+            id: Some(id),
+            ..note(beat!(1), 0.0)
+        };
+        let from = chart(vec![vec![moved]]);
+        let to = chart(vec![vec![
+            Note {
+                id: Some(Id::new()),
+                ..note(beat!(1), 0.0)
+            },
+            Note {
+                beat: beat!(4),
+                ..moved
+            },
+        ]]);
+
+        let diff = diff(&from, &to);
+        assert_eq!(
+            diff.lines[0].notes,
+            vec![
+                Change::Modified {
+                    from: moved,
+                    to: to.lines[0].notes[1]
+                },
+                Change::Added(to.lines[0].notes[0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_different_lines() {
+        let base = chart(vec![vec![note(beat!(1), 0.0)], vec![note(beat!(1), 0.0)]]);
+        let ours = chart(vec![
+            vec![note(beat!(1), 50.0), note(beat!(2), 0.0)],
+            vec![note(beat!(1), 0.0)],
+        ]);
+        let theirs = chart(vec![vec![note(beat!(1), 0.0)], vec![]]);
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(
+            result.chart.lines[0].notes,
+            vec![note(beat!(1), 50.0), note(beat!(2), 0.0)]
+        );
+        assert!(result.chart.lines[1].notes.is_empty());
+    }
+
+    #[test]
+    fn test_merge_inserted_line() {
+        let ids = [Id::new(), Id::new(), Id::new()];
+        let with_ids = |mut chart: PhichainChart| {
+            for (line, id) in chart.lines.iter_mut().zip(ids) {
+                line.line.id = Some(id);
+            }
+            chart
+        };
+        let lines = || vec![vec![note(beat!(1), 0.0)]; 3];
+        let base = with_ids(chart(lines()));
+
+        let mut inserted = LineWrapper::default();
+        inserted.line.id = Some(Id::new());
+        let mut ours = with_ids(chart(lines()));
+        ours.lines.insert(0, inserted.clone());
+
+        let mut theirs = with_ids(chart(lines()));
+        theirs.lines[2].notes[0].x = 100.0;
+
+        let our_diff = diff(&base, &ours);
+        assert_eq!(our_diff.added_lines, vec![vec![0]]);
+        assert!(our_diff.removed_lines.is_empty());
+        assert!(our_diff.lines.is_empty());
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.chart.lines.len(), 4);
+        assert_eq!(result.chart.lines[0], inserted);
+        assert_eq!(result.chart.lines[1].notes, vec![note(beat!(1), 0.0)]);
+        assert_eq!(result.chart.lines[2].notes, vec![note(beat!(1), 0.0)]);
+        assert_eq!(result.chart.lines[3].notes, vec![note(beat!(1), 100.0)]);
+
+        // the same edits on the opposite sides
+        let result = merge(&base, &theirs, &ours);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.chart.lines[0], inserted);
+        assert_eq!(result.chart.lines[3].notes, vec![note(beat!(1), 100.0)]);
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let base = chart(vec![vec![note(beat!(1), 0.0)]]);
+        let ours = chart(vec![vec![note(beat!(1), 50.0)]]);
+        let theirs = chart(vec![vec![note(beat!(1), -50.0)], vec![]]);
+
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(
+            result.conflicts,
+            vec![Conflict {
+                path: vec![0],
+                kind: ConflictKind::Note { beat: beat!(1) }
+            }]
+        );
+        assert_eq!(result.chart.lines[0].notes, vec![note(beat!(1), 50.0)]);
+        assert_eq!(result.chart.lines.len(), 2);
+    }
+}
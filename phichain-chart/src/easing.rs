@@ -5,10 +5,17 @@
 use serde::{Deserialize, Serialize};
 use simple_easing::*;
 use std::fmt::{Debug, Display, Formatter};
-use strum::EnumIter;
+use strum::{EnumDiscriminants, EnumIter, IntoEnumIterator};
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize, EnumIter)]
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize, EnumIter, EnumDiscriminants,
+)]
 #[serde(rename_all = "snake_case")]
+#[strum_discriminants(
+    name(EasingKind),
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
 #[repr(u8)]
 pub enum Easing {
     #[default]
@@ -55,6 +62,13 @@ pub enum Easing {
     EaseInOutBounce,
 
     Custom(f32, f32, f32, f32),
+
+    /// Only use the part of the inner easing between `left` and `right`, remapped to `[0, 1]`
+    ///
+    /// The inner easing is referenced by its [`EasingKind`], easings with parameters are not supported and fallback to linear,
+    /// [`Easing::partial`] splits custom easings into another custom easing instead
+    #[strum(disabled)]
+    Partial(EasingKind, f32, f32),
 }
 
 impl Easing {
@@ -93,6 +107,44 @@ impl Easing {
             Self::EaseInOutBounce => bounce_in_out(x),
 
             Self::Custom(x1, y1, x2, y2) => BezierTween::new((x1, y1), (x2, y2)).y(x),
+
+            Self::Partial(kind, left, right) => {
+                let easing = Self::from_kind(kind).unwrap_or_default();
+                let start = easing.ease(left);
+                let end = easing.ease(right);
+                if start == end {
+                    return x;
+                }
+
+                (easing.ease(left.ease_to(right, x, Self::Linear)) - start) / (end - start)
+            }
+        }
+    }
+
+    /// Get the easing of the given kind, returns [`None`] for kinds with parameters
+    pub fn from_kind(kind: EasingKind) -> Option<Self> {
+        match kind {
+            EasingKind::Custom | EasingKind::Partial => None,
+            _ => Self::iter().find(|easing| EasingKind::from(easing) == kind),
+        }
+    }
+
+    /// Wrap this easing into a [`Easing::Partial`] covering `[left, right]`
+    ///
+    /// Returns this easing unchanged if the range covers the whole curve or the easing is linear,
+    /// partial easings are narrowed down instead of being nested and custom easings are split into another custom easing
+    pub fn partial(self, left: f32, right: f32) -> Self {
+        match self {
+            _ if left == 0.0 && right == 1.0 => self,
+            Self::Linear => self,
+            Self::Custom(x1, y1, x2, y2) => {
+                let tween = BezierTween::new((x1, y1), (x2, y2));
+                tween.split(tween.t_for_x(left), tween.t_for_x(right))
+            }
+            Self::Partial(kind, l, r) => {
+                Self::Partial(kind, l + (r - l) * left, l + (r - l) * right)
+            }
+            _ => Self::Partial(self.into(), left, right),
         }
     }
 }
@@ -101,6 +153,7 @@ impl Display for Easing {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Easing::Custom(_, _, _, _) => write!(f, "Custom"),
+            Easing::Partial(kind, _, _) => write!(f, "{:?} (Partial)", kind),
             _ => write!(f, "{:?}", self),
         }
     }
@@ -197,6 +250,40 @@ impl BezierTween {
         }
     }
 
+    /// Get the part of the curve between the parameters `t0` and `t1` as a custom easing, remapped to `[0, 1]`
+    ///
+    /// Returns [`Easing::Linear`] if the part is flat
+    fn split(&self, t0: f32, t1: f32) -> Easing {
+        let points = [(0.0, 0.0), self.p1, self.p2, (1.0, 1.0)];
+        // the control points of the part are the blossoms of the curve
+        let blossom = |a: f32, b: f32, c: f32| {
+            let lerp = |p: (f32, f32), q: (f32, f32), t: f32| {
+                (p.0 + (q.0 - p.0) * t, p.1 + (q.1 - p.1) * t)
+            };
+            let [p0, p1, p2, p3] = points;
+            let (q0, q1, q2) = (lerp(p0, p1, a), lerp(p1, p2, a), lerp(p2, p3, a));
+            let (r0, r1) = (lerp(q0, q1, b), lerp(q1, q2, b));
+            lerp(r0, r1, c)
+        };
+
+        let start = blossom(t0, t0, t0);
+        let end = blossom(t1, t1, t1);
+        if start.0 == end.0 || start.1 == end.1 {
+            return Easing::Linear;
+        }
+
+        let normalize = |p: (f32, f32)| {
+            (
+                ((p.0 - start.0) / (end.0 - start.0)).clamp(0.0, 1.0),
+                (p.1 - start.1) / (end.1 - start.1),
+            )
+        };
+        let p1 = normalize(blossom(t0, t0, t1));
+        let p2 = normalize(blossom(t0, t1, t1));
+
+        Easing::Custom(p1.0, p1.1, p2.0, p2.1)
+    }
+
     pub fn new(p1: (f32, f32), p2: (f32, f32)) -> Self {
         Self {
             sample_table: std::array::from_fn(|i| Self::sample(p1.0, p2.0, i as f32 * SAMPLE_STEP)),
@@ -245,6 +332,49 @@ mod tests {
         assert_eq!(Easing::Custom(0.0, 0.0, 1.0, 1.0).ease(0.9), 0.9);
     }
 
+    #[test]
+    fn test_partial() {
+        assert_eq!(Easing::Linear.partial(0.0, 1.0), Easing::Linear);
+
+        let easing = Easing::EaseInQuad.partial(0.5, 1.0);
+        assert_eq!(easing, Easing::Partial(EasingKind::EaseInQuad, 0.5, 1.0));
+        assert_eq!(easing.ease(0.0), 0.0);
+        assert_eq!(easing.ease(1.0), 1.0);
+        // (0.75^2 - 0.5^2) / (1.0^2 - 0.5^2)
+        assert!((easing.ease(0.5) - 0.4166667).abs() < 1e-6);
+
+        assert!((Easing::Linear.partial(0.2, 0.8).ease(0.5) - 0.5).abs() < 1e-6);
+
+        let nested = Easing::EaseInQuad.partial(0.25, 0.75).partial(0.5, 1.0);
+        assert_eq!(nested, Easing::Partial(EasingKind::EaseInQuad, 0.5, 0.75));
+    }
+
+    #[test]
+    fn test_partial_custom() {
+        let custom = Easing::Custom(0.42, 0.0, 0.58, 1.0);
+        let (left, right) = (0.25, 0.75);
+        let easing = custom.partial(left, right);
+        assert!(matches!(easing, Easing::Custom(..)));
+
+        let (start, end) = (custom.ease(left), custom.ease(right));
+        for i in 0..=10 {
+            let x = i as f32 / 10.0;
+            let expected = (custom.ease(left + (right - left) * x) - start) / (end - start);
+            assert!((easing.ease(x) - expected).abs() < 1e-3, "at {}", x);
+        }
+
+        // an empty part of the curve is linear
+        assert_eq!(custom.partial(0.5, 0.5), Easing::Linear);
+    }
+
+    #[test]
+    fn test_partial_serialization() {
+        let easing = Easing::EaseOutSine.partial(0.25, 0.75);
+        let string = serde_json::to_string(&easing).unwrap();
+        assert_eq!(string, "{\"partial\":[\"ease_out_sine\",0.25,0.75]}");
+        assert_eq!(serde_json::from_str::<Easing>(&string).unwrap(), easing);
+    }
+
     #[test]
     fn test_tween() {
         assert_eq!(0.0.ease_to(1.0, 0.5, Easing::Linear), 0.5);
@@ -1,13 +1,27 @@
 use crate::easing::{Easing, Tween};
+use crate::expression::{Expression, Variables};
+use internment::ArcIntern;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use strum::{EnumCount, EnumIter};
 
 use crate::beat::Beat;
+use crate::id::Id;
 use crate::primitive;
 
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, IntoPrimitive, TryFromPrimitive,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    IntoPrimitive,
+    TryFromPrimitive,
+    EnumCount,
+    EnumIter,
 )]
 #[serde(rename_all = "snake_case")]
 #[repr(u8)]
@@ -17,6 +31,12 @@ pub enum LineEventKind {
     Rotation,
     Opacity,
     Speed,
+    ScaleX,
+    ScaleY,
+    /// The value of color events is an RGB color packed by [`pack_color`]
+    Color,
+    /// Text events use [`LineEventValue::Text`] as value, the line is rendered as the text instead of a texture
+    Text,
 }
 
 impl LineEventKind {
@@ -39,9 +59,43 @@ impl LineEventKind {
     pub fn is_speed(&self) -> bool {
         matches!(self, LineEventKind::Speed)
     }
+
+    pub fn is_scale_x(&self) -> bool {
+        matches!(self, LineEventKind::ScaleX)
+    }
+
+    pub fn is_scale_y(&self) -> bool {
+        matches!(self, LineEventKind::ScaleY)
+    }
+
+    pub fn is_color(&self) -> bool {
+        matches!(self, LineEventKind::Color)
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self, LineEventKind::Text)
+    }
+}
+
+/// Pack an RGB color into a single [`f32`] as the value of [`LineEventKind::Color`] events
+///
+/// The color is packed as `0xRRGGBB`, which fits in the mantissa of an [`f32`] without precision loss
+pub fn pack_color([r, g, b]: [u8; 3]) -> f32 {
+    u32::from_be_bytes([0, r, g, b]) as f32
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Unpack an RGB color packed by [`pack_color`]
+///
+/// ```rust
+/// use phichain_chart::event::{pack_color, unpack_color};
+/// assert_eq!(unpack_color(pack_color([12, 34, 56])), [12, 34, 56]);
+/// ```
+pub fn unpack_color(value: f32) -> [u8; 3] {
+    let [_, r, g, b] = (value.round().clamp(0.0, 0xFFFFFF as f32) as u32).to_be_bytes();
+    [r, g, b]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LineEventValue {
     Transition {
@@ -50,6 +104,14 @@ pub enum LineEventValue {
         easing: Easing,
     },
     Constant(f32),
+    /// The value of [`LineEventKind::Text`] events
+    ///
+    /// Texts are interned to keep events cheap to clone, and freed once no event refers to them
+    Text(ArcIntern<String>),
+    /// A math expression over the local time of the event, see [`crate::expression`]
+    ///
+    /// Expressions are interned like texts, they are baked into transitions when compiling a chart
+    Expression(ArcIntern<Expression>),
 }
 
 impl LineEventValue {
@@ -61,14 +123,24 @@ impl LineEventValue {
         Self::Constant(value)
     }
 
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(ArcIntern::new(text.into()))
+    }
+
+    pub fn expression(expression: Expression) -> Self {
+        Self::Expression(ArcIntern::new(expression))
+    }
+
     pub fn negated(&self) -> Self {
-        match *self {
-            LineEventValue::Transition { start, end, easing } => LineEventValue::Transition {
+        match self {
+            &LineEventValue::Transition { start, end, easing } => LineEventValue::Transition {
                 start: -start,
                 end: -end,
                 easing,
             },
-            LineEventValue::Constant(value) => LineEventValue::Constant(-value),
+            &LineEventValue::Constant(value) => LineEventValue::Constant(-value),
+            LineEventValue::Text(_) => self.clone(),
+            LineEventValue::Expression(expression) => Self::expression(expression.negated()),
         }
     }
 
@@ -80,42 +152,85 @@ impl LineEventValue {
         matches!(self, LineEventValue::Constant(_))
     }
 
+    pub fn is_text(&self) -> bool {
+        matches!(self, LineEventValue::Text(_))
+    }
+
+    pub fn is_expression(&self) -> bool {
+        matches!(self, LineEventValue::Expression(_))
+    }
+
+    /// Get the start value, texts have no numeric value and always return `0.0`
+    ///
+    /// Values of expressions depend on the timing of the event and also return `0.0`, use [`LineEvent::start_value`] instead
     pub fn start(&self) -> f32 {
         match self {
             LineEventValue::Transition { start, .. } => *start,
             LineEventValue::Constant(value) => *value,
+            LineEventValue::Text(_) | LineEventValue::Expression(_) => 0.0,
         }
     }
 
+    /// Get the end value, texts have no numeric value and always return `0.0`
+    ///
+    /// Values of expressions depend on the timing of the event and also return `0.0`, use [`LineEvent::end_value`] instead
     pub fn end(&self) -> f32 {
         match self {
             LineEventValue::Transition { end, .. } => *end,
             LineEventValue::Constant(value) => *value,
+            LineEventValue::Text(_) | LineEventValue::Expression(_) => 0.0,
+        }
+    }
+
+    /// Get the text of a [`LineEventValue::Text`]
+    pub fn text_value(&self) -> Option<&str> {
+        match self {
+            LineEventValue::Text(text) => Some(text.as_str()),
+            _ => None,
         }
     }
 
     pub fn into_constant(self) -> Self {
         match self {
             LineEventValue::Transition { start, .. } => Self::constant(start),
-            LineEventValue::Constant(_) => self,
+            LineEventValue::Constant(_)
+            | LineEventValue::Text(_)
+            | LineEventValue::Expression(_) => self,
         }
     }
 
+    /// Convert into an expression, transitions are converted into linear interpolations of their values
+    pub fn into_expression(self) -> Self {
+        let source = match self {
+            LineEventValue::Transition { start, end, .. } => {
+                format!("lerp({}, {}, t)", start, end)
+            }
+            LineEventValue::Constant(value) => value.to_string(),
+            LineEventValue::Text(_) | LineEventValue::Expression(_) => return self,
+        };
+        Self::expression(Expression::parse(&source).expect("generated expressions are valid"))
+    }
+
     pub fn into_transition(self) -> Self {
         match self {
-            LineEventValue::Transition { .. } => self,
+            LineEventValue::Transition { .. }
+            | LineEventValue::Text(_)
+            | LineEventValue::Expression(_) => self,
             LineEventValue::Constant(value) => Self::transition(value, value, Easing::Linear),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
 pub struct LineEvent {
     pub kind: LineEventKind,
     pub start_beat: Beat,
     pub end_beat: Beat,
     pub value: LineEventValue,
+    /// Persistent identifier of the event, see [`Id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Id>,
 }
 
 impl PartialOrd for LineEvent {
@@ -214,14 +329,37 @@ impl EventEvaluationResult {
 }
 
 impl LineEvent {
+    /// Interpolate between two values of this event
+    ///
+    /// Colors are interpolated channel by channel
+    fn interpolate(&self, start: f32, end: f32, percent: f32, easing: Easing) -> f32 {
+        if self.kind.is_color() {
+            let start = unpack_color(start);
+            let end = unpack_color(end);
+            let mut color = [0; 3];
+            for i in 0..3 {
+                color[i] = start[i]
+                    .ease_to(end[i], percent, easing)
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+            pack_color(color)
+        } else {
+            start.ease_to(end, percent, easing)
+        }
+    }
+
+    /// Evaluate the event at the given beat
+    ///
+    /// Text events evaluate to `0.0`, use [`LineEventValue::text_value`] to get the text of an affecting or inherited event
     pub fn evaluate(&self, beat: f32) -> EventEvaluationResult {
         let start_beat = self.start_beat.value();
         let end_beat = self.end_beat.value();
-        match self.value {
-            LineEventValue::Transition { start, end, easing } => {
+        match &self.value {
+            &LineEventValue::Transition { start, end, easing } => {
                 if beat >= start_beat && beat <= end_beat {
                     let percent = (beat - start_beat) / (end_beat - start_beat);
-                    EventEvaluationResult::Affecting(start.ease_to(end, percent, easing))
+                    EventEvaluationResult::Affecting(self.interpolate(start, end, percent, easing))
                 } else if beat > end_beat {
                     EventEvaluationResult::Inherited {
                         from: self.end_beat,
@@ -231,7 +369,7 @@ impl LineEvent {
                     EventEvaluationResult::Unaffected
                 }
             }
-            LineEventValue::Constant(value) => {
+            &LineEventValue::Constant(value) => {
                 if beat >= start_beat && beat <= end_beat {
                     EventEvaluationResult::Affecting(value)
                 } else if beat > end_beat {
@@ -243,17 +381,41 @@ impl LineEvent {
                     EventEvaluationResult::Unaffected
                 }
             }
+            LineEventValue::Text(_) => {
+                if beat >= start_beat && beat <= end_beat {
+                    EventEvaluationResult::Affecting(0.0)
+                } else if beat > end_beat {
+                    EventEvaluationResult::Inherited {
+                        from: self.end_beat,
+                        value: 0.0,
+                    }
+                } else {
+                    EventEvaluationResult::Unaffected
+                }
+            }
+            LineEventValue::Expression(expression) => {
+                if beat >= start_beat && beat <= end_beat {
+                    EventEvaluationResult::Affecting(self.evaluate_expression(expression, beat))
+                } else if beat > end_beat {
+                    EventEvaluationResult::Inherited {
+                        from: self.end_beat,
+                        value: self.evaluate_expression(expression, end_beat),
+                    }
+                } else {
+                    EventEvaluationResult::Unaffected
+                }
+            }
         }
     }
 
     pub fn evaluate_start_no_effect(&self, beat: f32) -> EventEvaluationResult {
         let start_beat = self.start_beat.value();
         let end_beat = self.end_beat.value();
-        match self.value {
-            LineEventValue::Transition { start, end, easing } => {
+        match &self.value {
+            &LineEventValue::Transition { start, end, easing } => {
                 if beat > start_beat && beat <= end_beat {
                     let percent = (beat - start_beat) / (end_beat - start_beat);
-                    EventEvaluationResult::Affecting(start.ease_to(end, percent, easing))
+                    EventEvaluationResult::Affecting(self.interpolate(start, end, percent, easing))
                 } else if beat > end_beat {
                     EventEvaluationResult::Inherited {
                         from: self.end_beat,
@@ -263,7 +425,7 @@ impl LineEvent {
                     EventEvaluationResult::Unaffected
                 }
             }
-            LineEventValue::Constant(value) => {
+            &LineEventValue::Constant(value) => {
                 if beat > start_beat && beat <= end_beat {
                     EventEvaluationResult::Affecting(value)
                 } else if beat > end_beat {
@@ -275,6 +437,59 @@ impl LineEvent {
                     EventEvaluationResult::Unaffected
                 }
             }
+            LineEventValue::Text(_) => {
+                if beat > start_beat && beat <= end_beat {
+                    EventEvaluationResult::Affecting(0.0)
+                } else if beat > end_beat {
+                    EventEvaluationResult::Inherited {
+                        from: self.end_beat,
+                        value: 0.0,
+                    }
+                } else {
+                    EventEvaluationResult::Unaffected
+                }
+            }
+            LineEventValue::Expression(expression) => {
+                if beat > start_beat && beat <= end_beat {
+                    EventEvaluationResult::Affecting(self.evaluate_expression(expression, beat))
+                } else if beat > end_beat {
+                    EventEvaluationResult::Inherited {
+                        from: self.end_beat,
+                        value: self.evaluate_expression(expression, end_beat),
+                    }
+                } else {
+                    EventEvaluationResult::Unaffected
+                }
+            }
+        }
+    }
+
+    /// Evaluate an expression of this event at the given beat
+    fn evaluate_expression(&self, expression: &Expression, beat: f32) -> f32 {
+        let start_beat = self.start_beat.value();
+        expression.evaluate(&Variables {
+            beat: beat - start_beat,
+            duration: self.end_beat.value() - start_beat,
+        })
+    }
+
+    /// The value of this event at its start beat, texts evaluate to `0.0`
+    pub fn start_value(&self) -> f32 {
+        match &self.value {
+            LineEventValue::Expression(expression) => {
+                self.evaluate_expression(expression, self.start_beat.value())
+            }
+            value => value.start(),
+        }
+    }
+
+    /// The value of this event at its end beat, texts evaluate to `0.0`
+    pub fn end_value(&self) -> f32 {
+        match &self.value {
+            LineEventValue::Expression(expression) => {
+                self.evaluate_expression(expression, self.end_beat.value())
+            }
+            value => value.end(),
         }
     }
 }
@@ -289,6 +504,8 @@ impl From<LineEvent> for primitive::event::LineEvent {
                 start,
                 end,
                 easing,
+                text: None,
+                id: event.id,
             },
             LineEventValue::Constant(value) => Self {
                 kind: event.kind,
@@ -297,6 +514,29 @@ impl From<LineEvent> for primitive::event::LineEvent {
                 start: value,
                 end: value,
                 easing: Easing::Linear,
+                text: None,
+                id: event.id,
+            },
+            LineEventValue::Text(text) => Self {
+                kind: event.kind,
+                start_beat: event.start_beat,
+                end_beat: event.end_beat,
+                start: 0.0,
+                end: 0.0,
+                easing: Easing::Linear,
+                text: Some(text),
+                id: event.id,
+            },
+            // expressions are baked when compiling, a leftover one only keeps its start and end values
+            LineEventValue::Expression(_) => Self {
+                kind: event.kind,
+                start_beat: event.start_beat,
+                end_beat: event.end_beat,
+                start: event.start_value(),
+                end: event.end_value(),
+                easing: Easing::Linear,
+                text: None,
+                id: event.id,
             },
         }
     }
@@ -308,7 +548,11 @@ impl From<primitive::event::LineEvent> for LineEvent {
             kind: event.kind,
             start_beat: event.start_beat,
             end_beat: event.end_beat,
-            value: LineEventValue::transition(event.start, event.end, event.easing),
+            value: match event.text {
+                Some(text) => LineEventValue::Text(text),
+                None => LineEventValue::transition(event.start, event.end, event.easing),
+            },
+            id: event.id,
         }
     }
 }
@@ -0,0 +1,657 @@
+//! Math expressions used as the value of [`LineEventValue::Expression`] events
+//!
+//! An expression is evaluated over the local time of its event, with the variables:
+//!
+//! - `beat`: beats since the start of the event
+//! - `progress`: progress of the event, from `0` at the start beat to `1` at the end beat
+//! - `duration`: length of the event in beats
+//!
+//! Constants `pi`, `tau` and `e`, operators `+`, `-`, `*`, `/`, `%`, `^` and the functions in [`Function`] are supported,
+//! e.g. `sin(progress * tau) * 100` is a full sine wave with an amplitude of 100
+//!
+//! [`LineEventValue::Expression`]: crate::event::LineEventValue::Expression
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{E, PI, TAU};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use strum::{EnumIter, IntoEnumIterator};
+
+/// Values of the variables an [`Expression`] is evaluated with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Variables {
+    /// Beats since the start of the event
+    pub beat: f32,
+    /// Length of the event in beats
+    pub duration: f32,
+}
+
+impl Variables {
+    /// Progress of the event from `0` to `1`, an event without length is always finished
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.beat / self.duration
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Round,
+    Exp,
+    Ln,
+    Pow,
+    Min,
+    Max,
+    /// `clamp(x, min, max)`
+    Clamp,
+    /// `lerp(a, b, t)`
+    Lerp,
+}
+
+impl Function {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Asin => "asin",
+            Function::Acos => "acos",
+            Function::Atan => "atan",
+            Function::Atan2 => "atan2",
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+            Function::Sign => "sign",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Round => "round",
+            Function::Exp => "exp",
+            Function::Ln => "ln",
+            Function::Pow => "pow",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Clamp => "clamp",
+            Function::Lerp => "lerp",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::Atan2 | Function::Pow | Function::Min | Function::Max => 2,
+            Function::Clamp | Function::Lerp => 3,
+            _ => 1,
+        }
+    }
+
+    fn call(&self, args: &[f32]) -> f32 {
+        match (self, args) {
+            (Function::Sin, [x]) => x.sin(),
+            (Function::Cos, [x]) => x.cos(),
+            (Function::Tan, [x]) => x.tan(),
+            (Function::Asin, [x]) => x.asin(),
+            (Function::Acos, [x]) => x.acos(),
+            (Function::Atan, [x]) => x.atan(),
+            (Function::Atan2, [y, x]) => y.atan2(*x),
+            (Function::Sqrt, [x]) => x.sqrt(),
+            (Function::Abs, [x]) => x.abs(),
+            (Function::Sign, [x]) => {
+                if *x == 0.0 {
+                    0.0
+                } else {
+                    x.signum()
+                }
+            }
+            (Function::Floor, [x]) => x.floor(),
+            (Function::Ceil, [x]) => x.ceil(),
+            (Function::Round, [x]) => x.round(),
+            (Function::Exp, [x]) => x.exp(),
+            (Function::Ln, [x]) => x.ln(),
+            (Function::Pow, [x, y]) => x.powf(*y),
+            (Function::Min, [x, y]) => x.min(*y),
+            (Function::Max, [x, y]) => x.max(*y),
+            (Function::Clamp, [x, min, max]) => x.max(*min).min(*max),
+            (Function::Lerp, [a, b, t]) => a + (b - a) * t,
+            _ => unreachable!("arity is checked when parsing"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    Beat,
+    Progress,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f32),
+    Variable(Variable),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    /// Replace the variables with the ones of a part of the event starting `offset` beats after its start
+    fn clip(&self, offset: f32, duration: f32) -> Node {
+        let beat = || {
+            Node::Binary(
+                Operator::Add,
+                Box::new(Node::Variable(Variable::Beat)),
+                Box::new(Node::Number(offset)),
+            )
+        };
+        match self {
+            Node::Number(_) => self.clone(),
+            Node::Variable(Variable::Beat) => beat(),
+            Node::Variable(Variable::Progress) if duration > 0.0 => Node::Binary(
+                Operator::Div,
+                Box::new(beat()),
+                Box::new(Node::Number(duration)),
+            ),
+            Node::Variable(Variable::Progress) => Node::Number(1.0),
+            Node::Variable(Variable::Duration) => Node::Number(duration),
+            Node::Negate(node) => Node::Negate(Box::new(node.clip(offset, duration))),
+            Node::Binary(operator, lhs, rhs) => Node::Binary(
+                *operator,
+                Box::new(lhs.clip(offset, duration)),
+                Box::new(rhs.clip(offset, duration)),
+            ),
+            Node::Call(function, args) => Node::Call(
+                *function,
+                args.iter().map(|arg| arg.clip(offset, duration)).collect(),
+            ),
+        }
+    }
+
+    fn evaluate(&self, variables: &Variables) -> f32 {
+        match self {
+            Node::Number(value) => *value,
+            Node::Variable(Variable::Beat) => variables.beat,
+            Node::Variable(Variable::Progress) => variables.progress(),
+            Node::Variable(Variable::Duration) => variables.duration,
+            Node::Negate(node) => -node.evaluate(variables),
+            Node::Binary(operator, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(variables), rhs.evaluate(variables));
+                match operator {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Rem => lhs.rem_euclid(rhs),
+                    Operator::Pow => lhs.powf(rhs),
+                }
+            }
+            Node::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(variables))
+                    .collect::<Vec<_>>();
+                function.call(&args)
+            }
+        }
+    }
+}
+
+/// Print the node back into a source, every operation is parenthesized
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Number(value) if *value < 0.0 => write!(f, "(-{})", -value),
+            Node::Number(value) => write!(f, "{}", value),
+            Node::Variable(Variable::Beat) => f.write_str("beat"),
+            Node::Variable(Variable::Progress) => f.write_str("progress"),
+            Node::Variable(Variable::Duration) => f.write_str("duration"),
+            Node::Negate(node) => write!(f, "(-{})", node),
+            Node::Binary(operator, lhs, rhs) => {
+                let operator = match operator {
+                    Operator::Add => "+",
+                    Operator::Sub => "-",
+                    Operator::Mul => "*",
+                    Operator::Div => "/",
+                    Operator::Rem => "%",
+                    Operator::Pow => "^",
+                };
+                write!(f, "({} {} {})", lhs, operator, rhs)
+            }
+            Node::Call(function, args) => {
+                write!(f, "{}(", function.name())?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(usize, usize),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<(usize, Token)>> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'0'..=b'9' | b'.' => {
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let number = &source[start..i];
+                Token::Number(
+                    number
+                        .parse()
+                        .with_context(|| format!("Invalid number `{}` at {}", number, start))?,
+                )
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                Token::Identifier(start, i)
+            }
+            c => {
+                i += 1;
+                match c {
+                    b'+' => Token::Operator(Operator::Add),
+                    b'-' => Token::Operator(Operator::Sub),
+                    b'*' => Token::Operator(Operator::Mul),
+                    b'/' => Token::Operator(Operator::Div),
+                    b'%' => Token::Operator(Operator::Rem),
+                    b'^' => Token::Operator(Operator::Pow),
+                    b'(' => Token::LeftParen,
+                    b')' => Token::RightParen,
+                    b',' => Token::Comma,
+                    _ => bail!(
+                        "Unexpected character `{}` at {}",
+                        source[start..].chars().next().unwrap_or_default(),
+                        start
+                    ),
+                }
+            }
+        };
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, from the lowest precedence to the highest:
+///
+/// - `+`, `-`
+/// - `*`, `/`, `%`
+/// - unary `-`
+/// - `^`, right associative
+/// - numbers, variables, constants, function calls and parentheses
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.position).map(|(_, token)| *token)
+    }
+
+    fn next(&mut self) -> anyhow::Result<(usize, Token)> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .copied()
+            .context("Unexpected end of expression")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token, description: &str) -> anyhow::Result<()> {
+        let (at, token) = self.next()?;
+        if token != expected {
+            bail!("Expected {} at {}", description, at);
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.term()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Sub))) = self.peek() {
+            self.position += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.unary()?;
+        while let Some(Token::Operator(
+            operator @ (Operator::Mul | Operator::Div | Operator::Rem),
+        )) = self.peek()
+        {
+            self.position += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Node> {
+        if let Some(Token::Operator(Operator::Sub)) = self.peek() {
+            self.position += 1;
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> anyhow::Result<Node> {
+        let base = self.atom()?;
+        if let Some(Token::Operator(Operator::Pow)) = self.peek() {
+            self.position += 1;
+            // the exponent may be negated, e.g. `2 ^ -1`
+            return Ok(Node::Binary(
+                Operator::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> anyhow::Result<Node> {
+        let (at, token) = self.next()?;
+        match token {
+            Token::Number(value) => Ok(Node::Number(value)),
+            Token::LeftParen => {
+                let node = self.expression()?;
+                self.expect(Token::RightParen, "`)`")?;
+                Ok(node)
+            }
+            Token::Identifier(start, end) => {
+                let name = &self.source[start..end];
+                if let Some(Token::LeftParen) = self.peek() {
+                    self.position += 1;
+                    return self.call(name, at);
+                }
+                match name {
+                    "beat" => Ok(Node::Variable(Variable::Beat)),
+                    "progress" => Ok(Node::Variable(Variable::Progress)),
+                    "duration" => Ok(Node::Variable(Variable::Duration)),
+                    "pi" => Ok(Node::Number(PI)),
+                    "tau" => Ok(Node::Number(TAU)),
+                    "e" => Ok(Node::Number(E)),
+                    _ => bail!("Unknown variable `{}` at {}", name, at),
+                }
+            }
+            _ => bail!("Unexpected token at {}", at),
+        }
+    }
+
+    fn call(&mut self, name: &str, at: usize) -> anyhow::Result<Node> {
+        let function = Function::iter()
+            .find(|function| function.name() == name)
+            .with_context(|| format!("Unknown function `{}` at {}", name, at))?;
+
+        let mut args = vec![];
+        if self.peek() != Some(Token::RightParen) {
+            args.push(self.expression()?);
+            while self.peek() == Some(Token::Comma) {
+                self.position += 1;
+                args.push(self.expression()?);
+            }
+        }
+        self.expect(Token::RightParen, "`)`")?;
+
+        if args.len() != function.arity() {
+            bail!(
+                "Function `{}` at {} takes {} arguments but {} are given",
+                name,
+                at,
+                function.arity(),
+                args.len()
+            );
+        }
+
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// A parsed math expression, compared and hashed by its source
+#[derive(Clone)]
+pub struct Expression {
+    source: String,
+    node: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let node = parser.expression()?;
+        if let Some((at, _)) = parser.tokens.get(parser.position) {
+            bail!("Unexpected token at {}", at);
+        }
+
+        Ok(Self {
+            source: source.to_owned(),
+            node,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The negation of this expression, e.g. for rotations of formats with the opposite direction
+    pub fn negated(&self) -> Self {
+        Self {
+            source: format!("-({})", self.source),
+            node: Node::Negate(Box::new(self.node.clone())),
+        }
+    }
+
+    /// The expression of a part of the event, starting `offset` beats after the start of the event
+    ///
+    /// Evaluating it over the part gives the same values as evaluating this expression over the whole event of `duration` beats
+    pub fn clipped(&self, offset: f32, duration: f32) -> Self {
+        let node = self.node.clip(offset, duration);
+        Self {
+            source: node.to_string(),
+            node,
+        }
+    }
+
+    /// Evaluate the expression, invalid results (e.g. `sqrt(-1)`) evaluate to `0.0`
+    pub fn evaluate(&self, variables: &Variables) -> f32 {
+        let value = self.node.evaluate(variables);
+        if value.is_finite() {
+            value
+        } else {
+            0.0
+        }
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Expression {}
+
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+    }
+}
+
+impl Debug for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Expression").field(&self.source).finish()
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Self::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str, beat: f32) -> f32 {
+        Expression::parse(source).unwrap().evaluate(&Variables {
+            beat,
+            duration: 4.0,
+        })
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3", 0.0), 9.0);
+        assert_eq!(evaluate("-2 ^ 2", 0.0), -4.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2", 0.0), 512.0);
+        assert_eq!(evaluate("2 ^ -1", 0.0), 0.5);
+        assert_eq!(evaluate("-5 % 3", 0.0), 1.0);
+        assert_eq!(evaluate("beat * 10", 2.0), 20.0);
+        assert_eq!(evaluate("progress", 1.0), 0.25);
+        assert_eq!(evaluate("progress * duration", 3.0), 3.0);
+        assert_eq!(evaluate("max(1, min(beat, 3))", 5.0), 3.0);
+        assert_eq!(evaluate("lerp(100, 200, progress)", 2.0), 150.0);
+        assert!((evaluate("sin(progress * tau) * 100", 1.0) - 100.0).abs() < 1e-3);
+        assert_eq!(evaluate("sqrt(-1)", 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_event() {
+        use crate::beat;
+        use crate::event::{LineEvent, LineEventKind, LineEventValue};
+
+        let event = LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(2),
+            end_beat: beat!(4),
+            value: LineEventValue::expression(Expression::parse("beat * 10 + progress").unwrap()),
+            id: None,
+        };
+        assert_eq!(event.evaluate(1.0).value(), None);
+        assert_eq!(event.evaluate(3.0).value(), Some(10.5));
+        assert_eq!(event.evaluate(5.0).value(), Some(21.0));
+        assert_eq!(event.start_value(), 0.0);
+        assert_eq!(event.end_value(), 21.0);
+    }
+
+    #[test]
+    fn test_clipped() {
+        let expression = Expression::parse("sin(progress * tau) * 100 + beat - duration").unwrap();
+        let clipped = expression.clipped(1.0, 4.0);
+        // the printed source parses into the same expression
+        assert_eq!(
+            Expression::parse(clipped.source()).unwrap().node,
+            clipped.node
+        );
+
+        for i in 0..=20 {
+            let beat = i as f32 / 10.0;
+            let original = expression.evaluate(&Variables {
+                beat: beat + 1.0,
+                duration: 4.0,
+            });
+            let part = clipped.evaluate(&Variables {
+                beat,
+                duration: 2.0,
+            });
+            assert!((original - part).abs() < 1e-3, "{} at {}", clipped, beat);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        for source in [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 2",
+            "foo",
+            "t",
+            "sin(1, 2)",
+            "unknown(1)",
+            "1 $ 2",
+            "1..2",
+        ] {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let expression = Expression::parse("cos(progress * pi)").unwrap();
+        let json = serde_json::to_string(&expression).unwrap();
+        assert_eq!(json, "\"cos(progress * pi)\"");
+        assert_eq!(
+            serde_json::from_str::<Expression>(&json).unwrap(),
+            expression
+        );
+        assert!(serde_json::from_str::<Expression>("\"cos(\"").is_err());
+    }
+}
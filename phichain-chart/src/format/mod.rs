@@ -1,2 +1,3 @@
 pub mod official;
+pub mod pec;
 pub mod rpe;
@@ -5,9 +5,12 @@ use crate::bpm_list::BpmList;
 use crate::constants::{CANVAS_HEIGHT, CANVAS_WIDTH};
 use crate::easing::Easing;
 use crate::event::LineEventKind;
+use crate::lint::Location;
 use crate::primitive::{Format, PrimitiveChart};
+use crate::report::{ConversionReport, LineProperty, LossKind, NoteProperty};
 use crate::{beat, primitive};
 use anyhow::bail;
+use num::Rational32;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
@@ -94,6 +97,119 @@ struct Line {
     notes_below: Vec<Note>,
 }
 
+/// Options of exporting a chart as official format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OfficialExportOptions {
+    /// Maximum error allowed when cutting non-linear events into linear events
+    ///
+    /// The error is measured in the unit of the event value: pixels for position, degrees for rotation and `0-255` for opacity
+    pub tolerance: f32,
+}
+
+impl Default for OfficialExportOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+}
+
+/// The default tolerance of [`OfficialExportOptions`]
+pub const DEFAULT_TOLERANCE: f32 = 0.5;
+
+/// Number of points sampled in a segment to measure its error
+const LINEARIZE_SAMPLES: u32 = 16;
+/// Maximum times an event can be subdivided, an event is cut into at most `2^LINEARIZE_MAX_DEPTH` segments
+const LINEARIZE_MAX_DEPTH: u32 = 10;
+
+/// Cut a non-linear event into linear events, the maximum error between them and the original curve does not exceed `tolerance`
+///
+/// The event is subdivided in half recursively until each segment is close enough to a straight line,
+/// so gentle curves end up in a few segments while sharp curves get more
+///
+/// Also returns whether the tolerance is met, curves too sharp to reach it within [`LINEARIZE_MAX_DEPTH`] subdivisions are cut into the finest segments possible
+fn linearize_event(
+    event: primitive::event::LineEvent,
+    tolerance: f32,
+) -> (Vec<primitive::event::LineEvent>, bool) {
+    if matches!(event.easing, Easing::Linear) || event.start_beat >= event.end_beat {
+        return (vec![event], true);
+    }
+
+    let curve = crate::event::LineEvent::from(event.clone());
+    let evaluate = |beat: f32| curve.evaluate(beat).value().unwrap_or(event.start);
+
+    fn subdivide(
+        event: &primitive::event::LineEvent,
+        evaluate: &impl Fn(f32) -> f32,
+        start_beat: Beat,
+        end_beat: Beat,
+        depth: u32,
+        tolerance: f32,
+        events: &mut Vec<primitive::event::LineEvent>,
+    ) -> bool {
+        let (from, to) = (start_beat.value(), end_beat.value());
+        let (start, end) = (evaluate(from), evaluate(to));
+
+        let error = (1..LINEARIZE_SAMPLES)
+            .map(|i| {
+                let percent = i as f32 / LINEARIZE_SAMPLES as f32;
+                let linear = start + (end - start) * percent;
+                (evaluate(from + (to - from) * percent) - linear).abs()
+            })
+            .fold(0.0, f32::max);
+
+        if error <= tolerance || depth >= LINEARIZE_MAX_DEPTH {
+            events.push(primitive::event::LineEvent {
+                kind: event.kind,
+                start,
+                end,
+                easing: Easing::Linear,
+                start_beat,
+                end_beat,
+                text: None,
+                id: None,
+            });
+            return error <= tolerance;
+        }
+
+        let middle = Beat::from((Rational32::from(start_beat) + Rational32::from(end_beat)) / 2);
+        let left = subdivide(
+            event,
+            evaluate,
+            start_beat,
+            middle,
+            depth + 1,
+            tolerance,
+            events,
+        );
+        let right = subdivide(
+            event,
+            evaluate,
+            middle,
+            end_beat,
+            depth + 1,
+            tolerance,
+            events,
+        );
+
+        left && right
+    }
+
+    let mut events = vec![];
+    let within_tolerance = subdivide(
+        &event,
+        &evaluate,
+        event.start_beat,
+        event.end_beat,
+        0,
+        tolerance,
+        &mut events,
+    );
+
+    (events, within_tolerance)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OfficialChart {
     #[serde(rename = "formatVersion")]
@@ -104,7 +220,10 @@ pub struct OfficialChart {
 }
 
 impl Format for OfficialChart {
-    fn into_primitive(self) -> anyhow::Result<PrimitiveChart> {
+    fn into_primitive_with_report(
+        self,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart> {
         if self.lines.is_empty() {
             bail!("Expect at least one line");
         }
@@ -121,9 +240,12 @@ impl Format for OfficialChart {
             bpm_list: BpmList::single(self.lines[0].bpm),
             ..Default::default()
         };
+        let bpm_list = primitive.bpm_list.clone();
 
         for line in self.lines {
-            let t: fn(f32) -> Beat = |x| Beat::from(x * 1.875 / 60.0);
+            // each line has its own bpm, convert times of the line into seconds first, then into beats on the bpm list of the chart
+            let bpm = line.bpm;
+            let t = |x: f32| bpm_list.beat_at(x * 1.875 / bpm);
             let x: fn(f32) -> f32 = |x| (x - 0.5) * CANVAS_WIDTH;
             let y: fn(f32) -> f32 = |x| (x - 0.5) * CANVAS_HEIGHT;
 
@@ -132,7 +254,7 @@ impl Format for OfficialChart {
                     NoteKind::Tap => crate::note::NoteKind::Tap,
                     NoteKind::Drag => crate::note::NoteKind::Drag,
                     NoteKind::Hold => crate::note::NoteKind::Hold {
-                        hold_beat: t(note.hold_time),
+                        hold_beat: t(note.time + note.hold_time) - t(note.time),
                     },
                     NoteKind::Flick => crate::note::NoteKind::Flick,
                 };
@@ -158,6 +280,8 @@ impl Format for OfficialChart {
                                 easing: Easing::Linear,
                                 start_beat: t(event.start_time),
                                 end_beat: t(event.end_time),
+                                text: None,
+                                id: None,
                             },
                             primitive::event::LineEvent {
                                 kind: LineEventKind::Y,
@@ -166,6 +290,8 @@ impl Format for OfficialChart {
                                 easing: Easing::Linear,
                                 start_beat: t(event.start_time),
                                 end_beat: t(event.end_time),
+                                text: None,
+                                id: None,
                             },
                         ]
                     }
@@ -178,6 +304,8 @@ impl Format for OfficialChart {
                                 easing: Easing::Linear,
                                 start_beat: t(event.start_time),
                                 end_beat: t(event.end_time),
+                                text: None,
+                                id: None,
                             },
                             primitive::event::LineEvent {
                                 kind: LineEventKind::Y,
@@ -186,6 +314,8 @@ impl Format for OfficialChart {
                                 easing: Easing::Linear,
                                 start_beat: t(event.start_time),
                                 end_beat: t(event.end_time),
+                                text: None,
+                                id: None,
                             },
                         ]
                     }
@@ -203,6 +333,8 @@ impl Format for OfficialChart {
                         easing: Easing::Linear,
                         start_beat: t(event.start_time),
                         end_beat: t(event.end_time),
+                        text: None,
+                        id: None,
                     });
 
             let opacity_event_iter =
@@ -215,6 +347,8 @@ impl Format for OfficialChart {
                         easing: Easing::Linear,
                         start_beat: t(event.start_time),
                         end_beat: t(event.end_time),
+                        text: None,
+                        id: None,
                     });
 
             let speed_event_iter =
@@ -227,9 +361,12 @@ impl Format for OfficialChart {
                         easing: Easing::Linear,
                         start_beat: t(event.start_time),
                         end_beat: t(event.end_time),
+                        text: None,
+                        id: None,
                     });
 
             let mut line = primitive::line::Line {
+                line: Default::default(),
                 notes: line
                     .notes_above
                     .iter()
@@ -241,6 +378,7 @@ impl Format for OfficialChart {
                     .chain(opacity_event_iter)
                     .chain(speed_event_iter)
                     .collect(),
+                children: vec![],
             };
 
             let mut speed_events = line
@@ -254,8 +392,8 @@ impl Format for OfficialChart {
                 if let crate::note::NoteKind::Hold { .. } = note.kind {
                     let mut speed = 0.0;
                     for event in &speed_events {
-                        let result =
-                            crate::event::LineEvent::from(**event).evaluate(note.beat.value());
+                        let result = crate::event::LineEvent::from((*event).clone())
+                            .evaluate(note.beat.value());
                         if let Some(value) = result.value() {
                             speed = value;
                         }
@@ -271,44 +409,24 @@ impl Format for OfficialChart {
         Ok(primitive)
     }
 
-    fn from_primitive(phichain: PrimitiveChart) -> anyhow::Result<Self>
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        fn cut_event(event: primitive::event::LineEvent) -> Vec<primitive::event::LineEvent> {
-            if matches!(event.easing, Easing::Linear) {
-                return vec![event];
-            }
-
-            let mut events = vec![];
-
-            let minimum = beat!(1, 32);
-
-            let mut current_beat = event.start_beat;
-
-            while current_beat <= event.end_beat {
-                let start_value = crate::event::LineEvent::from(event)
-                    .evaluate(current_beat.value())
-                    .value()
-                    .unwrap();
-                let end_value = crate::event::LineEvent::from(event)
-                    .evaluate(current_beat.value() + minimum.value())
-                    .value()
-                    .unwrap();
-                events.push(primitive::event::LineEvent {
-                    kind: event.kind,
-                    start: start_value,
-                    end: end_value,
-                    easing: Easing::Linear,
-                    start_beat: current_beat,
-                    end_beat: current_beat + minimum,
-                });
-                current_beat += minimum;
-            }
-
-            events
-        }
+        Self::from_primitive_with_options(primitive, OfficialExportOptions::default(), report)
+    }
+}
 
+impl OfficialChart {
+    /// Convert a [`PrimitiveChart`] into an [`OfficialChart`] with the given [`OfficialExportOptions`]
+    pub fn from_primitive_with_options(
+        phichain: PrimitiveChart,
+        options: OfficialExportOptions,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<Self> {
         let bpm = phichain.bpm_list.0[0].bpm; // take first bpm as base bpm for all lines, normalize all beats using this bpm
         let offset = phichain.offset / 1000.0;
 
@@ -320,7 +438,20 @@ impl Format for OfficialChart {
             lines: vec![],
         };
 
-        for line in phichain.lines {
+        let paths = primitive::line::flattened_paths(&phichain.lines);
+        for path in paths.iter().filter(|path| path.len() > 1) {
+            report.push(
+                Some(Location::Line { line: path.clone() }),
+                LossKind::LineHierarchy,
+            );
+        }
+
+        for (line, path) in phichain
+            .lines
+            .into_iter()
+            .flat_map(primitive::line::Line::flatten)
+            .zip(paths)
+        {
             let mut official_line = Line {
                 bpm,
                 move_events: vec![],
@@ -331,8 +462,93 @@ impl Format for OfficialChart {
                 notes_below: vec![],
             };
 
+            if let Some(texture) = &line.line.texture {
+                report.push(
+                    Some(Location::Line { line: path.clone() }),
+                    LossKind::DroppedTexture {
+                        texture: texture.clone(),
+                    },
+                );
+            }
+
+            for property in [
+                LineProperty::Name,
+                LineProperty::ZOrder,
+                LineProperty::HideNotesBelow,
+            ] {
+                if property.is_set(&line.line) {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedLineProperty { property },
+                    );
+                }
+            }
+
             // -------- Events --------
 
+            // scale, color and text events can not be represented in official format
+            for kind in [
+                LineEventKind::ScaleX,
+                LineEventKind::ScaleY,
+                LineEventKind::Color,
+                LineEventKind::Text,
+            ] {
+                let count = line.events.iter().filter(|e| e.kind == kind).count();
+                if count > 0 {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedEvents { kind, count },
+                    );
+                }
+            }
+
+            // speed events can only be set in official format
+            for (index, event) in line.events.iter().enumerate() {
+                if event.kind == LineEventKind::Speed && event.start != event.end {
+                    report.push(
+                        Some(Location::Event {
+                            line: path.clone(),
+                            index,
+                        }),
+                        LossKind::SteppedTransition { kind: event.kind },
+                    );
+                }
+            }
+
+            // official events are linear, non-linear events are cut into linear events within the tolerance,
+            // the easing is only reported as a loss when the curve is too sharp to reach the tolerance
+            fn linearize_events(
+                line: &primitive::line::Line,
+                kind: LineEventKind,
+                tolerance: f32,
+                path: &[usize],
+                report: &mut ConversionReport,
+            ) -> Vec<primitive::event::LineEvent> {
+                let mut events = vec![];
+                for (index, event) in line.events.iter().enumerate() {
+                    if event.kind != kind {
+                        continue;
+                    }
+                    let (mut linearized, within_tolerance) =
+                        linearize_event(event.clone(), tolerance);
+                    if !within_tolerance {
+                        report.push(
+                            Some(Location::Event {
+                                line: path.to_vec(),
+                                index,
+                            }),
+                            LossKind::UnsupportedEasing {
+                                kind,
+                                easing: event.easing,
+                            },
+                        );
+                    }
+                    events.append(&mut linearized);
+                }
+
+                events
+            }
+
             fn connect_events(
                 events: &[primitive::event::LineEvent],
             ) -> Vec<primitive::event::LineEvent> {
@@ -367,40 +583,36 @@ impl Format for OfficialChart {
                         easing: Easing::Linear,
                         start_beat,
                         end_beat,
+                        text: None,
+                        id: None,
                     })
                 }
 
                 connected_events
             }
 
+            // events are linearized before connecting, connected events are always linear and would lose the easing
             fn process_events<F, T>(
-                line: &primitive::line::Line,
-                kind: LineEventKind,
+                events: &[primitive::event::LineEvent],
                 mut transform: F,
                 target: &mut Vec<T>,
             ) where
                 F: FnMut(&primitive::event::LineEvent) -> T,
             {
-                let events = connect_events(
-                    &line
-                        .events
-                        .iter()
-                        .filter(|e| e.kind == kind)
-                        .copied()
-                        .collect::<Vec<_>>(),
-                );
+                let events = connect_events(events);
 
-                for event in events {
-                    let events = cut_event(event);
-                    let mut transformed_events =
-                        events.iter().map(&mut transform).collect::<Vec<_>>();
-                    target.append(&mut transformed_events);
-                }
+                let mut transformed_events = events.iter().map(&mut transform).collect::<Vec<_>>();
+                target.append(&mut transformed_events);
             }
 
             process_events(
-                &line,
-                LineEventKind::Rotation,
+                &linearize_events(
+                    &line,
+                    LineEventKind::Rotation,
+                    options.tolerance,
+                    &path,
+                    report,
+                ),
                 |e| NumericLineEvent {
                     start_time: time(e.start_beat),
                     end_time: time(e.end_beat),
@@ -411,8 +623,13 @@ impl Format for OfficialChart {
             );
 
             process_events(
-                &line,
-                LineEventKind::Opacity,
+                &linearize_events(
+                    &line,
+                    LineEventKind::Opacity,
+                    options.tolerance,
+                    &path,
+                    report,
+                ),
                 |e| NumericLineEvent {
                     start_time: time(e.start_beat),
                     end_time: time(e.end_beat),
@@ -423,8 +640,13 @@ impl Format for OfficialChart {
             );
 
             process_events(
-                &line,
-                LineEventKind::Speed,
+                &linearize_events(
+                    &line,
+                    LineEventKind::Speed,
+                    options.tolerance,
+                    &path,
+                    report,
+                ),
                 |e| SpeedEvent {
                     start_time: time(e.start_beat),
                     end_time: time(e.end_beat),
@@ -436,22 +658,10 @@ impl Format for OfficialChart {
 
             // -------- Move events --------
 
-            let mut x_events = vec![];
-            let mut y_events = vec![];
-
-            for event in &line.events {
-                match event.kind {
-                    LineEventKind::X => {
-                        let mut events = cut_event(*event);
-                        x_events.append(&mut events);
-                    }
-                    LineEventKind::Y => {
-                        let mut events = cut_event(*event);
-                        y_events.append(&mut events);
-                    }
-                    _ => {}
-                }
-            }
+            let mut x_events =
+                linearize_events(&line, LineEventKind::X, options.tolerance, &path, report);
+            let mut y_events =
+                linearize_events(&line, LineEventKind::Y, options.tolerance, &path, report);
 
             x_events.sort_by_key(|e| e.start_beat);
             y_events.sort_by_key(|e| e.start_beat);
@@ -464,9 +674,10 @@ impl Format for OfficialChart {
                 let mut ret = 0.0;
                 for event in events {
                     let result = if start_has_effect {
-                        crate::event::LineEvent::from(*event).evaluate(beat.value())
+                        crate::event::LineEvent::from(event.clone()).evaluate(beat.value())
                     } else {
-                        crate::event::LineEvent::from(*event).evaluate_start_no_effect(beat.value())
+                        crate::event::LineEvent::from(event.clone())
+                            .evaluate_start_no_effect(beat.value())
                     };
                     if let Some(value) = result.value() {
                         ret = value;
@@ -520,9 +731,38 @@ impl Format for OfficialChart {
                 .collect::<Vec<_>>();
             speed_events.sort_by_key(|e| e.start_beat);
 
-            let mut notes = line.notes.clone();
+            // fake notes can not be represented in official format
+            let fake_notes = line.notes.iter().filter(|n| n.fake).count();
+            if fake_notes > 0 {
+                report.push(
+                    Some(Location::Line { line: path.clone() }),
+                    LossKind::DroppedFakeNotes { count: fake_notes },
+                );
+            }
+
+            let mut notes = line
+                .notes
+                .iter()
+                .filter(|n| !n.fake)
+                .copied()
+                .collect::<Vec<_>>();
             notes.sort_by_key(|n| n.beat);
 
+            for property in [
+                NoteProperty::Alpha,
+                NoteProperty::Size,
+                NoteProperty::VisibleTime,
+                NoteProperty::YOffset,
+            ] {
+                let count = notes.iter().filter(|n| property.is_set(n)).count();
+                if count > 0 {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedNoteProperty { property, count },
+                    );
+                }
+            }
+
             for note in notes {
                 let kind = match note.kind {
                     crate::note::NoteKind::Tap => NoteKind::Tap,
@@ -535,8 +775,8 @@ impl Format for OfficialChart {
                 let speed = if matches!(note.kind, crate::note::NoteKind::Hold { .. }) {
                     let mut speed = 0.0;
                     for event in &speed_events {
-                        let result =
-                            crate::event::LineEvent::from(**event).evaluate(note.beat.value());
+                        let result = crate::event::LineEvent::from((*event).clone())
+                            .evaluate(note.beat.value());
                         if let Some(value) = result.value() {
                             speed = value;
                         }
@@ -629,3 +869,261 @@ impl Format for OfficialChart {
         Ok(chart)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // three lines with different BPMs, each with a hold note at 0.5s lasting 0.5s
+    const CHART: &str = r#"{
+  "formatVersion": 3,
+  "offset": 0.0,
+  "judgeLineList": [
+    {
+      "bpm": 120.0,
+      "judgeLineMoveEvents": [
+        { "startTime": 0.0, "endTime": 32.0, "start": 0.5, "end": 0.5, "start2": 0.5, "end2": 0.5 }
+      ],
+      "judgeLineRotateEvents": [],
+      "judgeLineDisappearEvents": [],
+      "speedEvents": [{ "startTime": 0.0, "endTime": 32.0, "value": 1.0 }],
+      "notesAbove": [
+        { "type": 3, "time": 32.0, "holdTime": 32.0, "positionX": 0.0, "speed": 1.0, "floorPosition": 0.0 }
+      ],
+      "notesBelow": []
+    },
+    {
+      "bpm": 240.0,
+      "judgeLineMoveEvents": [
+        { "startTime": 0.0, "endTime": 64.0, "start": 0.5, "end": 0.5, "start2": 0.5, "end2": 0.5 }
+      ],
+      "judgeLineRotateEvents": [],
+      "judgeLineDisappearEvents": [],
+      "speedEvents": [{ "startTime": 0.0, "endTime": 64.0, "value": 1.0 }],
+      "notesAbove": [
+        { "type": 3, "time": 64.0, "holdTime": 64.0, "positionX": 0.0, "speed": 1.0, "floorPosition": 0.0 }
+      ],
+      "notesBelow": []
+    },
+    {
+      "bpm": 60.0,
+      "judgeLineMoveEvents": [
+        { "startTime": 0.0, "endTime": 16.0, "start": 0.5, "end": 0.5, "start2": 0.5, "end2": 0.5 }
+      ],
+      "judgeLineRotateEvents": [],
+      "judgeLineDisappearEvents": [],
+      "speedEvents": [{ "startTime": 0.0, "endTime": 16.0, "value": 1.0 }],
+      "notesAbove": [
+        { "type": 3, "time": 16.0, "holdTime": 16.0, "positionX": 0.0, "speed": 1.0, "floorPosition": 0.0 }
+      ],
+      "notesBelow": []
+    }
+  ]
+}"#;
+
+    #[test]
+    fn test_per_line_bpm() {
+        let chart: OfficialChart = serde_json::from_str(CHART).unwrap();
+        let primitive = chart.into_primitive().unwrap();
+        assert_eq!(primitive.bpm_list.0.len(), 1);
+        assert_eq!(primitive.bpm_list.0[0].bpm, 120.0);
+
+        // every line places its note at 0.5s, which is beat 1 with 120 BPM
+        for line in &primitive.lines {
+            let note = &line.notes[0];
+            assert_eq!(note.beat, beat!(1));
+            assert_eq!(
+                note.kind,
+                crate::note::NoteKind::Hold {
+                    hold_beat: beat!(1)
+                }
+            );
+            assert!(line.events.iter().all(|e| e.end_beat == beat!(1)));
+        }
+    }
+
+    fn event(easing: Easing) -> primitive::event::LineEvent {
+        primitive::event::LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(0),
+            end_beat: beat!(4),
+            start: 0.0,
+            end: 100.0,
+            easing,
+            text: None,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_linearize_event() {
+        assert_eq!(linearize_event(event(Easing::Linear), 0.5).0.len(), 1);
+
+        for easing in [
+            Easing::EaseInOutSine,
+            Easing::EaseOutExpo,
+            Easing::EaseInOutBack,
+        ] {
+            let (events, within_tolerance) = linearize_event(event(easing), 0.5);
+            assert!(within_tolerance);
+            assert_eq!(events.first().unwrap().start_beat, beat!(0));
+            assert_eq!(events.last().unwrap().end_beat, beat!(4));
+            assert!(events.windows(2).all(|x| x[0].end_beat == x[1].start_beat));
+
+            // check the error between the linear events and the original curve
+            let original = crate::event::LineEvent::from(event(easing));
+            for i in 0..=400 {
+                let beat = i as f32 / 100.0;
+                let linear = events
+                    .iter()
+                    .find(|e| e.end_beat.value() >= beat)
+                    .map(|e| crate::event::LineEvent::from(e.clone()).evaluate(beat))
+                    .and_then(|x| x.value())
+                    .unwrap();
+                let expected = original.evaluate(beat).value().unwrap();
+                assert!((linear - expected).abs() < 0.6, "{:?} at {}", easing, beat);
+            }
+        }
+
+        // gentle curves need fewer segments than the fixed 1/32 beat slices
+        assert!(linearize_event(event(Easing::EaseInOutSine), 0.5).0.len() < 128);
+        // a tighter tolerance needs more segments
+        assert!(
+            linearize_event(event(Easing::EaseOutExpo), 0.05).0.len()
+                > linearize_event(event(Easing::EaseOutExpo), 0.5).0.len()
+        );
+        // a curve can not reach zero tolerance, it is cut into the finest segments possible
+        let (events, within_tolerance) = linearize_event(event(Easing::EaseOutExpo), 0.0);
+        assert!(!within_tolerance);
+        assert_eq!(events.len(), 1 << LINEARIZE_MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_linearize_rotation() {
+        let rotation = primitive::event::LineEvent {
+            kind: LineEventKind::Rotation,
+            end: 90.0,
+            ..event(Easing::EaseInOutSine)
+        };
+        let primitive = PrimitiveChart {
+            lines: vec![primitive::line::Line {
+                events: vec![rotation.clone()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut report = ConversionReport::default();
+        let official =
+            OfficialChart::from_primitive_with_options(primitive, Default::default(), &mut report)
+                .unwrap();
+
+        // the last event holds the final value until the end of the chart
+        let rotate_events = &official.lines[0].rotate_events;
+        let (segments, rest) = rotate_events.split_at(rotate_events.len() - 1);
+        assert!(segments.len() > 1);
+        assert_eq!(
+            segments
+                .iter()
+                .map(|e| (e.start, e.end))
+                .collect::<Vec<_>>(),
+            linearize_event(rotation, DEFAULT_TOLERANCE)
+                .0
+                .iter()
+                .map(|e| (e.start, e.end))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!((rest[0].start, rest[0].end), (90.0, 90.0));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut note = crate::note::Note::new(crate::note::NoteKind::Tap, true, beat!(1), 0.0, 1.0);
+        note.fake = true;
+        let mut transparent_note =
+            crate::note::Note::new(crate::note::NoteKind::Tap, true, beat!(2), 0.0, 1.0);
+        transparent_note.alpha = 128.0;
+        let child = primitive::line::Line {
+            line: crate::line::Line {
+                z_order: 1,
+                ..Default::default()
+            },
+            events: vec![primitive::event::LineEvent {
+                kind: LineEventKind::Color,
+                ..event(Easing::Linear)
+            }],
+            ..Default::default()
+        };
+        let primitive = PrimitiveChart {
+            lines: vec![primitive::line::Line {
+                notes: vec![note, transparent_note],
+                events: vec![event(Easing::Linear), event(Easing::EaseInSine)],
+                children: vec![child],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut report = ConversionReport::default();
+        OfficialChart::from_primitive_with_options(
+            primitive.clone(),
+            Default::default(),
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(
+            report
+                .losses
+                .iter()
+                .map(|loss| (loss.location.clone().unwrap(), loss.kind.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Location::Line { line: vec![0, 0] }, LossKind::LineHierarchy),
+                (
+                    Location::Line { line: vec![0] },
+                    LossKind::DroppedFakeNotes { count: 1 }
+                ),
+                (
+                    Location::Line { line: vec![0] },
+                    LossKind::DroppedNoteProperty {
+                        property: NoteProperty::Alpha,
+                        count: 1
+                    }
+                ),
+                (
+                    Location::Line { line: vec![0, 0] },
+                    LossKind::DroppedLineProperty {
+                        property: LineProperty::ZOrder
+                    }
+                ),
+                (
+                    Location::Line { line: vec![0, 0] },
+                    LossKind::DroppedEvents {
+                        kind: LineEventKind::Color,
+                        count: 1
+                    }
+                ),
+            ]
+        );
+        assert!(report.ensure_lossless().is_err());
+
+        // eased events are only reported when the tolerance can not be reached
+        let mut report = ConversionReport::default();
+        OfficialChart::from_primitive_with_options(
+            primitive,
+            OfficialExportOptions { tolerance: 0.0 },
+            &mut report,
+        )
+        .unwrap();
+        assert!(report.losses.iter().any(|loss| loss.location
+            == Some(Location::Event {
+                line: vec![0],
+                index: 1
+            })
+            && loss.kind
+                == LossKind::UnsupportedEasing {
+                    kind: LineEventKind::X,
+                    easing: Easing::EaseInSine
+                }));
+    }
+}
@@ -0,0 +1,1055 @@
+//! PhiEditer text format
+//!
+//! Unlike other formats, PEC charts are line-oriented text instead of json, use [`FromStr`] to parse and [`Display`] to emit them
+
+use crate::beat;
+use crate::beat::utils::attach;
+use crate::beat::Beat;
+use crate::bpm_list::{BpmList, BpmPoint, RAMP_SAMPLE_DENSITY};
+use crate::constants::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use crate::easing::Easing;
+use crate::event::LineEventKind;
+use crate::format::rpe::RPE_EASING;
+use crate::lint::Location;
+use crate::primitive;
+use crate::primitive::{Format, PrimitiveChart};
+use crate::report::{ConversionReport, LineProperty, LossKind, NoteProperty};
+use anyhow::{bail, Context};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Width of the PhiEditer canvas, line positions range in `[0, 2048]` and note positions range in `[-1024, 1024]`
+const PEC_WIDTH: f32 = 2048.0;
+/// Height of the PhiEditer canvas, line positions range in `[0, 1400]`
+const PEC_HEIGHT: f32 = 1400.0;
+/// PhiEditer delays every chart by 150ms, which is included in the offset
+const PEC_OFFSET_DELAY: f32 = 150.0;
+/// A speed of `5.85` in PhiEditer equals to a speed of `1.0` in the official format
+const PEC_SPEED_RATIO: f32 = 5.85;
+/// Beats are stored as decimals, they are attached to 1/192 beat when importing
+const PEC_BEAT_DENSITY: u32 = 192;
+/// Transitions PhiEditer can not represent are baked into 1/16 beat linear segments
+const BAKE_DENSITY: u32 = 16;
+
+/// The easing type id of `Linear`
+const LINEAR: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NoteKind {
+    Tap,
+    Hold { end_beat: f32 },
+    Flick,
+    Drag,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    /// `bp <beat> <bpm>`
+    Bpm { beat: f32, bpm: f32 },
+    /// `n1 <line> <beat> <x> <above> <fake>`, `n2 <line> <beat> <end beat> <x> <above> <fake>` for holds,
+    /// followed by `# <speed>` and `& <size>`
+    Note {
+        kind: NoteKind,
+        line: usize,
+        beat: f32,
+        x: f32,
+        above: bool,
+        fake: bool,
+        speed: f32,
+        size: f32,
+    },
+    /// `cv <line> <beat> <speed>`
+    Speed { line: usize, beat: f32, value: f32 },
+    /// `cp <line> <beat> <x> <y>`
+    Position {
+        line: usize,
+        beat: f32,
+        x: f32,
+        y: f32,
+    },
+    /// `cd <line> <beat> <degree>`
+    Rotation { line: usize, beat: f32, value: f32 },
+    /// `ca <line> <beat> <alpha>`
+    Alpha { line: usize, beat: f32, value: f32 },
+    /// `cm <line> <start beat> <end beat> <x> <y> <easing>`
+    Move {
+        line: usize,
+        start_beat: f32,
+        end_beat: f32,
+        x: f32,
+        y: f32,
+        easing: i32,
+    },
+    /// `cr <line> <start beat> <end beat> <degree> <easing>`
+    Rotate {
+        line: usize,
+        start_beat: f32,
+        end_beat: f32,
+        value: f32,
+        easing: i32,
+    },
+    /// `cf <line> <start beat> <end beat> <alpha>`
+    Fade {
+        line: usize,
+        start_beat: f32,
+        end_beat: f32,
+        value: f32,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PecChart {
+    /// Offset in milliseconds, including [`PEC_OFFSET_DELAY`]
+    offset: f32,
+    commands: Vec<Command>,
+}
+
+impl FromStr for PecChart {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+
+        fn next<T: FromStr>(tokens: &mut std::str::SplitWhitespace, name: &str) -> anyhow::Result<T>
+        where
+            T::Err: std::error::Error + Send + Sync + 'static,
+        {
+            tokens
+                .next()
+                .with_context(|| format!("Unexpected end of chart, expected {}", name))?
+                .parse()
+                .with_context(|| format!("Invalid {}", name))
+        }
+
+        let offset = next(&mut tokens, "offset")?;
+        let mut commands = vec![];
+
+        while let Some(command) = tokens.next() {
+            let tokens = &mut tokens;
+            let command = match command {
+                "bp" => Command::Bpm {
+                    beat: next(tokens, "beat")?,
+                    bpm: next(tokens, "bpm")?,
+                },
+                "n1" | "n2" | "n3" | "n4" => {
+                    let line = next(tokens, "line")?;
+                    let beat = next(tokens, "beat")?;
+                    let kind = match command {
+                        "n1" => NoteKind::Tap,
+                        "n2" => NoteKind::Hold {
+                            end_beat: next(tokens, "end beat")?,
+                        },
+                        "n3" => NoteKind::Flick,
+                        _ => NoteKind::Drag,
+                    };
+                    Command::Note {
+                        kind,
+                        line,
+                        beat,
+                        x: next(tokens, "x")?,
+                        above: next::<i32>(tokens, "above")? == 1,
+                        fake: next::<i32>(tokens, "fake")? == 1,
+                        speed: 1.0,
+                        size: 1.0,
+                    }
+                }
+                "#" | "&" => {
+                    let value = next(tokens, "value")?;
+                    match commands.last_mut() {
+                        Some(Command::Note { speed, size, .. }) => {
+                            if command == "#" {
+                                *speed = value;
+                            } else {
+                                *size = value;
+                            }
+                        }
+                        _ => bail!("`{}` must follow a note", command),
+                    }
+                    continue;
+                }
+                "cv" => Command::Speed {
+                    line: next(tokens, "line")?,
+                    beat: next(tokens, "beat")?,
+                    value: next(tokens, "speed")?,
+                },
+                "cp" => Command::Position {
+                    line: next(tokens, "line")?,
+                    beat: next(tokens, "beat")?,
+                    x: next(tokens, "x")?,
+                    y: next(tokens, "y")?,
+                },
+                "cd" => Command::Rotation {
+                    line: next(tokens, "line")?,
+                    beat: next(tokens, "beat")?,
+                    value: next(tokens, "degree")?,
+                },
+                "ca" => Command::Alpha {
+                    line: next(tokens, "line")?,
+                    beat: next(tokens, "beat")?,
+                    value: next(tokens, "alpha")?,
+                },
+                "cm" => Command::Move {
+                    line: next(tokens, "line")?,
+                    start_beat: next(tokens, "start beat")?,
+                    end_beat: next(tokens, "end beat")?,
+                    x: next(tokens, "x")?,
+                    y: next(tokens, "y")?,
+                    easing: next(tokens, "easing")?,
+                },
+                "cr" => Command::Rotate {
+                    line: next(tokens, "line")?,
+                    start_beat: next(tokens, "start beat")?,
+                    end_beat: next(tokens, "end beat")?,
+                    value: next(tokens, "degree")?,
+                    easing: next(tokens, "easing")?,
+                },
+                "cf" => Command::Fade {
+                    line: next(tokens, "line")?,
+                    start_beat: next(tokens, "start beat")?,
+                    end_beat: next(tokens, "end beat")?,
+                    value: next(tokens, "alpha")?,
+                },
+                _ => bail!("Unknown command `{}`", command),
+            };
+            commands.push(command);
+        }
+
+        Ok(Self { offset, commands })
+    }
+}
+
+impl Display for PecChart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.offset)?;
+        for command in &self.commands {
+            match *command {
+                Command::Bpm { beat, bpm } => writeln!(f, "bp {} {}", beat, bpm)?,
+                Command::Note {
+                    kind,
+                    line,
+                    beat,
+                    x,
+                    above,
+                    fake,
+                    speed,
+                    size,
+                } => {
+                    let (above, fake) = (if above { 1 } else { 2 }, i32::from(fake));
+                    match kind {
+                        NoteKind::Hold { end_beat } => writeln!(
+                            f,
+                            "n2 {} {} {} {} {} {}",
+                            line, beat, end_beat, x, above, fake
+                        )?,
+                        _ => {
+                            let id = match kind {
+                                NoteKind::Tap => 1,
+                                NoteKind::Flick => 3,
+                                _ => 4,
+                            };
+                            writeln!(f, "n{} {} {} {} {} {}", id, line, beat, x, above, fake)?
+                        }
+                    }
+                    writeln!(f, "# {}", speed)?;
+                    writeln!(f, "& {}", size)?;
+                }
+                Command::Speed { line, beat, value } => {
+                    writeln!(f, "cv {} {} {}", line, beat, value)?
+                }
+                Command::Position { line, beat, x, y } => {
+                    writeln!(f, "cp {} {} {} {}", line, beat, x, y)?
+                }
+                Command::Rotation { line, beat, value } => {
+                    writeln!(f, "cd {} {} {}", line, beat, value)?
+                }
+                Command::Alpha { line, beat, value } => {
+                    writeln!(f, "ca {} {} {}", line, beat, value)?
+                }
+                Command::Move {
+                    line,
+                    start_beat,
+                    end_beat,
+                    x,
+                    y,
+                    easing,
+                } => writeln!(
+                    f,
+                    "cm {} {} {} {} {} {}",
+                    line, start_beat, end_beat, x, y, easing
+                )?,
+                Command::Rotate {
+                    line,
+                    start_beat,
+                    end_beat,
+                    value,
+                    easing,
+                } => writeln!(
+                    f,
+                    "cr {} {} {} {} {}",
+                    line, start_beat, end_beat, value, easing
+                )?,
+                Command::Fade {
+                    line,
+                    start_beat,
+                    end_beat,
+                    value,
+                } => writeln!(f, "cf {} {} {} {}", line, start_beat, end_beat, value)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn beat(value: f32) -> Beat {
+    attach(value, PEC_BEAT_DENSITY)
+}
+
+/// Get the easing of a PhiEditer easing type id, unknown ids are reported and replaced by linear
+fn easing(id: i32, line: usize, report: &mut ConversionReport) -> Easing {
+    RPE_EASING.get(id as usize).copied().unwrap_or_else(|| {
+        report.push(
+            Some(Location::Line { line: vec![line] }),
+            LossKind::UnknownEasing {
+                easing: id.to_string(),
+            },
+        );
+        Easing::Linear
+    })
+}
+
+/// Get the PhiEditer easing type id of an easing, [`None`] if it is not supported
+fn easing_id(easing: Easing) -> Option<i32> {
+    match easing {
+        Easing::Linear => Some(LINEAR),
+        easing => RPE_EASING
+            .iter()
+            .position(|x| *x == easing)
+            .map(|x| x as i32),
+    }
+}
+
+/// A keyframe of a single value of a line, used to build events when importing
+#[derive(Debug, Clone, Copy)]
+enum Keyframe {
+    /// The value is set at the given beat
+    Set { beat: f32, value: f32 },
+    /// The value transitions from the current value to the given value
+    Transition {
+        start_beat: f32,
+        end_beat: f32,
+        value: f32,
+        easing: Easing,
+    },
+}
+
+impl Keyframe {
+    fn start_beat(&self) -> f32 {
+        match *self {
+            Keyframe::Set { beat, .. } => beat,
+            Keyframe::Transition { start_beat, .. } => start_beat,
+        }
+    }
+}
+
+/// Build events of a single kind from keyframes
+///
+/// A set keyframe holds its value until the next keyframe, transitions start from the value before them
+///
+/// A set keyframe followed by another keyframe at the same beat is only the start value of the next keyframe,
+/// so only the last keyframe of a beat builds an event
+fn build_events(
+    kind: LineEventKind,
+    mut keyframes: Vec<Keyframe>,
+) -> Vec<primitive::event::LineEvent> {
+    keyframes.sort_by(|a, b| a.start_beat().total_cmp(&b.start_beat()));
+
+    let mut events = vec![];
+    let mut current = 0.0;
+    for (index, keyframe) in keyframes.iter().enumerate() {
+        let (start_beat, end_beat, start, end, easing) = match *keyframe {
+            Keyframe::Set { beat: start, value }
+                if keyframes
+                    .get(index + 1)
+                    .is_some_and(|next| next.start_beat() == start) =>
+            {
+                current = value;
+                continue;
+            }
+            Keyframe::Set { beat: start, value } => {
+                let end = keyframes
+                    .get(index + 1)
+                    .map(Keyframe::start_beat)
+                    .filter(|next| *next > start)
+                    .unwrap_or(start + 1.0);
+                (start, end, value, value, Easing::Linear)
+            }
+            Keyframe::Transition {
+                start_beat,
+                end_beat,
+                value,
+                easing,
+            } => (start_beat, end_beat, current, value, easing),
+        };
+        current = end;
+        events.push(primitive::event::LineEvent {
+            kind,
+            start_beat: beat(start_beat),
+            end_beat: beat(end_beat),
+            start,
+            end,
+            easing,
+            text: None,
+            id: None,
+        });
+    }
+
+    events
+}
+
+impl Format for PecChart {
+    fn into_primitive_with_report(
+        self,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart> {
+        let mut bpm_points = vec![];
+        let mut lines: Vec<primitive::line::Line> = vec![];
+        // keyframes of x, y, rotation, opacity and speed of each line
+        let mut keyframes: Vec<[Vec<Keyframe>; 5]> = vec![];
+
+        let x = |x: f32| (x / PEC_WIDTH - 0.5) * CANVAS_WIDTH;
+        let y = |y: f32| (y / PEC_HEIGHT - 0.5) * CANVAS_HEIGHT;
+
+        for command in self.commands {
+            let line = match command {
+                Command::Bpm { .. } => None,
+                Command::Note { line, .. }
+                | Command::Speed { line, .. }
+                | Command::Position { line, .. }
+                | Command::Rotation { line, .. }
+                | Command::Alpha { line, .. }
+                | Command::Move { line, .. }
+                | Command::Rotate { line, .. }
+                | Command::Fade { line, .. } => Some(line),
+            };
+            if let Some(line) = line {
+                if line >= lines.len() {
+                    lines.resize_with(line + 1, Default::default);
+                    keyframes.resize_with(line + 1, Default::default);
+                }
+            }
+
+            match command {
+                Command::Bpm { beat: at, bpm } => bpm_points.push(BpmPoint::new(beat(at), bpm)),
+                Command::Note {
+                    kind,
+                    line,
+                    beat: at,
+                    x,
+                    above,
+                    fake,
+                    speed,
+                    size,
+                } => {
+                    let kind = match kind {
+                        NoteKind::Tap => crate::note::NoteKind::Tap,
+                        NoteKind::Hold { end_beat } => crate::note::NoteKind::Hold {
+                            hold_beat: beat(end_beat) - beat(at),
+                        },
+                        NoteKind::Flick => crate::note::NoteKind::Flick,
+                        NoteKind::Drag => crate::note::NoteKind::Drag,
+                    };
+                    lines[line].notes.push(crate::note::Note {
+                        fake,
+                        size,
+                        ..crate::note::Note::new(
+                            kind,
+                            above,
+                            beat(at),
+                            x / PEC_WIDTH * CANVAS_WIDTH,
+                            speed,
+                        )
+                    });
+                }
+                Command::Speed { line, beat, value } => keyframes[line][4].push(Keyframe::Set {
+                    beat,
+                    // same as the official format, see `OfficialChart::into_primitive`
+                    value: value / PEC_SPEED_RATIO / 2.0 * 9.0,
+                }),
+                Command::Position {
+                    line,
+                    beat,
+                    x: px,
+                    y: py,
+                } => {
+                    keyframes[line][0].push(Keyframe::Set { beat, value: x(px) });
+                    keyframes[line][1].push(Keyframe::Set { beat, value: y(py) });
+                }
+                // negate value for rotation
+                Command::Rotation { line, beat, value } => keyframes[line][2].push(Keyframe::Set {
+                    beat,
+                    value: -value,
+                }),
+                Command::Alpha { line, beat, value } => {
+                    keyframes[line][3].push(Keyframe::Set { beat, value })
+                }
+                Command::Move {
+                    line,
+                    start_beat,
+                    end_beat,
+                    x: px,
+                    y: py,
+                    easing: id,
+                } => {
+                    let easing = easing(id, line, report);
+                    keyframes[line][0].push(Keyframe::Transition {
+                        start_beat,
+                        end_beat,
+                        value: x(px),
+                        easing,
+                    });
+                    keyframes[line][1].push(Keyframe::Transition {
+                        start_beat,
+                        end_beat,
+                        value: y(py),
+                        easing,
+                    });
+                }
+                Command::Rotate {
+                    line,
+                    start_beat,
+                    end_beat,
+                    value,
+                    easing: id,
+                } => keyframes[line][2].push(Keyframe::Transition {
+                    start_beat,
+                    end_beat,
+                    value: -value,
+                    easing: easing(id, line, report),
+                }),
+                Command::Fade {
+                    line,
+                    start_beat,
+                    end_beat,
+                    value,
+                } => keyframes[line][3].push(Keyframe::Transition {
+                    start_beat,
+                    end_beat,
+                    value,
+                    easing: Easing::Linear,
+                }),
+            }
+        }
+
+        if bpm_points.is_empty() {
+            bail!("No BPM found in the chart");
+        }
+        bpm_points.sort_by_key(|x| x.beat);
+
+        for (line, keyframes) in lines.iter_mut().zip(keyframes) {
+            let kinds = [
+                LineEventKind::X,
+                LineEventKind::Y,
+                LineEventKind::Rotation,
+                LineEventKind::Opacity,
+                LineEventKind::Speed,
+            ];
+            for (kind, keyframes) in kinds.into_iter().zip(keyframes) {
+                line.events.append(&mut build_events(kind, keyframes));
+            }
+        }
+
+        Ok(PrimitiveChart {
+            offset: self.offset - PEC_OFFSET_DELAY,
+            bpm_list: BpmList::new(bpm_points),
+            lines,
+            ..Default::default()
+        })
+    }
+
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut pec = PecChart {
+            offset: primitive.offset + PEC_OFFSET_DELAY,
+            commands: vec![],
+        };
+
+        if primitive.bpm_list.has_ramps() {
+            report.push(None, LossKind::BpmRamp);
+        }
+
+        for point in &primitive.bpm_list.sampled(RAMP_SAMPLE_DENSITY).0 {
+            pec.commands.push(Command::Bpm {
+                beat: point.beat.value(),
+                bpm: point.bpm,
+            });
+        }
+
+        let paths = primitive::line::flattened_paths(&primitive.lines);
+        for path in paths.iter().filter(|path| path.len() > 1) {
+            report.push(
+                Some(Location::Line { line: path.clone() }),
+                LossKind::LineHierarchy,
+            );
+        }
+
+        for ((index, line), path) in primitive
+            .lines
+            .into_iter()
+            .flat_map(primitive::line::Line::flatten)
+            .enumerate()
+            .zip(paths)
+        {
+            for property in [
+                LineProperty::Name,
+                LineProperty::ZOrder,
+                LineProperty::HideNotesBelow,
+            ] {
+                if property.is_set(&line.line) {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedLineProperty { property },
+                    );
+                }
+            }
+
+            for property in [
+                NoteProperty::Alpha,
+                NoteProperty::VisibleTime,
+                NoteProperty::YOffset,
+            ] {
+                let count = line.notes.iter().filter(|n| property.is_set(n)).count();
+                if count > 0 {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedNoteProperty { property, count },
+                    );
+                }
+            }
+
+            for note in &line.notes {
+                let kind = match note.kind {
+                    crate::note::NoteKind::Tap => NoteKind::Tap,
+                    crate::note::NoteKind::Hold { hold_beat } => NoteKind::Hold {
+                        end_beat: (note.beat + hold_beat).value(),
+                    },
+                    crate::note::NoteKind::Flick => NoteKind::Flick,
+                    crate::note::NoteKind::Drag => NoteKind::Drag,
+                };
+                pec.commands.push(Command::Note {
+                    kind,
+                    line: index,
+                    beat: note.beat.value(),
+                    x: note.x / CANVAS_WIDTH * PEC_WIDTH,
+                    above: note.above,
+                    fake: note.fake,
+                    speed: note.speed,
+                    size: note.size,
+                });
+            }
+
+            let events_of = |kind: LineEventKind| {
+                let mut events = line
+                    .events
+                    .iter()
+                    .filter(|e| e.kind == kind)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                events.sort_by_key(|e| e.start_beat);
+                events
+            };
+
+            for kind in [
+                LineEventKind::ScaleX,
+                LineEventKind::ScaleY,
+                LineEventKind::Color,
+                LineEventKind::Text,
+            ] {
+                let count = line.events.iter().filter(|e| e.kind == kind).count();
+                if count > 0 {
+                    report.push(
+                        Some(Location::Line { line: path.clone() }),
+                        LossKind::DroppedEvents { kind, count },
+                    );
+                }
+            }
+
+            // unsupported easings are baked into linear segments, speed transitions into steps
+            for (event_index, event) in line.events.iter().enumerate() {
+                if event.start == event.end {
+                    continue;
+                }
+                let kind = match event.kind {
+                    LineEventKind::Speed => LossKind::SteppedTransition { kind: event.kind },
+                    LineEventKind::X | LineEventKind::Y | LineEventKind::Rotation
+                        if easing_id(event.easing).is_none() =>
+                    {
+                        LossKind::UnsupportedEasing {
+                            kind: event.kind,
+                            easing: event.easing,
+                        }
+                    }
+                    LineEventKind::Opacity if event.easing != Easing::Linear => {
+                        LossKind::UnsupportedEasing {
+                            kind: event.kind,
+                            easing: event.easing,
+                        }
+                    }
+                    _ => continue,
+                };
+                report.push(
+                    Some(Location::Event {
+                        line: path.clone(),
+                        index: event_index,
+                    }),
+                    kind,
+                );
+            }
+
+            // -------- Move events --------
+
+            for (start_beat, end_beat, [x, y], easing) in transitions(
+                &[events_of(LineEventKind::X), events_of(LineEventKind::Y)],
+                easing_id,
+            ) {
+                let x = (x / CANVAS_WIDTH + 0.5) * PEC_WIDTH;
+                let y = (y / CANVAS_HEIGHT + 0.5) * PEC_HEIGHT;
+                pec.commands.push(match easing {
+                    None => Command::Position {
+                        line: index,
+                        beat: start_beat.value(),
+                        x,
+                        y,
+                    },
+                    Some(easing) => Command::Move {
+                        line: index,
+                        start_beat: start_beat.value(),
+                        end_beat: end_beat.value(),
+                        x,
+                        y,
+                        easing,
+                    },
+                });
+            }
+
+            // -------- Rotate events --------
+
+            for (start_beat, end_beat, [value], easing) in
+                transitions(&[events_of(LineEventKind::Rotation)], easing_id)
+            {
+                // negate value for rotation
+                pec.commands.push(match easing {
+                    None => Command::Rotation {
+                        line: index,
+                        beat: start_beat.value(),
+                        value: -value,
+                    },
+                    Some(easing) => Command::Rotate {
+                        line: index,
+                        start_beat: start_beat.value(),
+                        end_beat: end_beat.value(),
+                        value: -value,
+                        easing,
+                    },
+                });
+            }
+
+            // -------- Opacity events --------
+
+            // fading is always linear
+            let linear = |easing| (easing == Easing::Linear).then_some(LINEAR);
+            for (start_beat, end_beat, [value], easing) in
+                transitions(&[events_of(LineEventKind::Opacity)], linear)
+            {
+                pec.commands.push(match easing {
+                    None => Command::Alpha {
+                        line: index,
+                        beat: start_beat.value(),
+                        value,
+                    },
+                    Some(_) => Command::Fade {
+                        line: index,
+                        start_beat: start_beat.value(),
+                        end_beat: end_beat.value(),
+                        value,
+                    },
+                });
+            }
+
+            // -------- Speed events --------
+
+            // speed can only be set, speed transitions are baked into steps
+            for (start_beat, _, [value], _) in
+                transitions(&[events_of(LineEventKind::Speed)], |_| None::<i32>)
+            {
+                pec.commands.push(Command::Speed {
+                    line: index,
+                    beat: start_beat.value(),
+                    value: value / 9.0 * 2.0 * PEC_SPEED_RATIO,
+                });
+            }
+        }
+
+        Ok(pec)
+    }
+}
+
+/// Convert events of one or more values changing together into PhiEditer transitions
+///
+/// Returns a list of `(start beat, end beat, end values, easing)`,
+/// a [`None`] easing means the values are set at the start beat instead of transitioning.
+///
+/// A transition keeps its easing only if every changing value is changed by a whole event with the same supported easing,
+/// otherwise it is baked into linear segments, or set steps when linear transitions are not supported either
+fn transitions<const N: usize>(
+    events: &[Vec<primitive::event::LineEvent>; N],
+    easing_id: impl Fn(Easing) -> Option<i32>,
+) -> Vec<(Beat, Beat, [f32; N], Option<i32>)> {
+    let mut beats = events
+        .iter()
+        .flatten()
+        .flat_map(|e| [e.start_beat, e.end_beat])
+        .collect::<Vec<_>>();
+    beats.sort();
+    beats.dedup();
+
+    let evaluate = |event: &primitive::event::LineEvent, beat: Beat| {
+        crate::event::LineEvent::from(event.clone())
+            .evaluate(beat.value())
+            .value()
+            .unwrap_or(event.start)
+    };
+
+    let mut result = vec![];
+    let mut current: Option<[f32; N]> = None;
+
+    for window in beats.windows(2) {
+        let (start_beat, end_beat) = (window[0], window[1]);
+        let covering = events.each_ref().map(|events| {
+            events
+                .iter()
+                .find(|e| e.start_beat <= start_beat && e.end_beat >= end_beat)
+        });
+        if covering.iter().all(Option::is_none) {
+            continue;
+        }
+
+        let last = current.unwrap_or([0.0; N]);
+        let mut start = last;
+        let mut end = last;
+        for (i, event) in covering.iter().enumerate() {
+            if let Some(event) = event {
+                start[i] = evaluate(event, start_beat);
+                end[i] = evaluate(event, end_beat);
+            }
+        }
+
+        if current != Some(start) {
+            result.push((start_beat, start_beat, start, None));
+        }
+        current = Some(end);
+        if start == end {
+            continue;
+        }
+
+        // easings of the changing values, `None` if the value is changed by a part of an event
+        let easings = covering
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| start[*i] != end[*i])
+            .map(|(_, event)| {
+                event
+                    .filter(|e| e.start_beat == start_beat && e.end_beat == end_beat)
+                    .and_then(|e| easing_id(e.easing))
+            })
+            .collect::<Vec<_>>();
+        if let [Some(easing), rest @ ..] = easings.as_slice() {
+            if rest.iter().all(|x| *x == Some(*easing)) {
+                result.push((start_beat, end_beat, end, Some(*easing)));
+                continue;
+            }
+        }
+
+        // bake the transition
+        let linear = easing_id(Easing::Linear);
+        let step = beat!(0, 1, BAKE_DENSITY);
+        let mut beat = start_beat;
+        while beat < end_beat {
+            let next = (beat + step).min(end_beat);
+            let mut values = end;
+            for (i, event) in covering.iter().enumerate() {
+                if let Some(event) = event {
+                    values[i] = evaluate(event, if linear.is_some() { next } else { beat });
+                }
+            }
+            match linear {
+                Some(_) => result.push((beat, next, values, linear)),
+                None => result.push((beat, beat, values, None)),
+            }
+            beat = next;
+        }
+        if linear.is_none() {
+            result.push((end_beat, end_beat, end, None));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHART: &str = "150
+bp 0.00 120.00
+n1 0 1.00 512.00 1 0
+# 1.00
+& 1.00
+n2 0 2.00 4.00 -512.00 2 0
+# 1.00
+& 1.50
+cv 0 0.00 11.70
+cp 0 0.00 1024.00 700.00
+cd 0 0.00 0.00
+ca 0 0.00 255
+cm 0 1.00 2.00 2048.00 1400.00 2
+cr 0 2.00 3.00 90.00 1
+cf 0 3.00 4.00 0
+";
+
+    #[test]
+    fn test_parse() {
+        let chart = PecChart::from_str(CHART).unwrap();
+        assert_eq!(chart.offset, 150.0);
+        assert_eq!(chart.commands.len(), 10);
+        assert_eq!(
+            chart.commands[2],
+            Command::Note {
+                kind: NoteKind::Hold { end_beat: 4.0 },
+                line: 0,
+                beat: 2.0,
+                x: -512.0,
+                above: false,
+                fake: false,
+                speed: 1.0,
+                size: 1.5,
+            }
+        );
+
+        assert!(PecChart::from_str("0\nbp 0.00").is_err());
+        assert!(PecChart::from_str("0\nxx 0 0").is_err());
+        assert!(PecChart::from_str("0\n# 1.00").is_err());
+    }
+
+    #[test]
+    fn test_into_primitive() {
+        let primitive = PecChart::from_str(CHART).unwrap().into_primitive().unwrap();
+        assert_eq!(primitive.offset, 0.0);
+        assert_eq!(primitive.lines.len(), 1);
+
+        let line = &primitive.lines[0];
+        assert_eq!(line.notes.len(), 2);
+        assert_eq!(line.notes[0].x, CANVAS_WIDTH / 4.0);
+        assert_eq!(
+            line.notes[1].kind,
+            crate::note::NoteKind::Hold {
+                hold_beat: beat!(2)
+            }
+        );
+
+        let x_events = line
+            .events
+            .iter()
+            .filter(|e| e.kind == LineEventKind::X)
+            .collect::<Vec<_>>();
+        assert_eq!(x_events.len(), 2);
+        assert_eq!(x_events[0].start, 0.0);
+        assert_eq!(x_events[1].start_beat, beat!(1));
+        assert_eq!(x_events[1].end, CANVAS_WIDTH / 2.0);
+        assert_eq!(x_events[1].easing, Easing::EaseOutSine);
+
+        let speed = line
+            .events
+            .iter()
+            .find(|e| e.kind == LineEventKind::Speed)
+            .unwrap();
+        assert!((speed.start - 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_same_beat_keyframes() {
+        let chart = "0
+bp 0.00 120.00
+cp 0 0.00 0.00 0.00
+cp 0 0.00 1024.00 700.00
+cd 0 1.00 0.00
+cr 0 1.00 2.00 90.00 1
+";
+        let primitive = PecChart::from_str(chart).unwrap().into_primitive().unwrap();
+        let events_of = |kind| {
+            primitive.lines[0]
+                .events
+                .iter()
+                .filter(|e| e.kind == kind)
+                .collect::<Vec<_>>()
+        };
+
+        // the later position at the same beat wins
+        let x_events = events_of(LineEventKind::X);
+        assert_eq!(x_events.len(), 1);
+        assert_eq!(x_events[0].start, 0.0);
+
+        // the rotation is set right before the transition, which starts from the set value
+        let rotation_events = events_of(LineEventKind::Rotation);
+        assert_eq!(rotation_events.len(), 1);
+        assert_eq!(rotation_events[0].start_beat, beat!(1));
+        assert_eq!(rotation_events[0].start, 0.0);
+        assert_eq!(rotation_events[0].end, -90.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let primitive = PecChart::from_str(CHART).unwrap().into_primitive().unwrap();
+        let pec = PecChart::from_primitive(primitive.clone()).unwrap();
+        let text = pec.to_string();
+        let reparsed = PecChart::from_str(&text).unwrap().into_primitive().unwrap();
+
+        assert_eq!(reparsed.offset, primitive.offset);
+        assert_eq!(reparsed.lines[0].notes, primitive.lines[0].notes);
+        let events = |chart: &PrimitiveChart| {
+            let mut events = chart.lines[0].events.clone();
+            events.sort_by_key(|e| (e.kind as u8, e.start_beat));
+            events
+        };
+        assert_eq!(events(&reparsed), events(&primitive));
+        assert!(text.contains("cm 0 1 2 2048 1400 2"));
+        assert!(text.contains("cr 0 2 3 90 1"));
+        assert!(text.contains("cf 0 3 4 0"));
+    }
+
+    #[test]
+    fn test_bake_unsupported_easing() {
+        let events = [vec![primitive::event::LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(0),
+            end_beat: beat!(1),
+            start: 0.0,
+            end: 16.0,
+            easing: Easing::Custom(0.0, 0.0, 1.0, 1.0),
+            text: None,
+            id: None,
+        }]];
+        let transitions = transitions(&events, easing_id);
+        // the initial value, followed by 16 baked linear segments
+        assert_eq!(transitions.len(), 17);
+        assert!(transitions[1..]
+            .iter()
+            .all(|(_, _, _, easing)| *easing == Some(LINEAR)));
+        assert_eq!(transitions[16].2, [16.0]);
+    }
+}
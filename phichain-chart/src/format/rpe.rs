@@ -1,13 +1,17 @@
 //! Re:PhiEdit json format
 
-use crate::bpm_list::BpmList;
-use crate::easing::Easing;
+use crate::beat;
+use crate::bpm_list::{BpmList, RAMP_SAMPLE_DENSITY};
+use crate::easing::{Easing, Tween};
+use crate::event::{pack_color, unpack_color, LineEventKind};
+use crate::lint::Location;
 use crate::primitive;
 use crate::primitive::{Format, PrimitiveChart};
-use num::{Num, Rational32};
+use crate::report::{ConversionReport, LossKind};
+use internment::ArcIntern;
+use num::Rational32;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use tracing::warn;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
@@ -20,7 +24,6 @@ enum NoteKind {
 }
 
 // generated by https://transform.tools/json-to-rust-serde
-// TODO: event layer, parent support
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct Beat(i32, i32, i32);
 
@@ -71,9 +74,44 @@ struct Meta {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct JudgeLine {
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Texture", default = "default_texture")]
+    texture: String,
+    #[serde(default)]
+    z_order: i32,
+    #[serde(default = "default_is_cover")]
+    is_cover: i32,
     event_layers: Vec<EventLayer>,
+    /// Index of the parent line in the judge line list, `-1` for lines without a parent
+    #[serde(default = "default_father")]
+    father: i32,
+    /// Whether the rotation of the parent line is added to the line
+    #[serde(default = "default_rotate_with_father")]
+    rotate_with_father: bool,
     #[serde(default)]
     notes: Vec<Note>,
+    #[serde(default)]
+    extended: Extended,
+}
+
+fn default_father() -> i32 {
+    -1
+}
+
+fn default_rotate_with_father() -> bool {
+    true
+}
+
+/// Texture used by Re:PhiEdit for lines without a custom texture
+const DEFAULT_TEXTURE: &str = "line.png";
+
+fn default_texture() -> String {
+    DEFAULT_TEXTURE.to_owned()
+}
+
+fn default_is_cover() -> i32 {
+    1
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -91,19 +129,113 @@ struct EventLayer {
     speed_events: Vec<SpeedEvent>,
 }
 
+/// Extended events of a judge line, they are not layered
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct CommonEvent<T: Num> {
+struct Extended {
+    #[serde(default, rename = "scaleXEvents")]
+    scale_xevents: Vec<CommonEvent<f32>>,
+    #[serde(default, rename = "scaleYEvents")]
+    scale_yevents: Vec<CommonEvent<f32>>,
+    #[serde(default)]
+    color_events: Vec<CommonEvent<[u8; 3]>>,
+    #[serde(default)]
+    text_events: Vec<CommonEvent<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommonEvent<T> {
     bezier: i32,
     #[serde(rename = "bezierPoints")]
     bezier_points: [f32; 4],
     easing_type: i32,
+    #[serde(default)]
+    easing_left: f32,
+    #[serde(default = "default_easing_right")]
+    easing_right: f32,
     end: T,
     end_time: Beat,
     start: T,
     start_time: Beat,
 }
 
+fn default_easing_right() -> f32 {
+    1.0
+}
+
+impl<T> CommonEvent<T> {
+    /// Resolve the easing of this event, bezier curves and easing ranges are taken into account
+    ///
+    /// Unknown easing types are resolved as [`Easing::Linear`], see [`CommonEvent::is_easing_unknown`]
+    fn easing(&self) -> Easing {
+        if self.bezier == 1 {
+            let [x1, y1, x2, y2] = self.bezier_points;
+            return Easing::Custom(x1, y1, x2, y2);
+        }
+
+        RPE_EASING
+            .get(self.easing_type as usize)
+            .copied()
+            .unwrap_or(Easing::Linear)
+            .partial(self.easing_left, self.easing_right)
+    }
+
+    fn is_easing_unknown(&self) -> bool {
+        self.bezier != 1 && RPE_EASING.get(self.easing_type as usize).is_none()
+    }
+
+    /// Convert this event into a primitive event with the given values
+    fn to_primitive(
+        &self,
+        kind: LineEventKind,
+        start: f32,
+        end: f32,
+    ) -> primitive::event::LineEvent {
+        primitive::event::LineEvent {
+            kind,
+            start_beat: self.start_time.clone().into(),
+            end_beat: self.end_time.clone().into(),
+            start,
+            end,
+            easing: self.easing(),
+            text: None,
+            id: None,
+        }
+    }
+
+    /// Copy the timing and easing of this event into an event with the given values
+    fn with_values<U>(&self, start: U, end: U) -> CommonEvent<U> {
+        CommonEvent {
+            bezier: self.bezier,
+            bezier_points: self.bezier_points,
+            easing_type: self.easing_type,
+            easing_left: self.easing_left,
+            easing_right: self.easing_right,
+            end,
+            end_time: self.end_time.clone(),
+            start,
+            start_time: self.start_time.clone(),
+        }
+    }
+}
+
+/// Report events with unknown easing types of the line at the given index
+fn report_unknown_easings<T>(
+    events: &[CommonEvent<T>],
+    line: usize,
+    report: &mut ConversionReport,
+) {
+    for event in events.iter().filter(|event| event.is_easing_unknown()) {
+        report.push(
+            Some(Location::Line { line: vec![line] }),
+            LossKind::UnknownEasing {
+                easing: event.easing_type.to_string(),
+            },
+        );
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SpeedEvent {
@@ -121,13 +253,36 @@ struct Note {
     position_x: f32,
     speed: f32,
     start_time: Beat,
-    size: f32,         // ignored
-    visible_time: f32, // ignored
+    #[serde(default = "default_note_size")]
+    size: f32,
+    #[serde(default = "default_visible_time")]
+    visible_time: f32,
+    #[serde(default)]
+    is_fake: i32,
+    #[serde(default = "default_note_alpha")]
+    alpha: i32,
+    #[serde(default)]
+    y_offset: f32,
     #[serde(rename = "type")]
     kind: NoteKind,
 }
 
-static RPE_EASING: [Easing; 30] = [
+/// Visible time used by Re:PhiEdit for notes that are always visible
+const ALWAYS_VISIBLE_TIME: f32 = 999999.0;
+
+fn default_note_size() -> f32 {
+    1.0
+}
+
+fn default_visible_time() -> f32 {
+    ALWAYS_VISIBLE_TIME
+}
+
+fn default_note_alpha() -> i32 {
+    255
+}
+
+pub(crate) static RPE_EASING: [Easing; 30] = [
     Easing::Linear,
     Easing::Linear,
     Easing::EaseOutSine,
@@ -160,8 +315,170 @@ static RPE_EASING: [Easing; 30] = [
     Easing::EaseInOutElastic,
 ];
 
+/// Evaluate an event layer at the given beat
+///
+/// Before the first event of the layer, the layer holds the start value of its first event
+fn evaluate_layer(
+    events: &[primitive::event::LineEvent],
+    beat: crate::beat::Beat,
+    start_has_effect: bool,
+) -> f32 {
+    let mut ret = events.first().map(|x| x.start).unwrap_or(0.0);
+    for event in events {
+        let event = crate::event::LineEvent::from(event.clone());
+        let result = if start_has_effect {
+            event.evaluate(beat.value())
+        } else {
+            event.evaluate_start_no_effect(beat.value())
+        };
+        if let Some(value) = result.value() {
+            ret = value;
+        }
+    }
+
+    ret
+}
+
+/// Merge events of the same kind from multiple event layers into a single event sequence
+///
+/// Re:PhiEdit evaluates every event layer independently and sums up their values.
+/// Segments where at most one layer is changing keep their original easing, others are baked into 1/32 beat linear events.
+/// An event spanning several segments is cut into parts of its easing, consecutive parts are joined back while the other layers stay the same
+fn merge_layers(layers: Vec<Vec<primitive::event::LineEvent>>) -> Vec<primitive::event::LineEvent> {
+    let mut layers = layers
+        .into_iter()
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    if layers.len() <= 1 {
+        return layers.pop().unwrap_or_default();
+    }
+
+    for layer in &mut layers {
+        layer.sort_by_key(|e| e.start_beat);
+    }
+
+    let kind = layers[0][0].kind;
+
+    let mut splits = layers
+        .iter()
+        .flatten()
+        .flat_map(|e| [e.start_beat, e.end_beat])
+        .collect::<Vec<_>>();
+    splits.sort();
+    splits.dedup();
+
+    let sum = |beat: crate::beat::Beat, start_has_effect: bool| {
+        layers
+            .iter()
+            .map(|layer| evaluate_layer(layer, beat, start_has_effect))
+            .sum::<f32>()
+    };
+    // sum of all layers except one
+    let sum_others = |except: usize, beat: crate::beat::Beat| {
+        layers
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != except)
+            .map(|(_, layer)| evaluate_layer(layer, beat, true))
+            .sum::<f32>()
+    };
+
+    let mut events = vec![];
+    // the event the last merged event is a part of, with the sum of the other layers during it
+    let mut continued: Option<(&primitive::event::LineEvent, f32)> = None;
+
+    for window in splits.windows(2) {
+        let (start_beat, end_beat) = (window[0], window[1]);
+
+        let changing = layers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, layer)| {
+                layer
+                    .iter()
+                    .find(|e| {
+                        e.start_beat <= start_beat && e.end_beat >= end_beat && e.start != e.end
+                    })
+                    .map(|e| (index, e))
+            })
+            .collect::<Vec<_>>();
+
+        match changing.as_slice() {
+            [] => {
+                continued = None;
+                let value = sum(start_beat, true);
+                events.push(primitive::event::LineEvent {
+                    kind,
+                    start_beat,
+                    end_beat,
+                    start: value,
+                    end: value,
+                    easing: Easing::Linear,
+                    text: None,
+                    id: None,
+                });
+            }
+            &[(index, event)] => {
+                // other layers are constant in this segment, keep the part of the easing covering it
+                let offset = sum_others(index, start_beat);
+                let length = (event.end_beat - event.start_beat).value();
+                let progress = |beat: crate::beat::Beat| (beat - event.start_beat).value() / length;
+                let value_at = |beat| event.start.ease_to(event.end, progress(beat), event.easing);
+                let part = |start_beat, end_beat| primitive::event::LineEvent {
+                    start_beat,
+                    end_beat,
+                    start: value_at(start_beat) + offset,
+                    end: value_at(end_beat) + offset,
+                    easing: if start_beat == event.start_beat && end_beat == event.end_beat {
+                        event.easing
+                    } else {
+                        event
+                            .easing
+                            .partial(progress(start_beat), progress(end_beat))
+                    },
+                    ..event.clone()
+                };
+
+                match (continued, events.last_mut()) {
+                    (Some((previous, previous_offset)), Some(last))
+                        if std::ptr::eq(previous, event) && previous_offset == offset =>
+                    {
+                        *last = part(last.start_beat, end_beat);
+                    }
+                    _ => events.push(part(start_beat, end_beat)),
+                }
+                continued = Some((event, offset));
+            }
+            _ => {
+                continued = None;
+                let minimum = beat!(1, 32);
+                let mut current = start_beat;
+                while current < end_beat {
+                    let next = (current + minimum).min(end_beat);
+                    events.push(primitive::event::LineEvent {
+                        kind,
+                        start_beat: current,
+                        end_beat: next,
+                        start: sum(current, true),
+                        end: sum(next, false),
+                        easing: Easing::Linear,
+                        text: None,
+                        id: None,
+                    });
+                    current = next;
+                }
+            }
+        }
+    }
+
+    events
+}
+
 impl Format for RpeChart {
-    fn into_primitive(self) -> anyhow::Result<PrimitiveChart> {
+    fn into_primitive_with_report(
+        self,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart> {
         let mut primitive = PrimitiveChart {
             offset: self.meta.offset as f32,
             bpm_list: BpmList::new(
@@ -174,77 +491,178 @@ impl Format for RpeChart {
             ..Default::default()
         };
 
-        let e = |id: i32| {
-            RPE_EASING.get(id as usize).copied().unwrap_or_else(|| {
-                warn!("Unknown easing type: {}", id);
-                Easing::Linear
-            })
-        };
+        let mut lines = vec![];
+        let mut fathers = vec![];
 
-        for line in self.judge_line_list {
-            let x_event_iter = line
-                .event_layers
-                .iter()
-                .flat_map(|layer| layer.move_xevents.clone())
-                .map(|event| primitive::event::LineEvent {
-                    kind: crate::event::LineEventKind::X,
-                    start_beat: event.start_time.into(),
-                    end_beat: event.end_time.into(),
-                    start: event.start,
-                    end: event.end,
-                    easing: e(event.easing_type),
-                });
-            let y_event_iter = line
-                .event_layers
-                .iter()
-                .flat_map(|layer| layer.move_yevents.clone())
-                .map(|event| primitive::event::LineEvent {
-                    kind: crate::event::LineEventKind::Y,
-                    start_beat: event.start_time.into(),
-                    end_beat: event.end_time.into(),
-                    start: event.start,
-                    end: event.end,
-                    easing: e(event.easing_type),
-                });
-            let rotate_event_iter = line
-                .event_layers
-                .iter()
-                .flat_map(|layer| layer.rotate_events.clone())
-                .map(|event| primitive::event::LineEvent {
-                    kind: crate::event::LineEventKind::Rotation,
-                    start_beat: event.start_time.into(),
-                    end_beat: event.end_time.into(),
-                    // negate value for rotation
-                    start: -event.start,
-                    end: -event.end,
-                    easing: e(event.easing_type),
-                });
-            let alpha_event_iter = line
-                .event_layers
+        for (index, line) in self.judge_line_list.into_iter().enumerate() {
+            fathers.push(line.father);
+
+            for layer in &line.event_layers {
+                report_unknown_easings(&layer.move_xevents, index, report);
+                report_unknown_easings(&layer.move_yevents, index, report);
+                report_unknown_easings(&layer.rotate_events, index, report);
+                report_unknown_easings(&layer.alpha_events, index, report);
+            }
+            report_unknown_easings(&line.extended.scale_xevents, index, report);
+            report_unknown_easings(&line.extended.scale_yevents, index, report);
+            report_unknown_easings(&line.extended.color_events, index, report);
+            report_unknown_easings(&line.extended.text_events, index, report);
+
+            let x_events = merge_layers(
+                line.event_layers
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .move_xevents
+                            .iter()
+                            .map(|event| primitive::event::LineEvent {
+                                kind: crate::event::LineEventKind::X,
+                                start_beat: event.start_time.clone().into(),
+                                end_beat: event.end_time.clone().into(),
+                                start: event.start,
+                                end: event.end,
+                                easing: event.easing(),
+                                text: None,
+                                id: None,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+            let y_events = merge_layers(
+                line.event_layers
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .move_yevents
+                            .iter()
+                            .map(|event| primitive::event::LineEvent {
+                                kind: crate::event::LineEventKind::Y,
+                                start_beat: event.start_time.clone().into(),
+                                end_beat: event.end_time.clone().into(),
+                                start: event.start,
+                                end: event.end,
+                                easing: event.easing(),
+                                text: None,
+                                id: None,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+            let rotate_events = merge_layers(
+                line.event_layers
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .rotate_events
+                            .iter()
+                            .map(|event| primitive::event::LineEvent {
+                                kind: crate::event::LineEventKind::Rotation,
+                                start_beat: event.start_time.clone().into(),
+                                end_beat: event.end_time.clone().into(),
+                                // negate value for rotation
+                                start: -event.start,
+                                end: -event.end,
+                                easing: event.easing(),
+                                text: None,
+                                id: None,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+            let alpha_events = merge_layers(
+                line.event_layers
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .alpha_events
+                            .iter()
+                            .map(|event| primitive::event::LineEvent {
+                                kind: crate::event::LineEventKind::Opacity,
+                                start_beat: event.start_time.clone().into(),
+                                end_beat: event.end_time.clone().into(),
+                                start: event.start as f32,
+                                end: event.end as f32,
+                                easing: event.easing(),
+                                text: None,
+                                id: None,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+            let speed_events = merge_layers(
+                line.event_layers
+                    .iter()
+                    .map(|layer| {
+                        layer
+                            .speed_events
+                            .iter()
+                            .map(|event| primitive::event::LineEvent {
+                                kind: crate::event::LineEventKind::Speed,
+                                start_beat: event.start_time.clone().into(),
+                                end_beat: event.end_time.clone().into(),
+                                start: event.start,
+                                end: event.end,
+                                easing: Easing::Linear, // speed events' easing are fixed to be Linear
+                                text: None,
+                                id: None,
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+
+            let extended = &line.extended;
+            let scale_x_events = extended
+                .scale_xevents
                 .iter()
-                .flat_map(|layer| layer.alpha_events.clone())
-                .map(|event| primitive::event::LineEvent {
-                    kind: crate::event::LineEventKind::Opacity,
-                    start_beat: event.start_time.into(),
-                    end_beat: event.end_time.into(),
-                    start: event.start as f32,
-                    end: event.end as f32,
-                    easing: e(event.easing_type),
-                });
-            let speed_event_iter = line
-                .event_layers
+                .map(|event| event.to_primitive(LineEventKind::ScaleX, event.start, event.end));
+            let scale_y_events = extended
+                .scale_yevents
                 .iter()
-                .flat_map(|layer| layer.speed_events.clone())
-                .map(|event| primitive::event::LineEvent {
-                    kind: crate::event::LineEventKind::Speed,
-                    start_beat: event.start_time.into(),
-                    end_beat: event.end_time.into(),
-                    start: event.start,
-                    end: event.end,
-                    easing: Easing::Linear, // speed events' easing are fixed to be Linear
-                });
+                .map(|event| event.to_primitive(LineEventKind::ScaleY, event.start, event.end));
+            let color_events = extended.color_events.iter().map(|event| {
+                event.to_primitive(
+                    LineEventKind::Color,
+                    pack_color(event.start),
+                    pack_color(event.end),
+                )
+            });
+            // texts can not be interpolated, the start text is kept during the whole event
+            let text_events =
+                extended
+                    .text_events
+                    .iter()
+                    .map(|event| primitive::event::LineEvent {
+                        text: Some(ArcIntern::new(event.start.clone())),
+                        id: None,
+                        ..event.to_primitive(LineEventKind::Text, 0.0, 0.0)
+                    });
+            let extended_events = scale_x_events
+                .chain(scale_y_events)
+                .chain(color_events)
+                .chain(text_events)
+                .collect::<Vec<_>>();
 
-            primitive.lines.push(primitive::line::Line {
+            let mut properties = crate::line::Line {
+                texture: (line.texture != DEFAULT_TEXTURE).then_some(line.texture),
+                z_order: line.z_order,
+                hide_notes_below: line.is_cover == 1,
+                inheritance: crate::line::LineInheritance {
+                    rotation: line.rotate_with_father,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            if !line.name.is_empty() {
+                properties.name = line.name;
+            }
+
+            lines.push(Some(primitive::line::Line {
+                line: properties,
                 notes: line
                     .notes
                     .iter()
@@ -260,34 +678,106 @@ impl Format for RpeChart {
                             NoteKind::Flick => crate::note::NoteKind::Flick,
                         };
 
-                        crate::note::Note::new(
-                            kind,
-                            note.above == 1,
-                            start_beat,
-                            note.position_x,
-                            note.speed,
-                        )
+                        crate::note::Note {
+                            fake: note.is_fake == 1,
+                            alpha: note.alpha as f32,
+                            size: note.size,
+                            visible_time: (note.visible_time < ALWAYS_VISIBLE_TIME)
+                                .then_some(note.visible_time),
+                            y_offset: note.y_offset,
+                            ..crate::note::Note::new(
+                                kind,
+                                note.above == 1,
+                                start_beat,
+                                note.position_x,
+                                note.speed,
+                            )
+                        }
                     })
                     .collect(),
-                events: x_event_iter
-                    .chain(y_event_iter)
-                    .chain(rotate_event_iter)
-                    .chain(alpha_event_iter)
-                    .chain(speed_event_iter)
-                    .collect(),
-            });
+                events: [
+                    x_events,
+                    y_events,
+                    rotate_events,
+                    alpha_events,
+                    speed_events,
+                    extended_events,
+                ]
+                .concat(),
+                children: vec![],
+            }));
+        }
+
+        let mut children_of = vec![vec![]; lines.len()];
+        let mut roots = vec![];
+        for (index, father) in fathers.into_iter().enumerate() {
+            match usize::try_from(father) {
+                Ok(father) if father < lines.len() && father != index => {
+                    children_of[father].push(index);
+                }
+                _ => {
+                    if father != -1 {
+                        report.push(
+                            Some(Location::Line { line: vec![index] }),
+                            LossKind::InvalidParent { parent: father },
+                        );
+                    }
+                    roots.push(index);
+                }
+            }
+        }
+
+        /// Take the line at the given index out of the list along with all its descendants
+        fn build_tree(
+            index: usize,
+            lines: &mut [Option<primitive::line::Line>],
+            children_of: &[Vec<usize>],
+        ) -> Option<primitive::line::Line> {
+            let mut line = lines[index].take()?;
+            for child in &children_of[index] {
+                if let Some(child) = build_tree(*child, lines, children_of) {
+                    line.children.push(child);
+                }
+            }
+
+            Some(line)
+        }
+
+        for index in roots {
+            if let Some(line) = build_tree(index, &mut lines, &children_of) {
+                primitive.lines.push(line);
+            }
+        }
+
+        // lines left are in a parent cycle, which can never be reached from a root line
+        while let Some(index) = lines.iter().position(Option::is_some) {
+            report.push(
+                Some(Location::Line { line: vec![index] }),
+                LossKind::ParentCycle,
+            );
+            if let Some(line) = build_tree(index, &mut lines, &children_of) {
+                primitive.lines.push(line);
+            }
         }
 
         Ok(primitive)
     }
 
-    fn from_primitive(primitive: PrimitiveChart) -> anyhow::Result<Self>
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
+        if primitive.bpm_list.has_ramps() {
+            report.push(None, LossKind::BpmRamp);
+        }
+
         let mut rpe = RpeChart {
             bpm_list: primitive
                 .bpm_list
+                .sampled(RAMP_SAMPLE_DENSITY)
                 .0
                 .iter()
                 .map(|x| BpmPoint {
@@ -302,18 +792,40 @@ impl Format for RpeChart {
             judge_line_list: vec![],
         };
 
-        let e = |easing: Easing| {
-            RPE_EASING
-                .iter()
-                .position(|x| *x == easing)
-                .unwrap_or_else(|| {
-                    warn!("Unknown easing type: {}", easing);
-                    1
-                })
-        };
+        let e = |easing: Easing| RPE_EASING.iter().position(|x| *x == easing);
+
+        let paths = primitive::line::flattened_paths(&primitive.lines);
+
+        // flatten the line tree in pre-order, so a parent line always comes before its children
+        let mut stack = primitive
+            .lines
+            .into_iter()
+            .rev()
+            .map(|line| (line, -1))
+            .collect::<Vec<_>>();
 
-        for primitive::line::Line { notes, events } in primitive.lines {
-            let mut line = JudgeLine::default();
+        while let Some((
+            primitive::line::Line {
+                line: properties,
+                notes,
+                events,
+                children,
+            },
+            father,
+        )) = stack.pop()
+        {
+            let index = rpe.judge_line_list.len() as i32;
+            stack.extend(children.into_iter().rev().map(|child| (child, index)));
+
+            let mut line = JudgeLine {
+                name: properties.name,
+                texture: properties.texture.unwrap_or_else(default_texture),
+                z_order: properties.z_order,
+                is_cover: i32::from(properties.hide_notes_below),
+                father,
+                rotate_with_father: properties.inheritance.rotation,
+                ..Default::default()
+            };
             for note in notes {
                 let kind = match note.kind {
                     crate::note::NoteKind::Tap => NoteKind::Tap,
@@ -331,29 +843,63 @@ impl Format for RpeChart {
                     position_x: note.x,
                     speed: note.speed,
                     start_time: note.beat.into(),
-                    size: 1.0,
-                    visible_time: 999999.0,
+                    size: note.size,
+                    visible_time: note.visible_time.unwrap_or(ALWAYS_VISIBLE_TIME),
+                    is_fake: i32::from(note.fake),
+                    alpha: note.alpha.round() as i32,
+                    y_offset: note.y_offset,
                     kind,
                 });
             }
             let mut event_layer = EventLayer::default();
-            for event in events {
+            for (event_index, event) in events.into_iter().enumerate() {
+                let mut unsupported = || {
+                    report.push(
+                        Some(Location::Event {
+                            line: paths[index as usize].clone(),
+                            index: event_index,
+                        }),
+                        LossKind::UnsupportedEasing {
+                            kind: event.kind,
+                            easing: event.easing,
+                        },
+                    );
+                };
+
                 let mut rpe_event = CommonEvent {
                     bezier: 0,
                     bezier_points: [0.0, 0.0, 0.0, 0.0],
                     easing_type: 0,
+                    easing_left: 0.0,
+                    easing_right: 1.0,
                     end: event.end,
                     end_time: event.end_beat.into(),
                     start: event.start,
                     start_time: event.start_beat.into(),
                 };
 
-                if let Easing::Custom(a, b, c, d) = event.easing {
-                    rpe_event.bezier_points = [a, b, c, d];
-                    rpe_event.bezier = 1;
-                    rpe_event.easing_type = 1;
-                } else {
-                    rpe_event.easing_type = e(event.easing) as i32;
+                match event.easing {
+                    Easing::Custom(a, b, c, d) => {
+                        rpe_event.bezier_points = [a, b, c, d];
+                        rpe_event.bezier = 1;
+                        rpe_event.easing_type = 1;
+                    }
+                    Easing::Partial(kind, left, right) => {
+                        let easing_type =
+                            Easing::from_kind(kind).and_then(e).unwrap_or_else(|| {
+                                unsupported();
+                                1
+                            });
+                        rpe_event.easing_type = easing_type as i32;
+                        rpe_event.easing_left = left;
+                        rpe_event.easing_right = right;
+                    }
+                    easing => {
+                        rpe_event.easing_type = e(easing).unwrap_or_else(|| {
+                            unsupported();
+                            1
+                        }) as i32;
+                    }
                 }
 
                 match event.kind {
@@ -369,6 +915,8 @@ impl Format for RpeChart {
                             bezier: rpe_event.bezier,
                             bezier_points: rpe_event.bezier_points,
                             easing_type: rpe_event.easing_type,
+                            easing_left: rpe_event.easing_left,
+                            easing_right: rpe_event.easing_right,
                             end: -rpe_event.end,
                             end_time: rpe_event.end_time,
                             start: -rpe_event.start,
@@ -380,6 +928,8 @@ impl Format for RpeChart {
                             bezier: rpe_event.bezier,
                             bezier_points: rpe_event.bezier_points,
                             easing_type: rpe_event.easing_type,
+                            easing_left: rpe_event.easing_left,
+                            easing_right: rpe_event.easing_right,
                             end: rpe_event.end as i32,
                             end_time: rpe_event.end_time,
                             start: rpe_event.start as i32,
@@ -394,6 +944,24 @@ impl Format for RpeChart {
                             start_time: event.start_beat.into(),
                         })
                     }
+                    LineEventKind::ScaleX => {
+                        line.extended.scale_xevents.push(rpe_event);
+                    }
+                    LineEventKind::ScaleY => {
+                        line.extended.scale_yevents.push(rpe_event);
+                    }
+                    LineEventKind::Color => {
+                        line.extended.color_events.push(
+                            rpe_event
+                                .with_values(unpack_color(event.start), unpack_color(event.end)),
+                        );
+                    }
+                    LineEventKind::Text => {
+                        let text = event.text.map(|x| x.to_string()).unwrap_or_default();
+                        line.extended
+                            .text_events
+                            .push(rpe_event.with_values(text.clone(), text));
+                    }
                 }
             }
             line.event_layers.push(event_layer);
@@ -404,3 +972,308 @@ impl Format for RpeChart {
         Ok(rpe)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LineEventKind;
+
+    fn event(
+        start_beat: crate::beat::Beat,
+        end_beat: crate::beat::Beat,
+        start: f32,
+        end: f32,
+        easing: Easing,
+    ) -> primitive::event::LineEvent {
+        primitive::event::LineEvent {
+            kind: LineEventKind::X,
+            start_beat,
+            end_beat,
+            start,
+            end,
+            easing,
+            text: None,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_single_layer() {
+        let layer = vec![event(beat!(0), beat!(1), 0.0, 100.0, Easing::EaseInSine)];
+        assert_eq!(merge_layers(vec![layer.clone(), vec![]]), layer);
+    }
+
+    #[test]
+    fn test_merge_layers_sum() {
+        let base = vec![event(beat!(0), beat!(2), 100.0, 100.0, Easing::Linear)];
+        let additive = vec![event(beat!(0), beat!(2), 0.0, 50.0, Easing::EaseOutQuad)];
+
+        assert_eq!(
+            merge_layers(vec![base, additive]),
+            vec![event(beat!(0), beat!(2), 100.0, 150.0, Easing::EaseOutQuad)]
+        );
+    }
+
+    #[test]
+    fn test_merge_layers_spanning_event() {
+        let long = vec![event(beat!(0), beat!(4), 0.0, 100.0, Easing::EaseInQuad)];
+        let steps = vec![
+            event(beat!(0), beat!(1), 10.0, 10.0, Easing::Linear),
+            event(beat!(1), beat!(2), 10.0, 10.0, Easing::Linear),
+        ];
+
+        // the other layer stays the same, so the event is kept whole
+        assert_eq!(
+            merge_layers(vec![long.clone(), steps]),
+            vec![event(beat!(0), beat!(4), 10.0, 110.0, Easing::EaseInQuad)]
+        );
+
+        // the other layer jumps at beat 2, the event is cut into two parts of its easing
+        let steps = vec![
+            event(beat!(0), beat!(2), 10.0, 10.0, Easing::Linear),
+            event(beat!(2), beat!(3), 20.0, 20.0, Easing::Linear),
+        ];
+        assert_eq!(
+            merge_layers(vec![long, steps]),
+            vec![
+                event(
+                    beat!(0),
+                    beat!(2),
+                    10.0,
+                    35.0,
+                    Easing::EaseInQuad.partial(0.0, 0.5)
+                ),
+                event(
+                    beat!(2),
+                    beat!(4),
+                    45.0,
+                    120.0,
+                    Easing::EaseInQuad.partial(0.5, 1.0)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_layers_baked() {
+        let a = vec![event(beat!(0), beat!(1), 0.0, 32.0, Easing::Linear)];
+        let b = vec![event(beat!(0), beat!(1), 0.0, 32.0, Easing::Linear)];
+
+        let merged = merge_layers(vec![a, b]);
+        assert_eq!(merged.len(), 32);
+        assert_eq!(merged[0].start, 0.0);
+        assert_eq!(merged[31].end, 64.0);
+    }
+
+    #[test]
+    fn test_father_round_trip() {
+        let line = |events, children| primitive::line::Line {
+            line: Default::default(),
+            notes: vec![],
+            events,
+            children,
+        };
+        let x = |value| vec![event(beat!(0), beat!(1), value, value, Easing::Linear)];
+
+        let primitive = PrimitiveChart {
+            lines: vec![
+                line(x(1.0), vec![line(x(2.0), vec![line(x(3.0), vec![])])]),
+                line(x(4.0), vec![]),
+            ],
+            ..Default::default()
+        };
+
+        let rpe = RpeChart::from_primitive(primitive.clone()).unwrap();
+        assert_eq!(
+            rpe.judge_line_list
+                .iter()
+                .map(|line| line.father)
+                .collect::<Vec<_>>(),
+            vec![-1, 0, 1, -1]
+        );
+
+        assert_eq!(rpe.into_primitive().unwrap().lines, primitive.lines);
+    }
+
+    #[test]
+    fn test_father_cycle() {
+        let mut rpe = RpeChart {
+            judge_line_list: vec![JudgeLine::default(); 3],
+            ..Default::default()
+        };
+        rpe.judge_line_list[0].father = -1;
+        rpe.judge_line_list[1].father = 2;
+        rpe.judge_line_list[2].father = 1;
+
+        let mut report = ConversionReport::default();
+        let primitive = rpe.into_primitive_with_report(&mut report).unwrap();
+        assert_eq!(primitive.lines.len(), 2);
+        assert_eq!(primitive.lines[1].children.len(), 1);
+        assert_eq!(
+            report.losses,
+            vec![crate::report::Loss {
+                location: Some(Location::Line { line: vec![1] }),
+                kind: LossKind::ParentCycle,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_report_unknown_easing() {
+        let mut rpe = RpeChart {
+            judge_line_list: vec![JudgeLine::default(); 2],
+            ..Default::default()
+        };
+        rpe.judge_line_list[0].father = -1;
+        rpe.judge_line_list[1].father = 5;
+        rpe.judge_line_list[1].event_layers.push(EventLayer {
+            move_xevents: vec![CommonEvent {
+                easing_type: 100,
+                start_time: beat!(0).into(),
+                end_time: beat!(1).into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        let mut report = ConversionReport::default();
+        let primitive = rpe.into_primitive_with_report(&mut report).unwrap();
+        assert_eq!(primitive.lines[1].events[0].easing, Easing::Linear);
+        assert_eq!(
+            report
+                .losses
+                .into_iter()
+                .map(|loss| loss.kind)
+                .collect::<Vec<_>>(),
+            vec![
+                LossKind::UnknownEasing {
+                    easing: "100".to_owned()
+                },
+                LossKind::InvalidParent { parent: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_properties_round_trip() {
+        let properties = crate::line::Line {
+            name: "Text Line".to_owned(),
+            texture: Some("textures/arrow.png".to_owned()),
+            z_order: 3,
+            hide_notes_below: true,
+            inheritance: crate::line::LineInheritance {
+                rotation: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let primitive = PrimitiveChart {
+            lines: vec![
+                primitive::line::Line {
+                    line: properties.clone(),
+                    ..Default::default()
+                },
+                primitive::line::Line::default(),
+            ],
+            ..Default::default()
+        };
+
+        let rpe = RpeChart::from_primitive(primitive).unwrap();
+        assert_eq!(rpe.judge_line_list[0].texture, "textures/arrow.png");
+        assert_eq!(rpe.judge_line_list[0].z_order, 3);
+        assert_eq!(rpe.judge_line_list[0].is_cover, 1);
+        assert_eq!(rpe.judge_line_list[1].texture, DEFAULT_TEXTURE);
+
+        let lines = rpe.into_primitive().unwrap().lines;
+        assert_eq!(lines[0].line, properties);
+        assert_eq!(lines[1].line, crate::line::Line::default());
+    }
+
+    #[test]
+    fn test_easing_round_trip() {
+        let events = vec![
+            event(
+                beat!(0),
+                beat!(1),
+                0.0,
+                1.0,
+                Easing::Custom(0.1, 0.2, 0.3, 0.4),
+            ),
+            event(
+                beat!(1),
+                beat!(2),
+                1.0,
+                2.0,
+                Easing::EaseInQuad.partial(0.25, 0.5),
+            ),
+            event(beat!(2), beat!(3), 2.0, 3.0, Easing::EaseOutSine),
+        ];
+        let primitive = PrimitiveChart {
+            lines: vec![primitive::line::Line {
+                line: Default::default(),
+                notes: vec![],
+                events: events.clone(),
+                children: vec![],
+            }],
+            ..Default::default()
+        };
+
+        let rpe = RpeChart::from_primitive(primitive).unwrap();
+        let x_events = &rpe.judge_line_list[0].event_layers[0].move_xevents;
+        assert_eq!(x_events[0].bezier, 1);
+        assert_eq!(x_events[0].bezier_points, [0.1, 0.2, 0.3, 0.4]);
+        assert_eq!(x_events[1].bezier, 0);
+        assert_eq!(x_events[1].easing_left, 0.25);
+        assert_eq!(x_events[1].easing_right, 0.5);
+        assert_eq!(x_events[2].easing_right, 1.0);
+
+        assert_eq!(rpe.into_primitive().unwrap().lines[0].events, events);
+    }
+
+    #[test]
+    fn test_extended_events_round_trip() {
+        let events = vec![
+            primitive::event::LineEvent {
+                kind: LineEventKind::ScaleX,
+                ..event(beat!(0), beat!(1), 1.0, 2.0, Easing::EaseInQuad)
+            },
+            primitive::event::LineEvent {
+                kind: LineEventKind::ScaleY,
+                ..event(beat!(0), beat!(1), 2.0, 1.0, Easing::Linear)
+            },
+            primitive::event::LineEvent {
+                kind: LineEventKind::Color,
+                ..event(
+                    beat!(0),
+                    beat!(2),
+                    pack_color([255, 0, 0]),
+                    pack_color([0, 128, 255]),
+                    Easing::Linear,
+                )
+            },
+            primitive::event::LineEvent {
+                kind: LineEventKind::Text,
+                text: Some(ArcIntern::new("phichain".to_owned())),
+                id: None,
+                ..event(beat!(1), beat!(2), 0.0, 0.0, Easing::Linear)
+            },
+        ];
+        let primitive = PrimitiveChart {
+            lines: vec![primitive::line::Line {
+                line: Default::default(),
+                notes: vec![],
+                events: events.clone(),
+                children: vec![],
+            }],
+            ..Default::default()
+        };
+
+        let rpe = RpeChart::from_primitive(primitive).unwrap();
+        let extended = &rpe.judge_line_list[0].extended;
+        assert_eq!(extended.color_events[0].start, [255, 0, 0]);
+        assert_eq!(extended.color_events[0].end, [0, 128, 255]);
+        assert_eq!(extended.text_events[0].start, "phichain");
+
+        assert_eq!(rpe.into_primitive().unwrap().lines[0].events, events);
+    }
+}
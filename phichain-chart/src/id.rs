@@ -0,0 +1,135 @@
+//! Persistent identifiers of lines, notes and events
+//!
+//! Identifiers are stored in the chart, so tools outside the editor can refer to a line, a note or an event
+//! regardless of its position in the chart
+
+use crate::event::LineEvent;
+use crate::line::Line;
+use crate::note::Note;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+/// A randomly generated identifier, serialized as a 16-digit hexadecimal string
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(u64);
+
+impl Id {
+    /// Generate a new random identifier
+    ///
+    /// Identifiers are random instead of sequential, so lines, notes and events created
+    /// by different charters on the same chart are unlikely to collide when merging
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self(rand::random())
+    }
+}
+
+/// Something carrying an optional persistent identifier
+pub trait Identifiable {
+    fn id(&self) -> Option<Id>;
+
+    fn set_id(&mut self, id: Id);
+}
+
+macro_rules! impl_identifiable {
+    ($($ty:ty),*) => {
+        $(
+            impl Identifiable for $ty {
+                fn id(&self) -> Option<Id> {
+                    self.id
+                }
+
+                fn set_id(&mut self, id: Id) {
+                    self.id = Some(id);
+                }
+            }
+        )*
+    };
+}
+
+impl_identifiable!(Line, Note, LineEvent);
+
+impl Display for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl Debug for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Id({})", self)
+    }
+}
+
+impl FromStr for Id {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::Format;
+    use crate::serialization::PhichainChart;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_unique() {
+        let ids = (0..1000).map(|_| Id::new()).collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 1000);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let id = Id(0x1234abcd);
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"000000001234abcd\"");
+        assert_eq!(
+            serde_json::from_str::<Id>("\"000000001234abcd\"").unwrap(),
+            id
+        );
+        assert!(serde_json::from_str::<Id>("\"xyz\"").is_err());
+    }
+
+    #[test]
+    fn test_primitive_round_trip() {
+        let mut chart = PhichainChart::default();
+        chart.assign_ids();
+        let ids = |chart: &PhichainChart| {
+            chart.lines[0]
+                .events
+                .iter()
+                .map(|event| event.id)
+                .collect::<Vec<_>>()
+        };
+
+        let (event_ids, line_id) = (ids(&chart), chart.lines[0].line.id);
+
+        let primitive = chart.into_primitive().unwrap();
+        let converted = PhichainChart::from_primitive(primitive).unwrap();
+        assert_eq!(ids(&converted), event_ids);
+        assert_eq!(converted.lines[0].line.id, line_id);
+    }
+}
@@ -1,13 +1,22 @@
 pub mod beat;
 pub mod bpm_list;
 pub mod constants;
+pub mod diff;
 pub mod easing;
 pub mod event;
+pub mod expression;
 pub mod format;
+pub mod id;
 pub mod line;
+pub mod lint;
 pub mod migration;
 pub mod note;
 pub mod offset;
+pub mod operation;
 pub mod primitive;
 pub mod project;
+pub mod report;
+pub mod retime;
 pub mod serialization;
+pub mod statistics;
+pub mod time_signature;
@@ -1,15 +1,64 @@
+use crate::id::Id;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
 pub struct Line {
     pub name: String,
+    /// Path of the texture image relative to the project directory, [`None`] to use the default line texture
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Lines with a higher z-order are rendered above lines with a lower z-order
+    ///
+    /// For child lines, the z-order is relative to the parent line
+    #[serde(default)]
+    pub z_order: i32,
+    /// Whether notes below the line are hidden before they reach the line
+    #[serde(default)]
+    pub hide_notes_below: bool,
+    /// How a child line inherits the state of its parent line, ignored for root lines
+    #[serde(default)]
+    pub inheritance: LineInheritance,
+    /// Persistent identifier of the line, see [`Id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Id>,
 }
 
 impl Default for Line {
     fn default() -> Self {
         Self {
             name: "Unnamed Line".to_owned(),
+            texture: None,
+            z_order: 0,
+            hide_notes_below: false,
+            inheritance: LineInheritance::default(),
+            id: None,
+        }
+    }
+}
+
+/// Which parts of the parent line's state are applied to a child line
+///
+/// The position of a child line is always relative to its parent, rotated by the rotation of the parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineInheritance {
+    /// Add the rotation of the parent to the rotation of the child, `rotateWithFather` in Phira
+    pub rotation: bool,
+    /// Multiply the opacity of the child by the opacity of the parent
+    pub opacity: bool,
+    /// Add the speed of the parent to the speed of the child
+    pub speed: bool,
+    /// Multiply the scale of the child by the scale of the parent
+    pub scale: bool,
+}
+
+impl Default for LineInheritance {
+    fn default() -> Self {
+        Self {
+            rotation: true,
+            opacity: false,
+            speed: false,
+            scale: false,
         }
     }
 }
@@ -26,6 +75,27 @@ pub struct LineRotation(pub f32);
 #[derive(bevy::prelude::Component, Debug, Default)]
 pub struct LineOpacity(pub f32);
 
+#[cfg(feature = "bevy")]
+#[derive(bevy::prelude::Component, Debug)]
+pub struct LineScale(pub bevy::prelude::Vec2);
+
+#[cfg(feature = "bevy")]
+impl Default for LineScale {
+    fn default() -> Self {
+        Self(bevy::prelude::Vec2::ONE)
+    }
+}
+
+/// The color of the line, [`None`] if the line has no color events
+#[cfg(feature = "bevy")]
+#[derive(bevy::prelude::Component, Debug, Default)]
+pub struct LineColor(pub Option<bevy::prelude::Color>);
+
+/// The text of the line, [`None`] if the line is not a text line at the moment
+#[cfg(feature = "bevy")]
+#[derive(bevy::prelude::Component, Debug, Default)]
+pub struct LineText(pub Option<String>);
+
 /// This will not affect line entity, it is only used to show realtime speed of lines in [phichain::tab::line_list]
 #[cfg(feature = "bevy")]
 #[derive(bevy::prelude::Component, Debug, Default)]
@@ -40,6 +110,9 @@ pub struct LineBundle {
     rotation: LineRotation,
     opacity: LineOpacity,
     speed: LineSpeed,
+    scale: LineScale,
+    color: LineColor,
+    text: LineText,
 }
 
 #[cfg(feature = "bevy")]
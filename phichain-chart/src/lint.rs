@@ -0,0 +1,462 @@
+//! Check a chart for problems before exporting
+
+use crate::beat::Beat;
+use crate::event::{LineEvent, LineEventKind};
+use crate::serialization::{LineWrapper, PhichainChart};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The chart can be exported, but the result may not be what the charter expects
+    Warning,
+    /// The chart is broken and should be fixed before exporting
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// Where a [`Diagnostic`] is found
+///
+/// Lines are located by their index path, e.g. `[1, 0]` is the first child of the second line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Location {
+    BpmPoint { index: usize },
+    Line { line: Vec<usize> },
+    Note { line: Vec<usize>, index: usize },
+    Event { line: Vec<usize>, index: usize },
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = |line: &[usize]| {
+            line.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        match self {
+            Location::BpmPoint { index } => write!(f, "bpm point #{}", index),
+            Location::Line { line } => write!(f, "line {}", path(line)),
+            Location::Note { line, index } => write!(f, "line {}, note #{}", path(line), index),
+            Location::Event { line, index } => write!(f, "line {}, event #{}", path(line), index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum DiagnosticKind {
+    /// The event overlaps with another event of the same kind on the same line
+    OverlappingEvents { kind: LineEventKind, other: usize },
+    /// The event ends before it starts
+    ReversedEvent { start_beat: Beat, end_beat: Beat },
+    /// The hold note has a zero or negative length
+    InvalidHoldLength { hold_beat: Beat },
+    /// The note is placed after the end of the music
+    NoteAfterMusicEnd { time: f32, duration: f32 },
+    /// The line has neither notes nor child lines
+    EmptyLine,
+    /// The bpm point is not placed after the previous bpm point
+    BpmPointOutOfOrder { beat: Beat, previous: Beat },
+    /// The beat has a non-zero float part, which is dropped on serialization
+    FloatPartBeat { beat: Beat },
+}
+
+impl DiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::ReversedEvent { .. }
+            | DiagnosticKind::InvalidHoldLength { .. }
+            | DiagnosticKind::BpmPointOutOfOrder { .. } => Severity::Error,
+            DiagnosticKind::OverlappingEvents { .. }
+            | DiagnosticKind::NoteAfterMusicEnd { .. }
+            | DiagnosticKind::EmptyLine
+            | DiagnosticKind::FloatPartBeat { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticKind::OverlappingEvents { kind, other } => {
+                write!(f, "{:?} event overlaps with event #{}", kind, other)
+            }
+            DiagnosticKind::ReversedEvent {
+                start_beat,
+                end_beat,
+            } => write!(
+                f,
+                "event ends at {:?} before it starts at {:?}",
+                end_beat, start_beat
+            ),
+            DiagnosticKind::InvalidHoldLength { hold_beat } => {
+                write!(f, "hold has a non-positive length {:?}", hold_beat)
+            }
+            DiagnosticKind::NoteAfterMusicEnd { time, duration } => write!(
+                f,
+                "note at {:.3}s is placed after the end of the music at {:.3}s",
+                time, duration
+            ),
+            DiagnosticKind::EmptyLine => f.write_str("line has neither notes nor child lines"),
+            DiagnosticKind::BpmPointOutOfOrder { beat, previous } => write!(
+                f,
+                "bpm point at {:?} is not placed after the previous bpm point at {:?}",
+                beat, previous
+            ),
+            DiagnosticKind::FloatPartBeat { beat } => write!(
+                f,
+                "beat {:?} has a float part {}, which will be dropped on saving",
+                beat,
+                beat.float()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Location,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn new(location: Location, kind: DiagnosticKind) -> Self {
+        Self {
+            severity: kind.severity(),
+            location,
+            kind,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.location, self.kind)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// Duration of the music in seconds, notes after it are reported if given
+    pub music_duration: Option<f32>,
+}
+
+/// Check a chart for problems, returning diagnostics in the order of bpm points and lines
+pub fn lint(chart: &PhichainChart, options: &LintOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for (index, point) in chart.bpm_list.0.iter().enumerate() {
+        let location = || Location::BpmPoint { index };
+        check_float_part(point.beat, location, &mut diagnostics);
+        if let Some(previous) = index.checked_sub(1).map(|x| chart.bpm_list.0[x].beat) {
+            if point.beat <= previous {
+                diagnostics.push(Diagnostic::new(
+                    location(),
+                    DiagnosticKind::BpmPointOutOfOrder {
+                        beat: point.beat,
+                        previous,
+                    },
+                ));
+            }
+        }
+    }
+
+    for (index, line) in chart.lines.iter().enumerate() {
+        lint_line(chart, line, vec![index], options, &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+fn lint_line(
+    chart: &PhichainChart,
+    line: &LineWrapper,
+    path: Vec<usize>,
+    options: &LintOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if line.notes.is_empty() && line.children.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Location::Line { line: path.clone() },
+            DiagnosticKind::EmptyLine,
+        ));
+    }
+
+    for (index, note) in line.notes.iter().enumerate() {
+        let location = || Location::Note {
+            line: path.clone(),
+            index,
+        };
+        check_float_part(note.beat, location, diagnostics);
+
+        let mut end_beat = note.beat;
+        if let crate::note::NoteKind::Hold { hold_beat } = note.kind {
+            check_float_part(hold_beat, location, diagnostics);
+            if hold_beat <= Beat::ZERO {
+                diagnostics.push(Diagnostic::new(
+                    location(),
+                    DiagnosticKind::InvalidHoldLength { hold_beat },
+                ));
+            }
+            end_beat += hold_beat;
+        }
+
+        if let Some(duration) = options.music_duration {
+            let time = chart.bpm_list.time_at(end_beat) + chart.offset.0 / 1000.0;
+            if time > duration {
+                diagnostics.push(Diagnostic::new(
+                    location(),
+                    DiagnosticKind::NoteAfterMusicEnd { time, duration },
+                ));
+            }
+        }
+    }
+
+    for (index, event) in line.events.iter().enumerate() {
+        let location = || Location::Event {
+            line: path.clone(),
+            index,
+        };
+        check_float_part(event.start_beat, location, diagnostics);
+        check_float_part(event.end_beat, location, diagnostics);
+        if event.end_beat < event.start_beat {
+            diagnostics.push(Diagnostic::new(
+                location(),
+                DiagnosticKind::ReversedEvent {
+                    start_beat: event.start_beat,
+                    end_beat: event.end_beat,
+                },
+            ));
+        }
+    }
+
+    // report each event overlapping with an earlier event of the same kind, compared with the earlier event
+    // reaching the furthest, since an event may overlap with a long event several events before it
+    let mut sorted = line.events.iter().enumerate().collect::<Vec<_>>();
+    sorted.sort_by_key(|(_, e)| (e.kind as u8, e.start_beat));
+    let mut furthest: Option<(usize, &LineEvent)> = None;
+    for (index, event) in sorted {
+        match furthest {
+            Some((other, previous)) if previous.kind == event.kind => {
+                if event.start_beat < previous.end_beat {
+                    diagnostics.push(Diagnostic::new(
+                        Location::Event {
+                            line: path.clone(),
+                            index,
+                        },
+                        DiagnosticKind::OverlappingEvents {
+                            kind: event.kind,
+                            other,
+                        },
+                    ));
+                }
+                if event.end_beat > previous.end_beat {
+                    furthest = Some((index, event));
+                }
+            }
+            _ => furthest = Some((index, event)),
+        }
+    }
+
+    for (index, child) in line.children.iter().enumerate() {
+        let mut path = path.clone();
+        path.push(index);
+        lint_line(chart, child, path, options, diagnostics);
+    }
+}
+
+fn check_float_part(
+    beat: Beat,
+    location: impl Fn() -> Location,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if beat.float() != 0.0 {
+        diagnostics.push(Diagnostic::new(
+            location(),
+            DiagnosticKind::FloatPartBeat { beat },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+    use crate::bpm_list::{BpmList, BpmPoint};
+    use crate::event::{LineEvent, LineEventValue};
+    use crate::note::{Note, NoteKind};
+
+    fn kinds(chart: &PhichainChart, options: &LintOptions) -> Vec<(Location, DiagnosticKind)> {
+        lint(chart, options)
+            .into_iter()
+            .map(|x| (x.location, x.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_chart() {
+        let mut chart = PhichainChart::default();
+        chart.lines[0]
+            .notes
+            .push(Note::new(NoteKind::Tap, true, beat!(1), 0.0, 1.0));
+        assert!(lint(&chart, &LintOptions::default()).is_empty());
+
+        chart.lines[0].notes.clear();
+        assert_eq!(
+            kinds(&chart, &LintOptions::default()),
+            vec![(Location::Line { line: vec![0] }, DiagnosticKind::EmptyLine)]
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let mut chart = PhichainChart {
+            bpm_list: BpmList::new(vec![
+                BpmPoint::new(beat!(0), 120.0),
+                BpmPoint::new(beat!(4), 120.0),
+            ]),
+            ..Default::default()
+        };
+        // bypass the sorting of `BpmList::insert`
+        chart.bpm_list.0[1].beat = beat!(0);
+        chart.bpm_list.compute();
+
+        let line = &mut chart.lines[0];
+        line.notes.push(Note::new(
+            NoteKind::Hold {
+                hold_beat: beat!(0),
+            },
+            true,
+            beat!(1),
+            0.0,
+            1.0,
+        ));
+        line.notes
+            .push(Note::new(NoteKind::Tap, true, beat!(10), 0.0, 1.0));
+        line.events.push(LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(2),
+            end_beat: beat!(1),
+            value: LineEventValue::constant(0.0),
+            id: None,
+        });
+        line.events.push(LineEvent {
+            kind: LineEventKind::Speed,
+            start_beat: beat!(1, 2),
+            end_beat: beat!(2),
+            value: LineEventValue::constant(0.0),
+            id: None,
+        });
+        *line.events[0].start_beat.float_mut() = 0.1;
+
+        let diagnostics = kinds(
+            &chart,
+            &LintOptions {
+                music_duration: Some(3.0),
+            },
+        );
+        let location = |index| Location::Event {
+            line: vec![0],
+            index,
+        };
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    Location::BpmPoint { index: 1 },
+                    DiagnosticKind::BpmPointOutOfOrder {
+                        beat: beat!(0),
+                        previous: beat!(0)
+                    }
+                ),
+                (
+                    Location::Note {
+                        line: vec![0],
+                        index: 0
+                    },
+                    DiagnosticKind::InvalidHoldLength {
+                        hold_beat: beat!(0)
+                    }
+                ),
+                (
+                    Location::Note {
+                        line: vec![0],
+                        index: 1
+                    },
+                    DiagnosticKind::NoteAfterMusicEnd {
+                        time: 5.0,
+                        duration: 3.0
+                    }
+                ),
+                (
+                    location(0),
+                    DiagnosticKind::FloatPartBeat {
+                        beat: chart.lines[0].events[0].start_beat
+                    }
+                ),
+                (
+                    location(5),
+                    DiagnosticKind::ReversedEvent {
+                        start_beat: beat!(2),
+                        end_beat: beat!(1)
+                    }
+                ),
+                (
+                    location(6),
+                    DiagnosticKind::OverlappingEvents {
+                        kind: LineEventKind::Speed,
+                        other: 4
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_events() {
+        let mut chart = PhichainChart::default();
+        let line = &mut chart.lines[0];
+        line.events.clear();
+        for (start_beat, end_beat) in [(0, 10), (1, 2), (3, 4), (10, 12), (11, 13)] {
+            line.events.push(LineEvent {
+                kind: LineEventKind::X,
+                start_beat: beat!(start_beat),
+                end_beat: beat!(end_beat),
+                value: LineEventValue::constant(0.0),
+                id: None,
+            });
+        }
+
+        let location = |index| Location::Event {
+            line: vec![0],
+            index,
+        };
+        let overlapping = |other| DiagnosticKind::OverlappingEvents {
+            kind: LineEventKind::X,
+            other,
+        };
+        assert_eq!(
+            kinds(&chart, &LintOptions::default())
+                .into_iter()
+                .filter(|(_, kind)| matches!(kind, DiagnosticKind::OverlappingEvents { .. }))
+                .collect::<Vec<_>>(),
+            vec![
+                (location(1), overlapping(0)),
+                (location(2), overlapping(0)),
+                (location(4), overlapping(3)),
+            ]
+        );
+    }
+}
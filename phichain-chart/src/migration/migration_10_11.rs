@@ -0,0 +1,71 @@
+use crate::migration::Migration;
+use serde_json::{json, Value};
+
+/// Migration from format `10` to `11`
+///
+/// # Changes
+///
+/// - Added `inheritance` to lines, which parts of the parent line's state are applied to a child line
+///
+/// # Modifications
+///
+/// - No data changes, lines without `inheritance` use the default inheritance, the format is bumped so older versions refuse charts that may contain it
+pub struct Migration10To11;
+
+impl Migration for Migration10To11 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        chart["format"] = json!(11);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_10_to_11() {
+        let old = json!({
+          "format": 10,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        let new = json!({
+          "format": 11,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        assert_eq!(Migration10To11::migrate(&old).unwrap(), new);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::migration::Migration;
+use serde_json::{json, Value};
+
+/// Migration from format `11` to `12`
+///
+/// # Changes
+///
+/// - Added event value type `expression` for math expressions over the local time of events
+///
+/// # Modifications
+///
+/// - No data changes, the format is bumped so older versions refuse charts that may contain expression events
+pub struct Migration11To12;
+
+impl Migration for Migration11To12 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        chart["format"] = json!(12);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_11_to_12() {
+        let old = json!({
+          "format": 11,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        let new = json!({
+          "format": 12,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        assert_eq!(Migration11To12::migrate(&old).unwrap(), new);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::migration::Migration;
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Migration from format `4` to `5`
+///
+/// # Changes
+///
+/// - Added extended attributes for notes: `fake`, `alpha`, `size`, `visible_time` and `y_offset`
+///
+/// # Modifications
+///
+/// - Added default values of the extended attributes to all notes, including notes of child lines
+pub struct Migration4To5;
+
+fn migrate_line(line: &mut Value) -> anyhow::Result<()> {
+    for note in line["notes"]
+        .as_array_mut()
+        .context("`line.notes` is not an array")?
+    {
+        note["fake"] = json!(false);
+        note["alpha"] = json!(255.0);
+        note["size"] = json!(1.0);
+        note["visible_time"] = json!(null);
+        note["y_offset"] = json!(0.0);
+    }
+
+    for child in line["children"]
+        .as_array_mut()
+        .context("`line.children` is not an array")?
+    {
+        migrate_line(child)?;
+    }
+
+    Ok(())
+}
+
+impl Migration for Migration4To5 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        for line in chart
+            .get_mut("lines")
+            .context("Failed to get lines")?
+            .as_array_mut()
+            .context("`lines` is not an array")?
+        {
+            migrate_line(line)?;
+        }
+
+        chart["format"] = json!(5);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_4_to_5() {
+        let old = json!({
+          "format": 4,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "notes": [
+                {
+                  "kind": "tap",
+                  "above": true,
+                  "beat": [0, 1, 1],
+                  "x": 0.0,
+                  "speed": 3.0
+                }
+              ],
+              "events": [],
+              "children": [
+                {
+                  "name": "Child Line",
+                  "notes": [
+                    {
+                      "kind": {
+                        "hold": {
+                          "hold_beat": [1, 0, 1],
+                        },
+                      },
+                      "above": false,
+                      "beat": [1, 0, 1],
+                      "x": 337.5,
+                      "speed": 1.0
+                    }
+                  ],
+                  "events": [],
+                  "children": []
+                }
+              ]
+            }
+          ]
+        });
+
+        let new = json!({
+          "format": 5,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "notes": [
+                {
+                  "kind": "tap",
+                  "above": true,
+                  "beat": [0, 1, 1],
+                  "x": 0.0,
+                  "speed": 3.0,
+                  "fake": false,
+                  "alpha": 255.0,
+                  "size": 1.0,
+                  "visible_time": null,
+                  "y_offset": 0.0
+                }
+              ],
+              "events": [],
+              "children": [
+                {
+                  "name": "Child Line",
+                  "notes": [
+                    {
+                      "kind": {
+                        "hold": {
+                          "hold_beat": [1, 0, 1],
+                        },
+                      },
+                      "above": false,
+                      "beat": [1, 0, 1],
+                      "x": 337.5,
+                      "speed": 1.0,
+                      "fake": false,
+                      "alpha": 255.0,
+                      "size": 1.0,
+                      "visible_time": null,
+                      "y_offset": 0.0
+                    }
+                  ],
+                  "events": [],
+                  "children": []
+                }
+              ]
+            }
+          ]
+        });
+
+        assert_eq!(Migration4To5::migrate(&old).unwrap(), new);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::migration::Migration;
+use serde_json::{json, Value};
+
+/// Migration from format `5` to `6`
+///
+/// # Changes
+///
+/// - Added line event kinds: `scale_x`, `scale_y`, `color` and `text`
+/// - Added event value type `text` for text events
+///
+/// # Modifications
+///
+/// - No data changes, the format is bumped so older versions refuse charts that may contain the new event kinds
+pub struct Migration5To6;
+
+impl Migration for Migration5To6 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        chart["format"] = json!(6);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_5_to_6() {
+        let old = json!({
+          "format": 5,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "notes": [],
+              "events": [
+                {
+                  "kind": "x",
+                  "start_beat": [0, 0, 1],
+                  "end_beat": [1, 0, 1],
+                  "value": {
+                    "constant": 0.0
+                  }
+                }
+              ],
+              "children": []
+            }
+          ]
+        });
+
+        let new = json!({
+          "format": 6,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "notes": [],
+              "events": [
+                {
+                  "kind": "x",
+                  "start_beat": [0, 0, 1],
+                  "end_beat": [1, 0, 1],
+                  "value": {
+                    "constant": 0.0
+                  }
+                }
+              ],
+              "children": []
+            }
+          ]
+        });
+
+        assert_eq!(Migration5To6::migrate(&old).unwrap(), new);
+    }
+}
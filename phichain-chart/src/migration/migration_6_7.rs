@@ -0,0 +1,118 @@
+use crate::migration::Migration;
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Migration from format `6` to `7`
+///
+/// # Changes
+///
+/// - Added line properties: `texture`, `z_order` and `hide_notes_below`
+///
+/// # Modifications
+///
+/// - Added default values of the line properties to all lines, including child lines
+pub struct Migration6To7;
+
+fn migrate_line(line: &mut Value) -> anyhow::Result<()> {
+    line["texture"] = json!(null);
+    line["z_order"] = json!(0);
+    line["hide_notes_below"] = json!(false);
+
+    for child in line["children"]
+        .as_array_mut()
+        .context("`line.children` is not an array")?
+    {
+        migrate_line(child)?;
+    }
+
+    Ok(())
+}
+
+impl Migration for Migration6To7 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        for line in chart
+            .get_mut("lines")
+            .context("Failed to get lines")?
+            .as_array_mut()
+            .context("`lines` is not an array")?
+        {
+            migrate_line(line)?;
+        }
+
+        chart["format"] = json!(7);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_6_to_7() {
+        let old = json!({
+          "format": 6,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "notes": [],
+              "events": [],
+              "children": [
+                {
+                  "name": "Child Line",
+                  "notes": [],
+                  "events": [],
+                  "children": []
+                }
+              ]
+            }
+          ]
+        });
+
+        let new = json!({
+          "format": 7,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "texture": null,
+              "z_order": 0,
+              "hide_notes_below": false,
+              "notes": [],
+              "events": [],
+              "children": [
+                {
+                  "name": "Child Line",
+                  "texture": null,
+                  "z_order": 0,
+                  "hide_notes_below": false,
+                  "notes": [],
+                  "events": [],
+                  "children": []
+                }
+              ]
+            }
+          ]
+        });
+
+        assert_eq!(Migration6To7::migrate(&old).unwrap(), new);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::id::Id;
+use crate::migration::Migration;
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Migration from format `7` to `8`
+///
+/// # Changes
+///
+/// - Added persistent `id` to lines, notes and events
+///
+/// # Modifications
+///
+/// - Assigned a new id to all lines, notes and events, including child lines and their notes and events
+pub struct Migration7To8;
+
+fn migrate_line(line: &mut Value) -> anyhow::Result<()> {
+    line["id"] = json!(Id::new());
+
+    for note in line["notes"]
+        .as_array_mut()
+        .context("`line.notes` is not an array")?
+    {
+        note["id"] = json!(Id::new());
+    }
+
+    for event in line["events"]
+        .as_array_mut()
+        .context("`line.events` is not an array")?
+    {
+        event["id"] = json!(Id::new());
+    }
+
+    for child in line["children"]
+        .as_array_mut()
+        .context("`line.children` is not an array")?
+    {
+        migrate_line(child)?;
+    }
+
+    Ok(())
+}
+
+impl Migration for Migration7To8 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        for line in chart
+            .get_mut("lines")
+            .context("Failed to get lines")?
+            .as_array_mut()
+            .context("`lines` is not an array")?
+        {
+            migrate_line(line)?;
+        }
+
+        chart["format"] = json!(8);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::PhichainChart;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_migration_7_to_8() {
+        let old = json!({
+          "format": 7,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0,
+              "time": 0.0
+            }
+          ],
+          "lines": [
+            {
+              "name": "Unnamed Line",
+              "texture": null,
+              "z_order": 0,
+              "hide_notes_below": false,
+              "notes": [
+                {
+                  "kind": "tap",
+                  "above": true,
+                  "beat": [0, 1, 1],
+                  "x": 0.0,
+                  "speed": 1.0,
+                  "fake": false,
+                  "alpha": 255.0,
+                  "size": 1.0,
+                  "visible_time": null,
+                  "y_offset": 0.0
+                }
+              ],
+              "events": [
+                {
+                  "kind": "x",
+                  "start_beat": [0, 0, 1],
+                  "end_beat": [1, 0, 1],
+                  "value": {
+                    "constant": 0.0
+                  }
+                }
+              ],
+              "children": [
+                {
+                  "name": "Child Line",
+                  "texture": null,
+                  "z_order": 0,
+                  "hide_notes_below": false,
+                  "notes": [],
+                  "events": [],
+                  "children": []
+                }
+              ]
+            }
+          ]
+        });
+
+        let new = Migration7To8::migrate(&old).unwrap();
+        assert_eq!(new["format"], json!(8));
+
+        let line = &new["lines"][0];
+        let ids = [
+            &line["id"],
+            &line["notes"][0]["id"],
+            &line["events"][0]["id"],
+            &line["children"][0]["id"],
+        ]
+        .iter()
+        .map(|x| x.as_str().unwrap().parse::<Id>().unwrap())
+        .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 4);
+
+        let chart: PhichainChart =
+            serde_json::from_value(crate::migration::migrate(&new).unwrap()).unwrap();
+        for id in ids {
+            assert!(
+                chart.line_by_id(id).is_some()
+                    || chart.note_by_id(id).is_some()
+                    || chart.event_by_id(id).is_some()
+            );
+        }
+        let child_id = chart.lines[0].children[0].line.id.unwrap();
+        assert_eq!(chart.line_by_id(child_id).unwrap().line.name, "Child Line");
+    }
+}
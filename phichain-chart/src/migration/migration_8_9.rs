@@ -0,0 +1,72 @@
+use crate::migration::Migration;
+use serde_json::{json, Value};
+
+/// Migration from format `8` to `9`
+///
+/// # Changes
+///
+/// - Added `time_signatures` to the chart
+///
+/// # Modifications
+///
+/// - Added a single 4/4 time signature starting from beat 0
+pub struct Migration8To9;
+
+impl Migration for Migration8To9 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        chart["time_signatures"] = json!([
+            {
+                "beat": [0, 0, 1],
+                "numerator": 4,
+                "denominator": 4
+            }
+        ]);
+
+        chart["format"] = json!(9);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_8_to_9() {
+        let old = json!({
+          "format": 8,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "lines": []
+        });
+
+        let new = json!({
+          "format": 9,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        assert_eq!(Migration8To9::migrate(&old).unwrap(), new);
+    }
+}
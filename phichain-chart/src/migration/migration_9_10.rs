@@ -0,0 +1,71 @@
+use crate::migration::Migration;
+use serde_json::{json, Value};
+
+/// Migration from format `9` to `10`
+///
+/// # Changes
+///
+/// - Added `ramp` to BPM points, an optional easing the BPM changes gradually with until the next point
+///
+/// # Modifications
+///
+/// - No data changes, the format is bumped so older versions refuse charts that may contain BPM ramps
+pub struct Migration9To10;
+
+impl Migration for Migration9To10 {
+    fn migrate(old: &Value) -> anyhow::Result<Value> {
+        let mut chart = old.clone();
+        chart["format"] = json!(10);
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_9_to_10() {
+        let old = json!({
+          "format": 9,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        let new = json!({
+          "format": 10,
+          "offset": 0.0,
+          "bpm_list": [
+            {
+              "beat": [0, 0, 1],
+              "bpm": 120.0
+            }
+          ],
+          "time_signatures": [
+            {
+              "beat": [0, 0, 1],
+              "numerator": 4,
+              "denominator": 4
+            }
+          ],
+          "lines": []
+        });
+
+        assert_eq!(Migration9To10::migrate(&old).unwrap(), new);
+    }
+}
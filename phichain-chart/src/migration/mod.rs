@@ -1,20 +1,36 @@
 use crate::migration::migration_0_1::Migration0To1;
+use crate::migration::migration_10_11::Migration10To11;
+use crate::migration::migration_11_12::Migration11To12;
 use crate::migration::migration_1_2::Migration1To2;
 use crate::migration::migration_2_3::Migration2To3;
 use crate::migration::migration_3_4::Migration3To4;
+use crate::migration::migration_4_5::Migration4To5;
+use crate::migration::migration_5_6::Migration5To6;
+use crate::migration::migration_6_7::Migration6To7;
+use crate::migration::migration_7_8::Migration7To8;
+use crate::migration::migration_8_9::Migration8To9;
+use crate::migration::migration_9_10::Migration9To10;
 use anyhow::{bail, Context};
 use serde_json::{json, Value};
 
 mod migration_0_1;
+mod migration_10_11;
+mod migration_11_12;
 mod migration_1_2;
 mod migration_2_3;
 mod migration_3_4;
+mod migration_4_5;
+mod migration_5_6;
+mod migration_6_7;
+mod migration_7_8;
+mod migration_8_9;
+mod migration_9_10;
 
 pub trait Migration {
     fn migrate(old: &Value) -> anyhow::Result<Value>;
 }
 
-pub const CURRENT_FORMAT: u64 = 4;
+pub const CURRENT_FORMAT: u64 = 12;
 
 fn get_format(chart: &Value) -> anyhow::Result<u64> {
     let version = chart
@@ -39,6 +55,14 @@ pub fn migrate(chart: &Value) -> anyhow::Result<Value> {
         1 => Migration1To2::migrate(chart)?,
         2 => Migration2To3::migrate(chart)?,
         3 => Migration3To4::migrate(chart)?,
+        4 => Migration4To5::migrate(chart)?,
+        5 => Migration5To6::migrate(chart)?,
+        6 => Migration6To7::migrate(chart)?,
+        7 => Migration7To8::migrate(chart)?,
+        8 => Migration8To9::migrate(chart)?,
+        9 => Migration9To10::migrate(chart)?,
+        10 => Migration10To11::migrate(chart)?,
+        11 => Migration11To12::migrate(chart)?,
         _ => bail!("Unsupported chart format {}", format),
     };
 
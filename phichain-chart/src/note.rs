@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
 use crate::beat::Beat;
+use crate::id::Id;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -35,6 +36,35 @@ pub struct Note {
     pub beat: Beat,
     pub x: f32,
     pub speed: f32,
+
+    /// Fake notes are rendered as usual, but they can not be hit and are not counted into the score
+    #[serde(default)]
+    pub fake: bool,
+    /// The opacity of the note, ranges from 0 to 255
+    #[serde(default = "default_alpha")]
+    pub alpha: f32,
+    /// The horizontal scale of the note
+    #[serde(default = "default_size")]
+    pub size: f32,
+    /// How many seconds before being hit the note starts to show up
+    ///
+    /// [`None`] means the note is always visible
+    #[serde(default)]
+    pub visible_time: Option<f32>,
+    /// The offset of the note along its falling direction
+    #[serde(default)]
+    pub y_offset: f32,
+    /// Persistent identifier of the note, see [`Id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Id>,
+}
+
+fn default_alpha() -> f32 {
+    255.0
+}
+
+fn default_size() -> f32 {
+    1.0
 }
 
 impl PartialOrd for Note {
@@ -51,6 +81,12 @@ impl Note {
             beat,
             x,
             speed,
+            fake: false,
+            alpha: default_alpha(),
+            size: default_size(),
+            visible_time: None,
+            y_offset: 0.0,
+            id: None,
         }
     }
 
@@ -88,6 +124,14 @@ impl Note {
         }
     }
 
+    /// Check if the note is visible at the given time, `note_time` is the time when the note is hit
+    pub fn is_visible_at(&self, time: f32, note_time: f32) -> bool {
+        match self.visible_time {
+            Some(visible_time) => time >= note_time - visible_time,
+            None => true,
+        }
+    }
+
     /// Set the end beat of this [`Note`]
     ///
     /// This only has effect when `self.kind` is [`NoteKind::Hold`]
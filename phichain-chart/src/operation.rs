@@ -0,0 +1,526 @@
+//! Operations on whole charts: slicing a time range, concatenating charts and scaling the tempo
+
+use crate::beat::Beat;
+use crate::bpm_list::{BpmList, BpmPoint, RAMP_SAMPLE_DENSITY};
+use crate::event::{LineEvent, LineEventKind, LineEventValue};
+use crate::migration::CURRENT_FORMAT;
+use crate::note::{Note, NoteKind};
+use crate::offset::Offset;
+use crate::serialization::{LineWrapper, PhichainChart};
+use crate::time_signature::{TimeSignatureList, TimeSignaturePoint};
+use anyhow::bail;
+use strum::IntoEnumIterator;
+
+impl PhichainChart {
+    /// The latest beat any note or event of the chart reaches
+    pub fn end_beat(&self) -> Beat {
+        fn line_end(line: &LineWrapper) -> Beat {
+            let notes = line.notes.iter().map(|note| match note.kind {
+                NoteKind::Hold { hold_beat } => note.beat + hold_beat,
+                _ => note.beat,
+            });
+            let events = line.events.iter().map(|event| event.end_beat);
+            let children = line.children.iter().map(line_end);
+            notes
+                .chain(events)
+                .chain(children)
+                .max()
+                .unwrap_or(Beat::ZERO)
+        }
+
+        self.lines.iter().map(line_end).max().unwrap_or(Beat::ZERO)
+    }
+
+    /// The beat where another chart is appended by default, which is the first bar after the end of the chart
+    pub fn append_beat(&self) -> Beat {
+        let bar = self.time_signatures.bar_at(self.end_beat().value()).ceil();
+        self.time_signatures.bar_start(bar as u32)
+    }
+
+    /// Extract the range `[start, end)` of the chart, moving `start` to beat 0
+    ///
+    /// Events straddling the boundaries are clipped with their values interpolated at the boundaries,
+    /// the state of each line at `start` is kept with constant events.
+    /// Notes starting before `start` are dropped and holds reaching past `end` are shortened.
+    ///
+    /// The offset of the sliced chart is zero, it is aligned to the audio cut from the time of `start`
+    pub fn slice(&self, start: Beat, end: Beat) -> anyhow::Result<Self> {
+        if start >= end {
+            bail!("The start of the slice must be before its end");
+        }
+
+        let index = self
+            .bpm_list
+            .0
+            .iter()
+            .rposition(|point| point.beat <= start)
+            .unwrap_or(0);
+        let mut first = self.bpm_list.0[index];
+        if first.beat < start {
+            first.ramp = match (first.ramp, self.bpm_list.0.get(index + 1)) {
+                (Some(easing), Some(next)) => {
+                    let progress = (start - first.beat).value() / (next.beat - first.beat).value();
+                    Some(easing.partial(progress, 1.0))
+                }
+                _ => None,
+            };
+            first.bpm = self.bpm_list.bpm_at(start);
+        }
+        first.beat = Beat::ZERO;
+        let bpm_points = std::iter::once(first)
+            .chain(
+                self.bpm_list
+                    .0
+                    .iter()
+                    .filter(|point| point.beat > start)
+                    .map(|point| shift_bpm_point(point, |beat| beat - start)),
+            )
+            .collect();
+
+        let time_signatures = std::iter::once(TimeSignaturePoint {
+            beat: Beat::ZERO,
+            ..self.time_signatures.point_at(start)
+        })
+        .chain(
+            self.time_signatures
+                .0
+                .iter()
+                .filter(|point| point.beat > start)
+                .map(|point| TimeSignaturePoint {
+                    beat: point.beat - start,
+                    ..*point
+                }),
+        )
+        .collect();
+
+        let mut chart = Self {
+            format: CURRENT_FORMAT,
+            offset: Default::default(),
+            bpm_list: BpmList::new(bpm_points),
+            time_signatures: TimeSignatureList::new(time_signatures),
+            lines: self
+                .lines
+                .iter()
+                .map(|line| slice_line(line, start, end))
+                .collect(),
+        };
+        chart.assign_ids();
+        Ok(chart)
+    }
+
+    /// Append another chart to this chart, placing beat 0 of the other chart at `at`
+    ///
+    /// If `at` is [`None`], the other chart starts at the first bar after the end of this chart.
+    /// Lines are paired by index, lines only present in this chart are hidden after the join.
+    ///
+    /// The BPM list and time signatures of this chart are used before the join and the ones of the other chart after it.
+    /// The offset of this chart is kept, the audio of the other chart should start at the time of the join minus its own offset
+    pub fn concat(&self, other: &PhichainChart, at: Option<Beat>) -> Self {
+        let join = at.unwrap_or_else(|| self.append_beat());
+
+        // a ramp reaching into the join would ramp to the first BPM of the other chart instead
+        let ramps_into_join = self
+            .bpm_list
+            .0
+            .iter()
+            .rfind(|point| point.beat < join)
+            .is_some_and(|point| point.ramp.is_some());
+        let bpm_list = if ramps_into_join {
+            self.bpm_list.sampled(RAMP_SAMPLE_DENSITY)
+        } else {
+            self.bpm_list.clone()
+        };
+
+        let bpm_points = bpm_list
+            .0
+            .iter()
+            .filter(|point| point.beat < join)
+            .copied()
+            .chain(
+                other
+                    .bpm_list
+                    .0
+                    .iter()
+                    .map(|point| shift_bpm_point(point, |beat| beat + join)),
+            )
+            .collect();
+
+        let time_signatures = self
+            .time_signatures
+            .0
+            .iter()
+            .filter(|point| point.beat < join)
+            .copied()
+            .chain(
+                other
+                    .time_signatures
+                    .0
+                    .iter()
+                    .map(|point| TimeSignaturePoint {
+                        beat: point.beat + join,
+                        ..*point
+                    }),
+            )
+            .collect();
+
+        let lines = (0..self.lines.len().max(other.lines.len()))
+            .map(|index| concat_line(self.lines.get(index), other.lines.get(index), join))
+            .collect();
+
+        let mut chart = Self {
+            format: CURRENT_FORMAT,
+            offset: Offset(self.offset.0),
+            bpm_list: BpmList::new(bpm_points),
+            time_signatures: TimeSignatureList::new(time_signatures),
+            lines,
+        };
+        chart.assign_ids();
+        chart
+    }
+
+    /// Scale the tempo of the chart uniformly, e.g. `1.25` for an audio sped up by 25%
+    ///
+    /// Beats are unchanged, BPMs are multiplied by the factor and the offset is divided by it
+    pub fn scale_tempo(&mut self, factor: f32) -> anyhow::Result<()> {
+        if !(factor > 0.0 && factor.is_finite()) {
+            bail!("The tempo factor must be a positive number");
+        }
+
+        for point in &mut self.bpm_list.0 {
+            point.bpm *= factor;
+        }
+        self.bpm_list.compute();
+        self.offset.0 /= factor;
+
+        Ok(())
+    }
+}
+
+fn shift_bpm_point(point: &BpmPoint, shift: impl Fn(Beat) -> Beat) -> BpmPoint {
+    let mut point = *point;
+    point.beat = shift(point.beat);
+    point
+}
+
+/// Notes in `[start, end)`, holds reaching past `end` are shortened
+fn notes_in(notes: &[Note], start: Beat, end: Beat) -> Vec<Note> {
+    notes
+        .iter()
+        .filter(|note| note.beat >= start && note.beat < end)
+        .map(|note| match note.kind {
+            NoteKind::Hold { hold_beat } if note.beat + hold_beat > end => Note {
+                kind: NoteKind::Hold {
+                    hold_beat: end - note.beat,
+                },
+                ..*note
+            },
+            _ => *note,
+        })
+        .collect()
+}
+
+/// Events overlapping `[start, end)`, clipped to the range
+fn events_in(events: &[LineEvent], start: Beat, end: Beat) -> Vec<LineEvent> {
+    events
+        .iter()
+        .filter(|event| {
+            event.start_beat < end && (event.start_beat >= start || event.end_beat > start)
+        })
+        .map(|event| clip_event(event, start, end))
+        .collect()
+}
+
+/// Clip an event to `[start, end]`, evaluating its values at the new boundaries
+fn clip_event(event: &LineEvent, start: Beat, end: Beat) -> LineEvent {
+    let start_beat = event.start_beat.max(start);
+    let end_beat = event.end_beat.min(end);
+    if start_beat == event.start_beat && end_beat == event.end_beat {
+        return event.clone();
+    }
+
+    let value = match &event.value {
+        &LineEventValue::Transition { start, end, easing } => {
+            let length = (event.end_beat - event.start_beat).value();
+            let left = (start_beat - event.start_beat).value() / length;
+            let right = (end_beat - event.start_beat).value() / length;
+            LineEventValue::Transition {
+                start: event.evaluate(start_beat.value()).value().unwrap_or(start),
+                end: event.evaluate(end_beat.value()).value().unwrap_or(end),
+                easing: easing.partial(left, right),
+            }
+        }
+        LineEventValue::Expression(expression) => LineEventValue::expression(expression.clipped(
+            (start_beat - event.start_beat).value(),
+            (event.end_beat - event.start_beat).value(),
+        )),
+        value => value.clone(),
+    };
+
+    LineEvent {
+        start_beat,
+        end_beat,
+        value,
+        ..event.clone()
+    }
+}
+
+/// The value of a kind of events at the beat, as the game evaluates it
+fn value_at(events: &[LineEvent], kind: LineEventKind, beat: f32) -> Option<LineEventValue> {
+    let (result, event) = events
+        .iter()
+        .filter(|event| event.kind == kind)
+        .map(|event| (event.evaluate(beat), event))
+        .max_by(|a, b| a.0.cmp(&b.0))?;
+
+    match event.value {
+        LineEventValue::Text(_) => result.value().map(|_| event.value.clone()),
+        _ => result.value().map(LineEventValue::constant),
+    }
+}
+
+/// Move all notes and events of a line and its child lines
+fn shift_line(mut line: LineWrapper, shift: &impl Fn(Beat) -> Beat) -> LineWrapper {
+    for note in &mut line.notes {
+        note.beat = shift(note.beat);
+    }
+    for event in &mut line.events {
+        event.start_beat = shift(event.start_beat);
+        event.end_beat = shift(event.end_beat);
+    }
+    line.children = line
+        .children
+        .into_iter()
+        .map(|child| shift_line(child, shift))
+        .collect();
+    line
+}
+
+fn slice_line(line: &LineWrapper, start: Beat, end: Beat) -> LineWrapper {
+    let events = events_in(&line.events, start, end);
+
+    // keep the state of the line at the start of the slice
+    let mut initial = vec![];
+    for kind in LineEventKind::iter() {
+        let kept = events.iter().filter(|event| event.kind == kind);
+        if kept.clone().any(|event| event.start_beat == start) {
+            continue;
+        }
+        if let Some(value) = value_at(&line.events, kind, start.value()) {
+            initial.push(LineEvent {
+                kind,
+                start_beat: start,
+                end_beat: kept.map(|event| event.start_beat).min().unwrap_or(end),
+                value,
+                id: None,
+            });
+        }
+    }
+
+    let sliced = LineWrapper {
+        line: line.line.clone(),
+        notes: notes_in(&line.notes, start, end),
+        events: initial.into_iter().chain(events).collect(),
+        children: line
+            .children
+            .iter()
+            .map(|child| slice_line(child, start, end))
+            .collect(),
+    };
+    shift_line(sliced, &|beat| beat - start)
+}
+
+fn concat_line(
+    first: Option<&LineWrapper>,
+    second: Option<&LineWrapper>,
+    join: Beat,
+) -> LineWrapper {
+    let children = (0..first
+        .map_or(0, |x| x.children.len())
+        .max(second.map_or(0, |x| x.children.len())))
+        .map(|index| {
+            concat_line(
+                first.and_then(|x| x.children.get(index)),
+                second.and_then(|x| x.children.get(index)),
+                join,
+            )
+        })
+        .collect();
+
+    let mut line = match first {
+        Some(first) => LineWrapper {
+            line: first.line.clone(),
+            notes: notes_in(&first.notes, Beat::MIN, join),
+            events: events_in(&first.events, Beat::MIN, join),
+            children: vec![],
+        },
+        None => LineWrapper {
+            line: second.expect("Either line must be present").line.clone(),
+            notes: vec![],
+            events: vec![],
+            children: vec![],
+        },
+    };
+
+    match second {
+        Some(second) => {
+            let shifted = shift_line(
+                LineWrapper {
+                    children: vec![],
+                    ..second.clone()
+                },
+                &|beat| beat + join,
+            );
+            line.notes.extend(shifted.notes);
+            line.events.extend(shifted.events);
+        }
+        None => line.events.push(LineEvent {
+            kind: LineEventKind::Opacity,
+            start_beat: join,
+            end_beat: join + Beat::ONE,
+            value: LineEventValue::constant(0.0),
+            id: None,
+        }),
+    }
+
+    line.children = children;
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+    use crate::easing::Easing;
+
+    fn event(kind: LineEventKind, start: Beat, end: Beat, value: LineEventValue) -> LineEvent {
+        LineEvent {
+            kind,
+            start_beat: start,
+            end_beat: end,
+            value,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut chart = PhichainChart {
+            bpm_list: BpmList::new(vec![
+                BpmPoint::ramp(beat!(0), 100.0, Easing::Linear),
+                BpmPoint::new(beat!(8), 200.0),
+            ]),
+            ..Default::default()
+        };
+        chart.lines[0].notes = vec![
+            Note::new(NoteKind::Tap, true, beat!(1), 0.0, 1.0),
+            Note::new(NoteKind::Tap, true, beat!(5), 0.0, 1.0),
+            Note::new(
+                NoteKind::Hold {
+                    hold_beat: beat!(4),
+                },
+                true,
+                beat!(6),
+                0.0,
+                1.0,
+            ),
+        ];
+        chart.lines[0].events = vec![
+            event(
+                LineEventKind::X,
+                beat!(2),
+                beat!(6),
+                LineEventValue::transition(0.0, 400.0, Easing::Linear),
+            ),
+            event(
+                LineEventKind::Y,
+                beat!(0),
+                beat!(1),
+                LineEventValue::constant(100.0),
+            ),
+        ];
+
+        let sliced = chart.slice(beat!(4), beat!(8)).unwrap();
+        let line = &sliced.lines[0];
+
+        assert_eq!(line.notes.len(), 2);
+        assert_eq!(line.notes[0].beat, beat!(1));
+        assert_eq!(
+            line.notes[1].kind,
+            NoteKind::Hold {
+                hold_beat: beat!(2)
+            }
+        );
+
+        let x = line
+            .events
+            .iter()
+            .find(|event| event.kind == LineEventKind::X)
+            .unwrap();
+        assert_eq!(x.start_beat, beat!(0));
+        assert_eq!(x.end_beat, beat!(2));
+        assert_eq!(x.value.start(), 200.0);
+        assert_eq!(x.value.end(), 400.0);
+
+        // the Y event ended before the slice, its value is kept
+        let y = line
+            .events
+            .iter()
+            .find(|event| event.kind == LineEventKind::Y)
+            .unwrap();
+        assert_eq!(y.start_beat, beat!(0));
+        assert_eq!(y.value, LineEventValue::constant(100.0));
+
+        // the ramp continues from the middle
+        assert_eq!(sliced.bpm_list.0[0].bpm, 150.0);
+        assert_eq!(sliced.bpm_list.bpm_at(beat!(2)), 175.0);
+
+        assert!(chart.slice(beat!(4), beat!(4)).is_err());
+    }
+
+    #[test]
+    fn test_concat() {
+        let mut first = PhichainChart::default();
+        first.lines.push(LineWrapper::default());
+        first.lines[0].notes = vec![Note::new(NoteKind::Tap, true, beat!(5), 0.0, 1.0)];
+
+        let mut second = PhichainChart {
+            bpm_list: BpmList::single(180.0),
+            ..Default::default()
+        };
+        second.lines[0].notes = vec![Note::new(NoteKind::Tap, true, beat!(1), 0.0, 1.0)];
+
+        let chart = first.concat(&second, None);
+
+        // the first chart ends in the second bar
+        assert_eq!(
+            chart.bpm_list,
+            BpmList::new(vec![
+                BpmPoint::new(beat!(0), 120.0),
+                BpmPoint::new(beat!(8), 180.0),
+            ])
+        );
+        assert_eq!(chart.lines.len(), 2);
+        assert_eq!(chart.lines[0].notes[1].beat, beat!(9));
+
+        // the line only in the first chart is hidden after the join
+        let hidden = chart.lines[1].events.last().unwrap();
+        assert_eq!(hidden.kind, LineEventKind::Opacity);
+        assert_eq!(hidden.start_beat, beat!(8));
+
+        let chart = first.concat(&second, Some(beat!(4)));
+        assert_eq!(chart.lines[0].notes.len(), 1);
+        assert_eq!(chart.lines[0].notes[0].beat, beat!(5));
+    }
+
+    #[test]
+    fn test_scale_tempo() {
+        let mut chart = PhichainChart::default();
+        chart.offset.0 = 100.0;
+        chart.scale_tempo(2.0).unwrap();
+        assert_eq!(chart.bpm_list.0[0].bpm, 240.0);
+        assert_eq!(chart.bpm_list.time_at(beat!(4)), 1.0);
+        assert_eq!(chart.offset.0, 50.0);
+
+        assert!(chart.scale_tempo(0.0).is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::beat::Beat;
 use crate::easing::Easing;
 use crate::event::LineEventKind;
+use crate::id::Id;
+use internment::ArcIntern;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LineEvent {
     pub kind: LineEventKind,
     pub start_beat: Beat,
@@ -11,4 +13,10 @@ pub struct LineEvent {
     pub start: f32,
     pub end: f32,
     pub easing: Easing,
+    /// The text of [`LineEventKind::Text`] events, `start` and `end` are ignored when present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<ArcIntern<String>>,
+    /// Persistent identifier of the event, kept when converting from and into Phichain charts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Id>,
 }
@@ -4,6 +4,43 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Line {
+    #[serde(flatten)]
+    pub line: crate::line::Line,
     pub notes: Vec<Note>,
     pub events: Vec<LineEvent>,
+    #[serde(default)]
+    pub children: Vec<Line>,
+}
+
+impl Line {
+    /// Flatten this line and all its descendants into a list, parents come before their children
+    ///
+    /// The hierarchy is dropped, so the transform of a parent line is no longer applied to its children
+    pub fn flatten(mut self) -> Vec<Line> {
+        let children = std::mem::take(&mut self.children);
+        let mut lines = vec![self];
+        for child in children {
+            lines.append(&mut child.flatten());
+        }
+
+        lines
+    }
+}
+
+/// Index paths of lines and all their descendants, in the same order as [`Line::flatten`]
+pub fn flattened_paths(lines: &[Line]) -> Vec<Vec<usize>> {
+    fn collect(line: &Line, path: Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        paths.push(path.clone());
+        for (index, child) in line.children.iter().enumerate() {
+            let mut path = path.clone();
+            path.push(index);
+            collect(child, path, paths);
+        }
+    }
+
+    let mut paths = vec![];
+    for (index, line) in lines.iter().enumerate() {
+        collect(line, vec![index], &mut paths);
+    }
+    paths
 }
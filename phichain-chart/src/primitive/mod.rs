@@ -1,6 +1,6 @@
 use crate::bpm_list::BpmList;
 use crate::primitive::line::Line;
-use serde::de::DeserializeOwned;
+use crate::report::ConversionReport;
 use serde::{Deserialize, Serialize};
 
 pub mod event;
@@ -25,23 +25,60 @@ impl Default for PrimitiveChart {
     }
 }
 
-pub trait Format: Serialize + DeserializeOwned {
-    fn into_primitive(self) -> anyhow::Result<PrimitiveChart>;
+/// A chart format which can be converted from and into [`PrimitiveChart`]
+///
+/// Reading and writing charts are up to the format, most formats are json and use serde while others may not
+///
+/// Formats record every lossy transformation into a [`ConversionReport`], the conversions without a report log them as warnings
+pub trait Format {
+    fn into_primitive_with_report(
+        self,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart>;
 
-    fn from_primitive(phichain: PrimitiveChart) -> anyhow::Result<Self>
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized;
+
+    fn into_primitive(self) -> anyhow::Result<PrimitiveChart>
+    where
+        Self: Sized,
+    {
+        let mut report = ConversionReport::default();
+        let primitive = self.into_primitive_with_report(&mut report)?;
+        report.log();
+        Ok(primitive)
+    }
+
+    fn from_primitive(primitive: PrimitiveChart) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut report = ConversionReport::default();
+        let chart = Self::from_primitive_with_report(primitive, &mut report)?;
+        report.log();
+        Ok(chart)
+    }
 }
 
 impl Format for PrimitiveChart {
-    fn into_primitive(self) -> anyhow::Result<PrimitiveChart> {
+    fn into_primitive_with_report(
+        self,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart> {
         Ok(self)
     }
 
-    fn from_primitive(phichain: PrimitiveChart) -> anyhow::Result<Self>
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Ok(phichain)
+        Ok(primitive)
     }
 }
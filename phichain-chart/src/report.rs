@@ -0,0 +1,219 @@
+//! Reports of lossy transformations made when converting or compiling a chart
+
+use crate::easing::Easing;
+use crate::event::LineEventKind;
+use crate::line::Line;
+use crate::lint::Location;
+use crate::note::Note;
+use anyhow::bail;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum LossKind {
+    /// The easing is unknown and replaced by linear
+    UnknownEasing { easing: String },
+    /// The easing can not be represented in the target format and is approximated
+    UnsupportedEasing { kind: LineEventKind, easing: Easing },
+    /// Transitions of the event can not be represented in the target format and are converted into steps
+    SteppedTransition { kind: LineEventKind },
+    /// Events of the kind can not be represented in the target format and are dropped
+    DroppedEvents { kind: LineEventKind, count: usize },
+    /// Fake notes can not be represented in the target format and are dropped
+    DroppedFakeNotes { count: usize },
+    /// The texture of the line can not be represented in the target format and is dropped
+    DroppedTexture { texture: String },
+    /// The property of notes can not be represented in the target format and is dropped from the notes setting it
+    DroppedNoteProperty {
+        property: NoteProperty,
+        count: usize,
+    },
+    /// The property of the line can not be represented in the target format and is dropped
+    DroppedLineProperty { property: LineProperty },
+    /// Expressions are not baked before the conversion, only their start and end values are kept
+    UnbakedExpression { kind: LineEventKind },
+    /// BPM ramps can not be represented in the target format and are converted into steps
+    BpmRamp,
+    /// The line hierarchy can not be represented in the target format, the child line is exported as an independent line
+    LineHierarchy,
+    /// The state the child line inherits from its parents is sampled into linear events
+    BakedLine,
+    /// The parent of the line does not exist, the line is treated as a root line
+    InvalidParent { parent: i32 },
+    /// The line is in a parent cycle, the line is treated as a root line
+    ParentCycle,
+}
+
+impl Display for LossKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossKind::UnknownEasing { easing } => {
+                write!(f, "unknown easing {}, replaced by linear", easing)
+            }
+            LossKind::UnsupportedEasing { kind, easing } => write!(
+                f,
+                "easing {} of {:?} event is not supported, approximated",
+                easing, kind
+            ),
+            LossKind::SteppedTransition { kind } => write!(
+                f,
+                "transitions of {:?} event are not supported, converted into steps",
+                kind
+            ),
+            LossKind::DroppedEvents { kind, count } => {
+                write!(f, "{:?} events are not supported, {} dropped", kind, count)
+            }
+            LossKind::DroppedFakeNotes { count } => {
+                write!(f, "fake notes are not supported, {} dropped", count)
+            }
+            LossKind::DroppedTexture { texture } => {
+                write!(f, "line textures are not supported, {} dropped", texture)
+            }
+            LossKind::DroppedNoteProperty { property, count } => write!(
+                f,
+                "note {} is not supported, dropped from {} notes",
+                property.name(),
+                count
+            ),
+            LossKind::DroppedLineProperty { property } => {
+                write!(f, "line {} is not supported, dropped", property.name())
+            }
+            LossKind::UnbakedExpression { kind } => write!(
+                f,
+                "expression of {:?} event is not baked, converted into a linear transition",
+                kind
+            ),
+            LossKind::BpmRamp => f.write_str("BPM ramps are not supported, converted into steps"),
+            LossKind::LineHierarchy => f.write_str(
+                "line hierarchy is not supported, the child line is exported as an independent line",
+            ),
+            LossKind::BakedLine => f.write_str(
+                "the state inherited from the parent lines is sampled into linear events",
+            ),
+            LossKind::InvalidParent { parent } => {
+                write!(f, "parent {} does not exist, treated as a root line", parent)
+            }
+            LossKind::ParentCycle => f.write_str("line is in a parent cycle, treated as a root line"),
+        }
+    }
+}
+
+/// Note properties not every format can represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteProperty {
+    Alpha,
+    Size,
+    VisibleTime,
+    YOffset,
+}
+
+impl NoteProperty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoteProperty::Alpha => "alpha",
+            NoteProperty::Size => "size",
+            NoteProperty::VisibleTime => "visible time",
+            NoteProperty::YOffset => "y offset",
+        }
+    }
+
+    /// Whether the note sets the property to something other than its default, which is lost if the property is dropped
+    pub fn is_set(&self, note: &Note) -> bool {
+        match self {
+            NoteProperty::Alpha => note.alpha != 255.0,
+            NoteProperty::Size => note.size != 1.0,
+            NoteProperty::VisibleTime => note.visible_time.is_some(),
+            NoteProperty::YOffset => note.y_offset != 0.0,
+        }
+    }
+}
+
+/// Line properties not every format can represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineProperty {
+    Name,
+    ZOrder,
+    HideNotesBelow,
+}
+
+impl LineProperty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineProperty::Name => "name",
+            LineProperty::ZOrder => "z-order",
+            LineProperty::HideNotesBelow => "hide notes below",
+        }
+    }
+
+    /// Whether the line sets the property to something other than its default, which is lost if the property is dropped
+    pub fn is_set(&self, line: &Line) -> bool {
+        match self {
+            LineProperty::Name => line.name != Line::default().name,
+            LineProperty::ZOrder => line.z_order != 0,
+            LineProperty::HideNotesBelow => line.hide_notes_below,
+        }
+    }
+}
+
+/// A lossy transformation made to a chart
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Loss {
+    /// Where the loss happens in the chart being converted, [`None`] for the whole chart
+    pub location: Option<Location>,
+    #[serde(flatten)]
+    pub kind: LossKind,
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// All lossy transformations made when converting or compiling a chart
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConversionReport {
+    pub losses: Vec<Loss>,
+}
+
+impl ConversionReport {
+    pub fn push(&mut self, location: Option<Location>, kind: LossKind) {
+        self.losses.push(Loss { location, kind });
+    }
+
+    /// Append the losses of another report, e.g. of a later conversion of the same chart
+    pub fn extend(&mut self, other: ConversionReport) {
+        self.losses.extend(other.losses);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.losses.is_empty()
+    }
+
+    /// Log all losses as warnings
+    pub fn log(&self) {
+        for loss in &self.losses {
+            warn!("{}", loss);
+        }
+    }
+
+    /// Fail if any loss is reported
+    pub fn ensure_lossless(&self) -> anyhow::Result<()> {
+        if let Some(first) = self.losses.first() {
+            bail!(
+                "The conversion is lossy, {} losses are reported, the first one is: {}",
+                self.losses.len(),
+                first
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Retiming of a chart when its BPM list or offset changes
+//!
+//! Beats of notes and events are recomputed to keep their absolute time in the audio,
+//! then quantised to the beat grid of a given density
+
+use crate::beat::utils::attach;
+use crate::beat::Beat;
+use crate::bpm_list::BpmList;
+use crate::event::LineEvent;
+use crate::lint::Location;
+use crate::note::{Note, NoteKind};
+use crate::serialization::{LineWrapper, PhichainChart};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetimeOptions {
+    /// Retimed beats are quantised to `1 / density` beat
+    pub density: u32,
+    /// Items moved further than this by quantising are reported as off-grid, in seconds
+    pub tolerance: f32,
+}
+
+impl Default for RetimeOptions {
+    fn default() -> Self {
+        Self {
+            density: 32,
+            tolerance: 0.001,
+        }
+    }
+}
+
+/// A note or an event which does not land on the beat grid after retiming
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OffGrid {
+    pub location: Location,
+    /// Seconds the item is moved away from its original time by quantising
+    pub error: f32,
+}
+
+/// Converts beats on the old timing to beats on the new timing
+pub struct Retimer {
+    from: BpmList,
+    /// Offset of the old timing, in seconds
+    from_offset: f32,
+    to: BpmList,
+    /// Offset of the new timing, in seconds
+    to_offset: f32,
+    options: RetimeOptions,
+}
+
+impl Retimer {
+    /// Create a retimer, offsets are in milliseconds as [`crate::offset::Offset`]
+    pub fn new(from: (&BpmList, f32), to: (&BpmList, f32), options: RetimeOptions) -> Self {
+        Self {
+            from: from.0.clone(),
+            from_offset: from.1 / 1000.0,
+            to: to.0.clone(),
+            to_offset: to.1 / 1000.0,
+            options,
+        }
+    }
+
+    /// Retime a beat, returning the new beat and the error in seconds
+    ///
+    /// Beats keeping their time on the new timing are left untouched, even if they are not on the grid
+    pub fn beat(&self, beat: Beat) -> (Beat, f32) {
+        let time = self.from.time_at(beat) + self.from_offset;
+        let error = |beat: Beat| (self.to.time_at(beat) + self.to_offset - time).abs();
+
+        if error(beat) <= self.options.tolerance {
+            return (beat, error(beat));
+        }
+
+        let exact = self.to.beat_at_f32(time - self.to_offset);
+        let quantised = attach(exact, self.options.density);
+        (quantised, error(quantised))
+    }
+
+    /// Retime a note, returning the new note and the largest error of its start and end in seconds
+    pub fn note(&self, note: &Note) -> (Note, f32) {
+        let (beat, error) = self.beat(note.beat);
+        let mut retimed = Note { beat, ..*note };
+
+        let mut max_error = error;
+        if let NoteKind::Hold { hold_beat } = note.kind {
+            let (end, end_error) = self.beat(note.beat + hold_beat);
+            retimed.kind = NoteKind::Hold {
+                hold_beat: end - beat,
+            };
+            max_error = max_error.max(end_error);
+        }
+
+        (retimed, max_error)
+    }
+
+    /// Retime an event, returning the new event and the largest error of its start and end in seconds
+    pub fn event(&self, event: &LineEvent) -> (LineEvent, f32) {
+        let (start_beat, start_error) = self.beat(event.start_beat);
+        let (end_beat, end_error) = self.beat(event.end_beat);
+        (
+            LineEvent {
+                start_beat,
+                end_beat,
+                ..event.clone()
+            },
+            start_error.max(end_error),
+        )
+    }
+
+    /// If the error of a retimed item is large enough to be reported
+    pub fn is_off_grid(&self, error: f32) -> bool {
+        error > self.options.tolerance
+    }
+
+    /// Retime all notes and events of a chart and switch it to the new timing
+    ///
+    /// Returns the notes and events which do not land on the grid
+    pub fn chart(&self, chart: &mut PhichainChart) -> Vec<OffGrid> {
+        let mut off_grid = vec![];
+        for (index, line) in chart.lines.iter_mut().enumerate() {
+            self.line(line, vec![index], &mut off_grid);
+        }
+
+        chart.bpm_list = self.to.clone();
+        chart.offset.0 = self.to_offset * 1000.0;
+
+        off_grid
+    }
+
+    fn line(&self, line: &mut LineWrapper, path: Vec<usize>, off_grid: &mut Vec<OffGrid>) {
+        for (index, note) in line.notes.iter_mut().enumerate() {
+            let (retimed, error) = self.note(note);
+            *note = retimed;
+            if self.is_off_grid(error) {
+                off_grid.push(OffGrid {
+                    location: Location::Note {
+                        line: path.clone(),
+                        index,
+                    },
+                    error,
+                });
+            }
+        }
+
+        for (index, event) in line.events.iter_mut().enumerate() {
+            let (retimed, error) = self.event(event);
+            *event = retimed;
+            if self.is_off_grid(error) {
+                off_grid.push(OffGrid {
+                    location: Location::Event {
+                        line: path.clone(),
+                        index,
+                    },
+                    error,
+                });
+            }
+        }
+
+        for (index, child) in line.children.iter_mut().enumerate() {
+            let mut path = path.clone();
+            path.push(index);
+            self.line(child, path, off_grid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+    use crate::bpm_list::BpmPoint;
+
+    #[test]
+    fn test_retime_bpm() {
+        let mut chart = PhichainChart::default();
+        chart.lines[0].notes = vec![
+            Note::new(NoteKind::Tap, true, beat!(2), 0.0, 1.0),
+            Note::new(NoteKind::Tap, true, beat!(1, 1, 3), 0.0, 1.0),
+            Note::new(
+                NoteKind::Hold {
+                    hold_beat: beat!(2),
+                },
+                true,
+                beat!(6),
+                0.0,
+                1.0,
+            ),
+            Note::new(NoteKind::Tap, true, beat!(4, 1, 3), 0.0, 1.0),
+        ];
+
+        // the BPM from beat 4 was measured wrong, 120 should be 180
+        let to = BpmList::new(vec![
+            BpmPoint::new(beat!(0), 120.0),
+            BpmPoint::new(beat!(4), 180.0),
+        ]);
+        let retimer = Retimer::new((&chart.bpm_list, 0.0), (&to, 0.0), Default::default());
+        let off_grid = retimer.chart(&mut chart);
+
+        let notes = &chart.lines[0].notes;
+        // notes before beat 4 are untouched, including the triplet
+        assert_eq!(notes[0].beat, beat!(2));
+        assert_eq!(notes[1].beat, beat!(1, 1, 3));
+        // 1 second after beat 4 is 3 beats at 180 BPM
+        assert_eq!(notes[2].beat, beat!(7));
+        assert_eq!(
+            notes[2].kind,
+            NoteKind::Hold {
+                hold_beat: beat!(3)
+            }
+        );
+        // 1/3 beat at 120 BPM is 1/2 beat at 180 BPM
+        assert_eq!(notes[3].beat, beat!(4, 1, 2));
+        assert!(off_grid.is_empty());
+        assert_eq!(chart.bpm_list, to);
+    }
+
+    #[test]
+    fn test_retime_offset() {
+        let mut chart = PhichainChart::default();
+        chart.lines[0].notes = vec![
+            Note::new(NoteKind::Tap, true, beat!(4), 0.0, 1.0),
+            Note::new(NoteKind::Tap, true, beat!(8), 0.0, 1.0),
+        ];
+        let bpm_list = chart.bpm_list.clone();
+
+        // moving the offset by 250ms shifts notes by half a beat at 120 BPM
+        let retimer = Retimer::new((&bpm_list, 0.0), (&bpm_list, 250.0), Default::default());
+        let off_grid = retimer.chart(&mut chart);
+        assert_eq!(chart.lines[0].notes[0].beat, beat!(3, 1, 2));
+        assert!(off_grid.is_empty());
+        assert_eq!(chart.offset.0, 250.0);
+
+        // 10ms does not land on a 1/4 beat grid
+        let retimer = Retimer::new(
+            (&bpm_list, 250.0),
+            (&bpm_list, 260.0),
+            RetimeOptions {
+                density: 4,
+                ..Default::default()
+            },
+        );
+        let off_grid = retimer.chart(&mut chart);
+        // two notes and five default events
+        assert_eq!(off_grid.len(), 7);
+        assert_eq!(
+            off_grid[0].location,
+            Location::Note {
+                line: vec![0],
+                index: 0
+            }
+        );
+        assert!((off_grid[0].error - 0.01).abs() < 1e-4);
+    }
+}
@@ -1,61 +1,81 @@
 use crate::beat::Beat;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 use crate::bpm_list::BpmList;
 use crate::event::{LineEvent, LineEventKind, LineEventValue};
+use crate::id::{Id, Identifiable};
 use crate::line::Line;
+use crate::lint::Location;
 use crate::migration::CURRENT_FORMAT;
 use crate::note::Note;
 use crate::offset::Offset;
 use crate::primitive;
 use crate::primitive::{Format, PrimitiveChart};
+use crate::report::{ConversionReport, LossKind};
+use crate::time_signature::TimeSignatureList;
 
 #[derive(Serialize, Deserialize)]
 pub struct PhichainChart {
     pub format: u64,
     pub offset: Offset,
     pub bpm_list: BpmList,
+    /// Missing in charts before format 9, which are in 4/4
+    #[serde(default)]
+    pub time_signatures: TimeSignatureList,
     pub lines: Vec<LineWrapper>,
 }
 
 impl Format for PhichainChart {
-    fn into_primitive(self) -> anyhow::Result<PrimitiveChart> {
+    fn into_primitive_with_report(
+        self,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PrimitiveChart> {
+        fn report_expressions(line: &LineWrapper, path: Vec<usize>, report: &mut ConversionReport) {
+            for (index, event) in line.events.iter().enumerate() {
+                if event.value.is_expression() {
+                    report.push(
+                        Some(Location::Event {
+                            line: path.clone(),
+                            index,
+                        }),
+                        LossKind::UnbakedExpression { kind: event.kind },
+                    );
+                }
+            }
+            for (index, child) in line.children.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(index);
+                report_expressions(child, path, report);
+            }
+        }
+
+        for (index, line) in self.lines.iter().enumerate() {
+            report_expressions(line, vec![index], report);
+        }
+
         Ok(PrimitiveChart {
             offset: self.offset.0,
-            bpm_list: self.bpm_list.clone(),
-            lines: self
-                .lines
-                .iter()
-                .map(|line| primitive::line::Line {
-                    notes: line.notes.clone(),
-                    events: line.events.iter().map(|x| (*x).into()).collect(),
-                })
-                .collect(),
+            bpm_list: self.bpm_list,
+            lines: self.lines.into_iter().map(Into::into).collect(),
             ..Default::default()
         })
     }
 
-    fn from_primitive(primitive: PrimitiveChart) -> anyhow::Result<Self>
+    fn from_primitive_with_report(
+        primitive: PrimitiveChart,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
         Ok(Self {
             offset: Offset(primitive.offset),
             bpm_list: primitive.bpm_list,
-            lines: primitive
-                .lines
-                .iter()
-                .map(|line| {
-                    LineWrapper::new(
-                        Line::default(),
-                        line.notes.clone(),
-                        line.events.iter().map(|x| (*x).into()).collect(),
-                        vec![],
-                    )
-                })
-                .collect(),
+            lines: primitive.lines.into_iter().map(Into::into).collect(),
             ..Default::default()
-        })
+        }
+        .with_ids())
     }
 }
 
@@ -65,9 +85,38 @@ impl PhichainChart {
             format: CURRENT_FORMAT,
             offset: Offset(offset),
             bpm_list,
+            time_signatures: Default::default(),
             lines,
         }
     }
+
+    /// Find a line by its id, including child lines
+    pub fn line_by_id(&self, id: Id) -> Option<&LineWrapper> {
+        self.lines.iter().find_map(|line| line.line_by_id(id))
+    }
+
+    /// Find a note by its id, including notes of child lines
+    pub fn note_by_id(&self, id: Id) -> Option<&Note> {
+        self.lines.iter().find_map(|line| line.note_by_id(id))
+    }
+
+    /// Find an event by its id, including events of child lines
+    pub fn event_by_id(&self, id: Id) -> Option<&LineEvent> {
+        self.lines.iter().find_map(|line| line.event_by_id(id))
+    }
+
+    /// Assign new ids to lines, notes and events without an id or sharing an id with a previous one
+    pub fn assign_ids(&mut self) {
+        let mut seen = HashSet::new();
+        for line in &mut self.lines {
+            line.assign_ids(&mut seen);
+        }
+    }
+
+    fn with_ids(mut self) -> Self {
+        self.assign_ids();
+        self
+    }
 }
 
 impl Default for PhichainChart {
@@ -76,13 +125,14 @@ impl Default for PhichainChart {
             format: CURRENT_FORMAT,
             offset: Default::default(),
             bpm_list: Default::default(),
+            time_signatures: Default::default(),
             lines: vec![Default::default()],
         }
     }
 }
 
 /// A wrapper struct to handle line serialization and deserialization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LineWrapper {
     #[serde(flatten)]
     pub line: Line,
@@ -105,6 +155,76 @@ impl LineWrapper {
             children,
         }
     }
+
+    /// Find this line or one of its child lines by id
+    pub fn line_by_id(&self, id: Id) -> Option<&LineWrapper> {
+        if self.line.id == Some(id) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.line_by_id(id))
+    }
+
+    /// Find a note of this line or its child lines by id
+    pub fn note_by_id(&self, id: Id) -> Option<&Note> {
+        self.notes
+            .iter()
+            .find(|note| note.id == Some(id))
+            .or_else(|| self.children.iter().find_map(|child| child.note_by_id(id)))
+    }
+
+    /// Find an event of this line or its child lines by id
+    pub fn event_by_id(&self, id: Id) -> Option<&LineEvent> {
+        self.events
+            .iter()
+            .find(|event| event.id == Some(id))
+            .or_else(|| self.children.iter().find_map(|child| child.event_by_id(id)))
+    }
+
+    fn assign_ids(&mut self, seen: &mut HashSet<Id>) {
+        fn assign(item: &mut impl Identifiable, seen: &mut HashSet<Id>) {
+            match item.id() {
+                Some(id) if seen.insert(id) => {}
+                _ => {
+                    let id = Id::new();
+                    seen.insert(id);
+                    item.set_id(id);
+                }
+            }
+        }
+
+        assign(&mut self.line, seen);
+        for note in &mut self.notes {
+            assign(note, seen);
+        }
+        for event in &mut self.events {
+            assign(event, seen);
+        }
+        for child in &mut self.children {
+            child.assign_ids(seen);
+        }
+    }
+}
+
+impl From<LineWrapper> for primitive::line::Line {
+    fn from(value: LineWrapper) -> Self {
+        Self {
+            line: value.line,
+            notes: value.notes,
+            events: value.events.into_iter().map(Into::into).collect(),
+            children: value.children.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<primitive::line::Line> for LineWrapper {
+    fn from(value: primitive::line::Line) -> Self {
+        Self::new(
+            value.line,
+            value.notes,
+            value.events.into_iter().map(Into::into).collect(),
+            value.children.into_iter().map(Into::into).collect(),
+        )
+    }
 }
 
 /// A default line with no notes and default events
@@ -119,30 +239,35 @@ impl Default for LineWrapper {
                     value: LineEventValue::constant(0.0),
                     start_beat: Beat::ZERO,
                     end_beat: Beat::ONE,
+                    id: None,
                 },
                 LineEvent {
                     kind: LineEventKind::Y,
                     value: LineEventValue::constant(0.0),
                     start_beat: Beat::ZERO,
                     end_beat: Beat::ONE,
+                    id: None,
                 },
                 LineEvent {
                     kind: LineEventKind::Rotation,
                     value: LineEventValue::constant(0.0),
                     start_beat: Beat::ZERO,
                     end_beat: Beat::ONE,
+                    id: None,
                 },
                 LineEvent {
                     kind: LineEventKind::Opacity,
                     value: LineEventValue::constant(0.0),
                     start_beat: Beat::ZERO,
                     end_beat: Beat::ONE,
+                    id: None,
                 },
                 LineEvent {
                     kind: LineEventKind::Speed,
                     value: LineEventValue::constant(10.0),
                     start_beat: Beat::ZERO,
                     end_beat: Beat::ONE,
+                    id: None,
                 },
             ],
             children: vec![],
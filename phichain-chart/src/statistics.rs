@@ -0,0 +1,285 @@
+//! Statistics of a chart, e.g. note counts, note density and a rough difficulty estimation
+
+use crate::beat::Beat;
+use crate::bpm_list::BpmList;
+use crate::note::{Note, NoteKind};
+use crate::primitive::PrimitiveChart;
+use crate::serialization::{LineWrapper, PhichainChart};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatisticsOptions {
+    /// Length of the sliding window used to compute notes per second, in seconds
+    pub window: f32,
+    /// Step between two adjacent sliding windows, in seconds
+    pub step: f32,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        Self {
+            window: 1.0,
+            step: 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NoteCounts {
+    pub tap: usize,
+    pub drag: usize,
+    pub hold: usize,
+    pub flick: usize,
+    /// Fake notes are not counted into the other kinds
+    pub fake: usize,
+}
+
+impl NoteCounts {
+    /// Number of notes which are counted into the score, excluding fake notes
+    pub fn total(&self) -> usize {
+        self.tap + self.drag + self.hold + self.flick
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ChartStatistics {
+    pub notes: NoteCounts,
+    /// Number of lines, including child lines
+    pub lines: usize,
+    pub events: usize,
+
+    /// Total duration of all holds in seconds
+    pub total_hold_duration: f32,
+    /// Duration of the longest hold in seconds
+    pub longest_hold_duration: f32,
+
+    /// Seconds from the first note to the end of the last note
+    pub duration: f32,
+    /// Notes per second of each sliding window, starting from the first note
+    pub nps: Vec<f32>,
+    pub peak_nps: f32,
+    pub average_nps: f32,
+
+    /// Number of notes sharing their beat with other notes, which are multi-highlighted in game
+    pub simultaneous_notes: usize,
+    /// Largest number of notes on a single beat
+    pub max_simultaneous_notes: usize,
+
+    /// A rough difficulty estimation based on weighted note density, higher is harder
+    ///
+    /// This is a heuristic and is not comparable with official levels
+    pub difficulty: f32,
+}
+
+/// How much a note contributes to the difficulty, compared with a tap
+fn note_weight(kind: NoteKind) -> f32 {
+    match kind {
+        NoteKind::Tap => 1.0,
+        NoteKind::Drag => 0.5,
+        NoteKind::Hold { .. } => 1.2,
+        NoteKind::Flick => 0.8,
+    }
+}
+
+impl ChartStatistics {
+    pub fn new<'a>(
+        bpm_list: &BpmList,
+        notes: impl IntoIterator<Item = &'a Note>,
+        lines: usize,
+        events: usize,
+        options: StatisticsOptions,
+    ) -> Self {
+        let mut statistics = ChartStatistics {
+            lines,
+            events,
+            ..Default::default()
+        };
+
+        let mut beats: HashMap<Beat, usize> = HashMap::new();
+        // (time, end time, weight) of notes counted into the score
+        let mut timings = vec![];
+
+        for note in notes {
+            if note.fake {
+                statistics.notes.fake += 1;
+                continue;
+            }
+
+            *beats.entry(note.beat.reduced()).or_default() += 1;
+
+            let time = bpm_list.time_at(note.beat);
+            let mut end_time = time;
+            match note.kind {
+                NoteKind::Tap => statistics.notes.tap += 1,
+                NoteKind::Drag => statistics.notes.drag += 1,
+                NoteKind::Flick => statistics.notes.flick += 1,
+                NoteKind::Hold { hold_beat } => {
+                    statistics.notes.hold += 1;
+                    end_time = bpm_list.time_at(note.beat + hold_beat);
+                    let duration = end_time - time;
+                    statistics.total_hold_duration += duration;
+                    statistics.longest_hold_duration =
+                        statistics.longest_hold_duration.max(duration);
+                }
+            }
+            timings.push((time, end_time, note_weight(note.kind)));
+        }
+
+        statistics.simultaneous_notes = beats.values().filter(|x| **x > 1).sum();
+        statistics.max_simultaneous_notes = beats.values().copied().max().unwrap_or(0);
+
+        if timings.is_empty() {
+            return statistics;
+        }
+
+        timings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let start = timings[0].0;
+        let end = timings
+            .iter()
+            .map(|x| x.1)
+            .fold(f32::NEG_INFINITY, f32::max);
+        statistics.duration = end - start;
+
+        let window = options.window.max(f32::EPSILON);
+        let step = options.step.max(f32::EPSILON);
+        let mut peak_weighted = 0.0f32;
+        let mut window_start = start;
+        loop {
+            let window_end = window_start + window;
+            let in_window = timings
+                .iter()
+                .skip_while(|x| x.0 < window_start)
+                .take_while(|x| x.0 < window_end);
+            let (count, weighted) =
+                in_window.fold((0, 0.0), |(count, weighted), x| (count + 1, weighted + x.2));
+            statistics.nps.push(count as f32 / window);
+            peak_weighted = peak_weighted.max(weighted / window);
+
+            if window_end >= end {
+                break;
+            }
+            window_start += step;
+        }
+
+        statistics.peak_nps = statistics.nps.iter().copied().fold(0.0, f32::max);
+        let total_weight = timings.iter().map(|x| x.2).sum::<f32>();
+        let average_weighted = total_weight / statistics.duration.max(window);
+        statistics.average_nps = timings.len() as f32 / statistics.duration.max(window);
+
+        // peaks make a chart hard, while the overall density makes it exhausting
+        statistics.difficulty = 0.6 * peak_weighted + 0.4 * average_weighted;
+
+        statistics
+    }
+
+    pub fn of_phichain(chart: &PhichainChart, options: StatisticsOptions) -> Self {
+        fn walk<'a>(line: &'a LineWrapper, notes: &mut Vec<&'a Note>, counts: &mut (usize, usize)) {
+            notes.extend(&line.notes);
+            counts.0 += 1;
+            counts.1 += line.events.len();
+            for child in &line.children {
+                walk(child, notes, counts);
+            }
+        }
+
+        let mut notes = vec![];
+        let mut counts = (0, 0);
+        for line in &chart.lines {
+            walk(line, &mut notes, &mut counts);
+        }
+
+        Self::new(&chart.bpm_list, notes, counts.0, counts.1, options)
+    }
+
+    pub fn of_primitive(chart: &PrimitiveChart, options: StatisticsOptions) -> Self {
+        fn walk<'a>(
+            line: &'a crate::primitive::line::Line,
+            notes: &mut Vec<&'a Note>,
+            counts: &mut (usize, usize),
+        ) {
+            notes.extend(&line.notes);
+            counts.0 += 1;
+            counts.1 += line.events.len();
+            for child in &line.children {
+                walk(child, notes, counts);
+            }
+        }
+
+        let mut notes = vec![];
+        let mut counts = (0, 0);
+        for line in &chart.lines {
+            walk(line, &mut notes, &mut counts);
+        }
+
+        Self::new(&chart.bpm_list, notes, counts.0, counts.1, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+
+    #[test]
+    fn test_statistics() {
+        let mut chart = PhichainChart::default();
+        let notes = &mut chart.lines[0].notes;
+        // 120 BPM, a beat lasts 0.5s
+        for i in 0..8 {
+            notes.push(Note::new(NoteKind::Tap, true, beat!(i), 0.0, 1.0));
+        }
+        notes.push(Note::new(NoteKind::Drag, true, beat!(0), 0.0, 1.0));
+        notes.push(Note::new(
+            NoteKind::Hold {
+                hold_beat: beat!(4),
+            },
+            true,
+            beat!(4),
+            0.0,
+            1.0,
+        ));
+        notes.push(Note {
+            fake: true,
+            ..Note::new(NoteKind::Flick, true, beat!(2), 0.0, 1.0)
+        });
+        chart.lines[0].children.push(Default::default());
+
+        let statistics = ChartStatistics::of_phichain(&chart, StatisticsOptions::default());
+
+        assert_eq!(
+            statistics.notes,
+            NoteCounts {
+                tap: 8,
+                drag: 1,
+                hold: 1,
+                flick: 0,
+                fake: 1,
+            }
+        );
+        assert_eq!(statistics.notes.total(), 10);
+        assert_eq!(statistics.lines, 2);
+        assert_eq!(statistics.events, 10);
+        assert_eq!(statistics.total_hold_duration, 2.0);
+        assert_eq!(statistics.longest_hold_duration, 2.0);
+        assert_eq!(statistics.duration, 4.0);
+        // beat 0 and 4 have two notes each, the fake note on beat 2 is not counted
+        assert_eq!(statistics.simultaneous_notes, 4);
+        assert_eq!(statistics.max_simultaneous_notes, 2);
+        // the first window covers beat 0 and 1, which has 3 notes
+        assert_eq!(statistics.nps[0], 3.0);
+        assert_eq!(statistics.peak_nps, 3.0);
+        assert_eq!(statistics.average_nps, 2.5);
+        assert!(statistics.difficulty > 0.0);
+    }
+
+    #[test]
+    fn test_empty_chart() {
+        let statistics =
+            ChartStatistics::of_phichain(&PhichainChart::default(), StatisticsOptions::default());
+        assert_eq!(statistics.notes.total(), 0);
+        assert_eq!(statistics.lines, 1);
+        assert!(statistics.nps.is_empty());
+        assert_eq!(statistics.difficulty, 0.0);
+    }
+}
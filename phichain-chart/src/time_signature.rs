@@ -0,0 +1,386 @@
+//! Time signatures, which group beats into bars
+//!
+//! Beats of a chart are always quarter notes, a time signature point starts a new bar at its beat,
+//! cutting the last bar of the previous time signature short if needed
+
+use crate::beat::Beat;
+use anyhow::{bail, Context};
+use num::Rational32;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeSignaturePoint {
+    pub beat: Beat,
+    /// Number of beats in a bar
+    pub numerator: u32,
+    /// Note value of a beat, e.g. `8` for eighth notes
+    pub denominator: u32,
+}
+
+impl TimeSignaturePoint {
+    pub fn new(beat: Beat, numerator: u32, denominator: u32) -> Self {
+        Self {
+            beat,
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Length of a beat of this time signature, in quarter notes
+    pub fn beat_length(&self) -> Rational32 {
+        Rational32::new(4, self.denominator.max(1) as i32)
+    }
+
+    /// Length of a bar of this time signature, in quarter notes
+    pub fn bar_length(&self) -> Rational32 {
+        self.beat_length() * self.numerator.max(1) as i32
+    }
+}
+
+impl Display for TimeSignaturePoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// A beat on the grid of time signatures
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BarBeat {
+    pub beat: Beat,
+    /// Index of the bar, starting from 0
+    pub bar: u32,
+    /// Index of the beat in the bar, `0` is the downbeat
+    pub index: u32,
+}
+
+/// A position in bar:beat terms
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BarPosition {
+    /// Index of the bar, starting from 0
+    pub bar: u32,
+    /// Beats of the time signature since the start of the bar, starting from 0
+    pub beat: f32,
+}
+
+impl Display for BarPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{:.2}", self.bar + 1, self.beat + 1.0)
+    }
+}
+
+/// Parse a position from `bar:beat` with both starting from 1, e.g. `4:3` or `4:2.5`, the beat can be omitted
+impl FromStr for BarPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bar, beat) = s.trim().split_once(':').unwrap_or((s.trim(), "1"));
+        let bar = bar.trim().parse::<u32>().context("Invalid bar")?;
+        let beat = beat.trim().parse::<f32>().context("Invalid beat")?;
+        if bar < 1 || beat < 1.0 {
+            bail!("Bars and beats start from 1");
+        }
+
+        Ok(Self {
+            bar: bar - 1,
+            beat: beat - 1.0,
+        })
+    }
+}
+
+/// The time signature points covering a range of bars
+struct Segment {
+    point: TimeSignaturePoint,
+    first_bar: u32,
+    end: Option<Beat>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Resource))]
+pub struct TimeSignatureList(pub Vec<TimeSignaturePoint>);
+
+impl<'de> Deserialize<'de> for TimeSignatureList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let points = Vec::<TimeSignaturePoint>::deserialize(deserializer)?;
+        if points.is_empty() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self::new(points))
+    }
+}
+
+impl Default for TimeSignatureList {
+    fn default() -> Self {
+        Self(vec![TimeSignaturePoint::new(Beat::ZERO, 4, 4)])
+    }
+}
+
+impl TimeSignatureList {
+    /// Create a list from the points sorted by their beats, only the first point at a beat is kept
+    pub fn new(points: Vec<TimeSignaturePoint>) -> Self {
+        let mut list = Self(points);
+        list.sort();
+        list.0.dedup_by_key(|x| x.beat);
+        list
+    }
+
+    /// Insert a new [`TimeSignaturePoint`] into the list
+    ///
+    /// The point will be inserted in the correct order, it is rejected if there is already a point at its beat
+    ///
+    /// Returns whether the point is inserted
+    pub fn insert(&mut self, point: TimeSignaturePoint) -> bool {
+        if self.0.iter().any(|p| p.beat == point.beat) {
+            return false;
+        }
+
+        let index = self
+            .0
+            .iter()
+            .position(|p| p.beat > point.beat)
+            .unwrap_or(self.0.len());
+        self.0.insert(index, point);
+        true
+    }
+
+    /// Sort the points by their beats, e.g. after the beat of a point is edited
+    pub fn sort(&mut self) {
+        self.0.sort_by_key(|x| x.beat);
+    }
+
+    fn segments(&self) -> Vec<Segment> {
+        assert!(!self.0.is_empty(), "No time signature points available");
+
+        let mut bar = 0;
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let end = self.0.get(index + 1).map(|x| x.beat);
+                let segment = Segment {
+                    point: *point,
+                    first_bar: bar,
+                    end,
+                };
+                if let Some(end) = end {
+                    let bars = Rational32::from(end - point.beat) / point.bar_length();
+                    bar += bars.ceil().to_integer().max(0) as u32;
+                }
+                segment
+            })
+            .collect()
+    }
+
+    /// The time signature point at the beat
+    pub fn point_at(&self, beat: Beat) -> TimeSignaturePoint {
+        self.0
+            .iter()
+            .take_while(|p| p.beat <= beat)
+            .last()
+            .or_else(|| self.0.first())
+            .copied()
+            .expect("No time signature points available")
+    }
+
+    /// Get the position of a beat in bar:beat terms
+    pub fn position(&self, beat: f32) -> BarPosition {
+        let bar = self.bar_at(beat);
+        let start = self.bar_start(bar.floor() as u32);
+        BarPosition {
+            bar: bar.floor() as u32,
+            beat: (beat - start.value()).max(0.0) / to_f32(self.point_at(start).beat_length()),
+        }
+    }
+
+    /// Get the beat at a position in bar:beat terms
+    pub fn beat_at_position(&self, position: BarPosition) -> f32 {
+        let start = self.bar_start(position.bar);
+        start.value() + position.beat * to_f32(self.point_at(start).beat_length())
+    }
+
+    /// Get the bar at the beat, the fractional part is the progress in the bar
+    pub fn bar_at(&self, beat: f32) -> f32 {
+        let segments = self.segments();
+        let segment = segments
+            .iter()
+            .take_while(|x| x.point.beat.value() <= beat)
+            .last()
+            .unwrap_or(&segments[0]);
+
+        let progress =
+            (beat - segment.point.beat.value()).max(0.0) / to_f32(segment.point.bar_length());
+        segment.first_bar as f32 + progress
+    }
+
+    /// Get the beat of a bar, the fractional part of the bar is the progress in the bar
+    pub fn beat_at_bar(&self, bar: f32) -> f32 {
+        let segments = self.segments();
+        let segment = segments
+            .iter()
+            .take_while(|x| x.first_bar as f32 <= bar)
+            .last()
+            .unwrap_or(&segments[0]);
+
+        let beat = segment.point.beat.value()
+            + (bar - segment.first_bar as f32).max(0.0) * to_f32(segment.point.bar_length());
+        match segment.end {
+            Some(end) => beat.min(end.value()),
+            None => beat,
+        }
+    }
+
+    /// Get the beat where the bar starts
+    pub fn bar_start(&self, bar: u32) -> Beat {
+        let segments = self.segments();
+        let segment = segments
+            .iter()
+            .take_while(|x| x.first_bar <= bar)
+            .last()
+            .unwrap_or(&segments[0]);
+
+        let offset = segment.point.bar_length() * (bar - segment.first_bar) as i32;
+        segment.point.beat + Beat::from(offset)
+    }
+
+    /// All beats of the time signatures until the given beat (inclusive)
+    pub fn bar_beats(&self, until: Beat) -> Vec<BarBeat> {
+        let mut beats = vec![];
+
+        for segment in self.segments() {
+            let point = segment.point;
+            let mut bar = segment.first_bar;
+            let mut bar_start = point.beat;
+            'bars: loop {
+                for index in 0..point.numerator.max(1) {
+                    let beat = bar_start + Beat::from(point.beat_length() * index as i32);
+                    if beat > until || segment.end.is_some_and(|end| beat >= end) {
+                        break 'bars;
+                    }
+                    beats.push(BarBeat { beat, bar, index });
+                }
+                bar_start += Beat::from(point.bar_length());
+                bar += 1;
+            }
+        }
+
+        beats
+    }
+}
+
+fn to_f32(value: Rational32) -> f32 {
+    *value.numer() as f32 / *value.denom() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beat;
+
+    fn list() -> TimeSignatureList {
+        // two bars of 4/4, two bars of 7/8, then 3/4
+        TimeSignatureList::new(vec![
+            TimeSignaturePoint::new(beat!(0), 4, 4),
+            TimeSignaturePoint::new(beat!(8), 7, 8),
+            TimeSignaturePoint::new(beat!(15), 3, 4),
+        ])
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut list = list();
+        assert!(list.insert(TimeSignaturePoint::new(beat!(4), 3, 4)));
+        assert!(!list.insert(TimeSignaturePoint::new(beat!(8), 3, 4)));
+        assert_eq!(
+            list.0.iter().map(|x| x.beat).collect::<Vec<_>>(),
+            vec![beat!(0), beat!(4), beat!(8), beat!(15)]
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let list: TimeSignatureList = serde_json::from_str(
+            r#"[
+                { "beat": [8, 0, 1], "numerator": 7, "denominator": 8 },
+                { "beat": [0, 0, 1], "numerator": 4, "denominator": 4 },
+                { "beat": [8, 0, 1], "numerator": 3, "denominator": 4 }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            list.0,
+            vec![
+                TimeSignaturePoint::new(beat!(0), 4, 4),
+                TimeSignaturePoint::new(beat!(8), 7, 8),
+            ]
+        );
+
+        let list: TimeSignatureList = serde_json::from_str("[]").unwrap();
+        assert_eq!(list, TimeSignatureList::default());
+        assert_eq!(list.bar_start(1), beat!(4));
+    }
+
+    #[test]
+    fn test_bar_start() {
+        let list = list();
+        assert_eq!(list.bar_start(0), beat!(0));
+        assert_eq!(list.bar_start(1), beat!(4));
+        assert_eq!(list.bar_start(2), beat!(8));
+        assert_eq!(list.bar_start(3), beat!(11, 1, 2));
+        assert_eq!(list.bar_start(4), beat!(15));
+        assert_eq!(list.bar_start(5), beat!(18));
+    }
+
+    #[test]
+    fn test_position() {
+        let list = list();
+        assert_eq!(list.bar_at(6.0), 1.5);
+        assert_eq!(list.bar_at(15.0), 4.0);
+        assert_eq!(list.beat_at_bar(3.0), 11.5);
+        assert_eq!(list.beat_at_bar(4.5), 16.5);
+
+        assert_eq!(list.position(12.5), BarPosition { bar: 3, beat: 2.0 });
+        assert_eq!(list.position(12.5).to_string(), "4:3.00");
+        assert_eq!(list.beat_at_position("4:3".parse().unwrap()), 12.5);
+        assert_eq!(list.beat_at_position("5".parse().unwrap()), 15.0);
+        assert!("0:1".parse::<BarPosition>().is_err());
+    }
+
+    #[test]
+    fn test_partial_bar() {
+        // the second bar of 3/4 is cut short by 4/4
+        let list = TimeSignatureList::new(vec![
+            TimeSignaturePoint::new(beat!(0), 3, 4),
+            TimeSignaturePoint::new(beat!(5), 4, 4),
+        ]);
+        assert_eq!(list.bar_start(2), beat!(5));
+
+        let beats = list.bar_beats(beat!(6));
+        let downbeats = beats
+            .iter()
+            .filter(|x| x.index == 0)
+            .map(|x| (x.bar, x.beat))
+            .collect::<Vec<_>>();
+        assert_eq!(downbeats, vec![(0, beat!(0)), (1, beat!(3)), (2, beat!(5))]);
+        assert_eq!(beats.len(), 7);
+    }
+
+    #[test]
+    fn test_bar_beats() {
+        let beats = list().bar_beats(beat!(12));
+        assert_eq!(
+            beats[8],
+            BarBeat {
+                beat: beat!(8),
+                bar: 2,
+                index: 0
+            }
+        );
+        assert_eq!(beats[9].beat, beat!(8, 1, 2));
+        assert_eq!(beats.last().unwrap().beat, beat!(12));
+    }
+}
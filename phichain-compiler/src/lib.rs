@@ -1,14 +1,26 @@
-mod steps;
+mod pipeline;
+pub mod source_map;
+pub mod steps;
 mod utils;
 
-use crate::steps::merge_children_line;
-use phichain_chart::primitive::{Format, PrimitiveChart};
+pub use pipeline::{CompileOutput, CompileStep, Pipeline, StepTiming};
+
+use phichain_chart::primitive::PrimitiveChart;
+use phichain_chart::report::ConversionReport;
 use phichain_chart::serialization::PhichainChart;
 
-/// Compile a Phichain chart into a primitive chart
+/// Compile a Phichain chart into a primitive chart with the default [`Pipeline`], lossy transformations are logged
 pub fn compile(chart: PhichainChart) -> anyhow::Result<PrimitiveChart> {
-    let chart = merge_children_line(chart);
+    let (chart, report) = compile_with_report(chart)?;
+    report.log();
+    Ok(chart)
+}
 
-    // TODO: move into_primitive implementation here and use compile() in into_primitive
-    chart.into_primitive()
+/// Compile a Phichain chart into a primitive chart with the default [`Pipeline`], along with its lossy transformations
+pub fn compile_with_report(
+    chart: PhichainChart,
+) -> anyhow::Result<(PrimitiveChart, ConversionReport)> {
+    Pipeline::default()
+        .compile(chart)
+        .map(|output| (output.chart, output.report))
 }
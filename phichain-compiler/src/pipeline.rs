@@ -0,0 +1,281 @@
+use crate::source_map::{SourceIndex, SourceMap};
+use crate::steps::{BakeExpressions, MergeChildrenLine, SimplifyEvents};
+use anyhow::Context;
+use phichain_chart::primitive::{Format, PrimitiveChart};
+use phichain_chart::report::ConversionReport;
+use phichain_chart::serialization::PhichainChart;
+use std::time::{Duration, Instant};
+
+/// A pass transforming a chart during compilation
+///
+/// Options of a step are fields of the implementing type, configure them when adding the step into a [`Pipeline`]
+///
+/// Steps should keep the ids of lines, notes and events they pass through, so a [`SourceMap`] can trace them back,
+/// and push every lossy transformation they make into the report, located in the chart they receive
+pub trait CompileStep {
+    /// Unique name of the step, used to enable, disable or replace it in a [`Pipeline`]
+    fn name(&self) -> &'static str;
+
+    fn apply(
+        &self,
+        chart: PhichainChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PhichainChart>;
+}
+
+struct PipelineEntry {
+    step: Box<dyn CompileStep>,
+    enabled: bool,
+}
+
+/// Time spent in a step of a pipeline
+#[derive(Debug, Clone)]
+pub struct StepTiming {
+    pub name: &'static str,
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+pub struct CompileOutput {
+    pub chart: PrimitiveChart,
+    /// Timings of the enabled steps, in the order they are applied
+    pub timings: Vec<StepTiming>,
+    /// Sources of the items in the compiled chart, only built by [`Pipeline::compile_with_source_map`]
+    pub source_map: Option<SourceMap>,
+    /// Lossy transformations made by the steps and the conversion into a primitive chart
+    pub report: ConversionReport,
+}
+
+/// An ordered list of [`CompileStep`]s applied to a chart before converting it into a primitive chart
+pub struct Pipeline {
+    entries: Vec<PipelineEntry>,
+}
+
+/// The pipeline used by [`crate::compile`], baking expressions and flattening child lines for formats without them and simplifying the result
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::empty()
+            .with(BakeExpressions::default())
+            .with(MergeChildrenLine::default())
+            .with(SimplifyEvents::default())
+    }
+}
+
+impl Pipeline {
+    /// A pipeline without any step
+    pub fn empty() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Append a step to the end of the pipeline
+    pub fn with(mut self, step: impl CompileStep + 'static) -> Self {
+        self.push(step);
+        self
+    }
+
+    /// Append a step to the end of the pipeline
+    pub fn push(&mut self, step: impl CompileStep + 'static) {
+        self.entries.push(PipelineEntry {
+            step: Box::new(step),
+            enabled: true,
+        });
+    }
+
+    /// Insert a step before the step with the given name
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        step: impl CompileStep + 'static,
+    ) -> anyhow::Result<()> {
+        let index = self.position(name)?;
+        self.entries.insert(
+            index,
+            PipelineEntry {
+                step: Box::new(step),
+                enabled: true,
+            },
+        );
+        Ok(())
+    }
+
+    /// Insert a step after the step with the given name
+    pub fn insert_after(
+        &mut self,
+        name: &str,
+        step: impl CompileStep + 'static,
+    ) -> anyhow::Result<()> {
+        let index = self.position(name)?;
+        self.entries.insert(
+            index + 1,
+            PipelineEntry {
+                step: Box::new(step),
+                enabled: true,
+            },
+        );
+        Ok(())
+    }
+
+    /// Replace the step with the same name, e.g. to change its options
+    pub fn replace(&mut self, step: impl CompileStep + 'static) -> anyhow::Result<()> {
+        let index = self.position(step.name())?;
+        self.entries[index].step = Box::new(step);
+        Ok(())
+    }
+
+    /// Enable or disable the step with the given name, disabled steps are skipped
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> anyhow::Result<()> {
+        let index = self.position(name)?;
+        self.entries[index].enabled = enabled;
+        Ok(())
+    }
+
+    /// Names of the steps with their enabled state, in the order they are applied
+    pub fn steps(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.entries
+            .iter()
+            .map(|entry| (entry.step.name(), entry.enabled))
+    }
+
+    fn position(&self, name: &str) -> anyhow::Result<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.step.name() == name)
+            .with_context(|| format!("No compile step named `{}`", name))
+    }
+
+    /// Apply all enabled steps to the chart, pushing their lossy transformations into the report
+    pub fn apply(
+        &self,
+        chart: PhichainChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<(PhichainChart, Vec<StepTiming>)> {
+        let mut chart = chart;
+        let mut timings = vec![];
+
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            let name = entry.step.name();
+            let start = Instant::now();
+            chart = entry
+                .step
+                .apply(chart, report)
+                .with_context(|| format!("Compile step `{}` failed", name))?;
+            timings.push(StepTiming {
+                name,
+                duration: start.elapsed(),
+            });
+        }
+
+        Ok((chart, timings))
+    }
+
+    /// Apply all enabled steps to the chart and convert it into a primitive chart
+    pub fn compile(&self, chart: PhichainChart) -> anyhow::Result<CompileOutput> {
+        let mut report = ConversionReport::default();
+        let (chart, timings) = self.apply(chart, &mut report)?;
+
+        // TODO: move into_primitive implementation here and use compile() in into_primitive
+        Ok(CompileOutput {
+            chart: chart.into_primitive_with_report(&mut report)?,
+            timings,
+            source_map: None,
+            report,
+        })
+    }
+
+    /// Same as [`Pipeline::compile`], also building a [`SourceMap`] of the compiled chart
+    ///
+    /// The source map is indexed by locations in the returned primitive chart, not in charts of other formats converted from it
+    ///
+    /// Items without an id are assigned one first, the ids in the source map only match the given chart if all its items have an id
+    pub fn compile_with_source_map(&self, chart: PhichainChart) -> anyhow::Result<CompileOutput> {
+        let mut chart = chart;
+        chart.assign_ids();
+        let index = SourceIndex::new(&chart);
+
+        let mut report = ConversionReport::default();
+        let (chart, timings) = self.apply(chart, &mut report)?;
+        let source_map = SourceMap::new(&index, &chart);
+
+        Ok(CompileOutput {
+            chart: chart.into_primitive_with_report(&mut report)?,
+            timings,
+            source_map: Some(source_map),
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use phichain_chart::serialization::LineWrapper;
+
+    struct AddLine;
+
+    impl CompileStep for AddLine {
+        fn name(&self) -> &'static str {
+            "add_line"
+        }
+
+        fn apply(
+            &self,
+            mut chart: PhichainChart,
+            _: &mut ConversionReport,
+        ) -> anyhow::Result<PhichainChart> {
+            chart.lines.push(LineWrapper::default());
+            Ok(chart)
+        }
+    }
+
+    struct Fail;
+
+    impl CompileStep for Fail {
+        fn name(&self) -> &'static str {
+            "fail"
+        }
+
+        fn apply(
+            &self,
+            _: PhichainChart,
+            _: &mut ConversionReport,
+        ) -> anyhow::Result<PhichainChart> {
+            bail!("failed on purpose")
+        }
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::default();
+        pipeline
+            .insert_before(MergeChildrenLine::NAME, AddLine)
+            .unwrap();
+        assert_eq!(
+            pipeline.steps().collect::<Vec<_>>(),
+            vec![
+                (BakeExpressions::NAME, true),
+                ("add_line", true),
+                (MergeChildrenLine::NAME, true),
+                (SimplifyEvents::NAME, true)
+            ]
+        );
+
+        let output = pipeline.compile(PhichainChart::default()).unwrap();
+        assert_eq!(output.chart.lines.len(), 2);
+        assert_eq!(output.timings.len(), 4);
+
+        pipeline.set_enabled("add_line", false).unwrap();
+        let output = pipeline.compile(PhichainChart::default()).unwrap();
+        assert_eq!(output.chart.lines.len(), 1);
+        assert_eq!(output.timings.len(), 3);
+
+        assert!(pipeline.set_enabled("missing", false).is_err());
+    }
+
+    #[test]
+    fn test_step_error() {
+        let pipeline = Pipeline::empty().with(Fail);
+        let error = pipeline.compile(PhichainChart::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Compile step `fail` failed");
+    }
+}
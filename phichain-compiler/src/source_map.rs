@@ -0,0 +1,227 @@
+//! Links lines, notes and events of a compiled chart back to the chart they are compiled from
+//!
+//! Items are traced through their persistent [`Id`], steps keep the id of the items they pass through
+//! and items derived from a whole line (e.g. events baked by [`crate::steps::MergeChildrenLine`]) fall back to the line
+//!
+//! The map is indexed by locations in the compiled primitive chart, i.e. the output of [`crate::Pipeline::compile`],
+//! not by locations in the chart exported from it: e.g. the official format splits the notes of a line into
+//! `notesAbove` and `notesBelow` and cuts events into linear events, so its indexes must not be looked up in the map
+
+use phichain_chart::id::Id;
+use phichain_chart::lint::Location;
+use phichain_chart::serialization::{LineWrapper, PhichainChart};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Where an item of the compiled chart comes from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Source {
+    /// Location of the item in the source chart, the line producing the item if it has no single source item
+    pub location: Location,
+    /// Persistent id of the item at [`Source::location`]
+    pub id: Id,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct LineSources {
+    line: Option<Source>,
+    notes: Vec<Option<Source>>,
+    events: Vec<Option<Source>>,
+    children: Vec<LineSources>,
+}
+
+/// Sources of all lines, notes and events of a compiled chart, in the same layout as the compiled primitive chart
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceMap {
+    lines: Vec<LineSources>,
+}
+
+/// Locations of all items of a source chart by their ids
+#[derive(Default)]
+pub(crate) struct SourceIndex {
+    lines: HashMap<Id, Location>,
+    notes: HashMap<Id, Location>,
+    events: HashMap<Id, Location>,
+}
+
+impl SourceIndex {
+    pub(crate) fn new(chart: &PhichainChart) -> Self {
+        let mut index = Self::default();
+        for (i, line) in chart.lines.iter().enumerate() {
+            index.add_line(line, vec![i]);
+        }
+        index
+    }
+
+    fn add_line(&mut self, line: &LineWrapper, path: Vec<usize>) {
+        if let Some(id) = line.line.id {
+            self.lines.insert(id, Location::Line { line: path.clone() });
+        }
+        for (index, note) in line.notes.iter().enumerate() {
+            if let Some(id) = note.id {
+                let line = path.clone();
+                self.notes.insert(id, Location::Note { line, index });
+            }
+        }
+        for (index, event) in line.events.iter().enumerate() {
+            if let Some(id) = event.id {
+                let line = path.clone();
+                self.events.insert(id, Location::Event { line, index });
+            }
+        }
+        for (index, child) in line.children.iter().enumerate() {
+            let mut path = path.clone();
+            path.push(index);
+            self.add_line(child, path);
+        }
+    }
+
+    fn source(map: &HashMap<Id, Location>, id: Option<Id>) -> Option<Source> {
+        let id = id?;
+        map.get(&id).map(|location| Source {
+            location: location.clone(),
+            id,
+        })
+    }
+
+    fn line_sources(&self, line: &LineWrapper) -> LineSources {
+        let line_source = Self::source(&self.lines, line.line.id);
+        let or_line = |source: Option<Source>| source.or_else(|| line_source.clone());
+
+        LineSources {
+            notes: line
+                .notes
+                .iter()
+                .map(|note| or_line(Self::source(&self.notes, note.id)))
+                .collect(),
+            events: line
+                .events
+                .iter()
+                .map(|event| or_line(Self::source(&self.events, event.id)))
+                .collect(),
+            children: line
+                .children
+                .iter()
+                .map(|child| self.line_sources(child))
+                .collect(),
+            line: line_source.clone(),
+        }
+    }
+}
+
+impl SourceMap {
+    /// Build the source map of a compiled chart from the index of its source chart
+    pub(crate) fn new(index: &SourceIndex, compiled: &PhichainChart) -> Self {
+        Self {
+            lines: compiled
+                .lines
+                .iter()
+                .map(|line| index.line_sources(line))
+                .collect(),
+        }
+    }
+
+    /// Find the source of a line, a note or an event at a location in the compiled primitive chart
+    pub fn source(&self, location: &Location) -> Option<&Source> {
+        let line = |path: &[usize]| {
+            let (first, rest) = path.split_first()?;
+            rest.iter()
+                .try_fold(self.lines.get(*first)?, |line, index| {
+                    line.children.get(*index)
+                })
+        };
+
+        match location {
+            Location::BpmPoint { .. } => None,
+            Location::Line { line: path } => line(path)?.line.as_ref(),
+            Location::Note { line: path, index } => line(path)?.notes.get(*index)?.as_ref(),
+            Location::Event { line: path, index } => line(path)?.events.get(*index)?.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pipeline;
+    use phichain_chart::beat;
+    use phichain_chart::event::{LineEvent, LineEventKind, LineEventValue};
+    use phichain_chart::note::{Note, NoteKind};
+
+    #[test]
+    fn test_source_map() {
+        let mut child = LineWrapper::default();
+        child
+            .notes
+            .push(Note::new(NoteKind::Tap, true, beat!(1), 0.0, 1.0));
+        child.events.push(LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(1),
+            end_beat: beat!(2),
+            value: LineEventValue::transition(0.0, 100.0, Default::default()),
+            id: None,
+        });
+        let parent = LineWrapper {
+            children: vec![child],
+            ..Default::default()
+        };
+        let chart = PhichainChart {
+            lines: vec![LineWrapper::default(), parent],
+            ..Default::default()
+        };
+
+        let output = Pipeline::default().compile_with_source_map(chart).unwrap();
+        let source_map = output.source_map.unwrap();
+
+        // the child line is flattened before its parent
+        let child = &output.chart.lines[1];
+        let source = |location| source_map.source(&location).map(|x| x.location.clone());
+        assert_eq!(
+            source(Location::Line { line: vec![1] }),
+            Some(Location::Line { line: vec![1, 0] })
+        );
+        assert_eq!(
+            source(Location::Note {
+                line: vec![1],
+                index: 0
+            }),
+            Some(Location::Note {
+                line: vec![1, 0],
+                index: 0
+            })
+        );
+
+        // the position of the child is baked from both lines, traced back to the child line
+        let x = child
+            .events
+            .iter()
+            .position(|event| event.kind.is_x())
+            .unwrap();
+        assert_eq!(
+            source(Location::Event {
+                line: vec![1],
+                index: x
+            }),
+            Some(Location::Line { line: vec![1, 0] })
+        );
+
+        // events kept by the steps are traced back to the event
+        let opacity = child
+            .events
+            .iter()
+            .position(|event| event.kind.is_opacity())
+            .unwrap();
+        assert_eq!(
+            source(Location::Event {
+                line: vec![1],
+                index: opacity
+            }),
+            Some(Location::Event {
+                line: vec![1, 0],
+                index: 3
+            })
+        );
+
+        assert_eq!(source(Location::Line { line: vec![3] }), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::pipeline::CompileStep;
+use num::Rational32;
+use phichain_chart::beat::Beat;
+use phichain_chart::easing::Easing;
+use phichain_chart::event::{LineEvent, LineEventValue};
+use phichain_chart::report::ConversionReport;
+use phichain_chart::serialization::{LineWrapper, PhichainChart};
+
+/// Number of points sampled in a segment to measure its error
+const BAKE_SAMPLES: u32 = 16;
+/// Maximum times an event can be subdivided, an event is baked into at most `2^BAKE_MAX_DEPTH` segments
+const BAKE_MAX_DEPTH: u32 = 10;
+
+/// Bake expression events into linear transitions, for formats without expressions
+///
+/// An event is subdivided in half recursively until each segment is close enough to a straight line,
+/// the segments keep the id of the event. Runs of segments are re-fitted into eased events by [`super::SimplifyEvents`]
+#[derive(Debug, Clone, Copy)]
+pub struct BakeExpressions {
+    /// Maximum error allowed between the segments and the expression,
+    /// measured in pixels for position, degrees for rotation and 0-255 for opacity
+    pub tolerance: f32,
+}
+
+impl BakeExpressions {
+    pub const NAME: &'static str = "bake_expressions";
+}
+
+impl Default for BakeExpressions {
+    fn default() -> Self {
+        Self { tolerance: 0.01 }
+    }
+}
+
+impl CompileStep for BakeExpressions {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    // baking is bounded by the tolerance, so it is not reported as a loss
+    fn apply(
+        &self,
+        chart: PhichainChart,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<PhichainChart> {
+        Ok(PhichainChart {
+            lines: chart
+                .lines
+                .into_iter()
+                .map(|line| self.bake_line(line))
+                .collect(),
+            ..chart
+        })
+    }
+}
+
+impl BakeExpressions {
+    fn bake_line(&self, line: LineWrapper) -> LineWrapper {
+        LineWrapper {
+            events: line
+                .events
+                .iter()
+                .flat_map(|event| self.bake_event(event))
+                .collect(),
+            children: line
+                .children
+                .into_iter()
+                .map(|child| self.bake_line(child))
+                .collect(),
+            ..line
+        }
+    }
+
+    fn bake_event(&self, event: &LineEvent) -> Vec<LineEvent> {
+        if !event.value.is_expression() {
+            return vec![event.clone()];
+        }
+        if event.start_beat >= event.end_beat {
+            return vec![LineEvent {
+                value: LineEventValue::constant(event.end_value()),
+                ..*event
+            }];
+        }
+
+        let mut events = vec![];
+        self.subdivide(event, event.start_beat, event.end_beat, 0, &mut events);
+        events
+    }
+
+    fn subdivide(
+        &self,
+        event: &LineEvent,
+        start_beat: Beat,
+        end_beat: Beat,
+        depth: u32,
+        events: &mut Vec<LineEvent>,
+    ) {
+        let evaluate = |beat: f32| event.evaluate(beat).value().unwrap_or_default();
+        let (from, to) = (start_beat.value(), end_beat.value());
+        let (start, end) = (evaluate(from), evaluate(to));
+
+        let error = (1..BAKE_SAMPLES)
+            .map(|i| {
+                let percent = i as f32 / BAKE_SAMPLES as f32;
+                let linear = start + (end - start) * percent;
+                (evaluate(from + (to - from) * percent) - linear).abs()
+            })
+            .fold(0.0, f32::max);
+
+        if error <= self.tolerance || depth >= BAKE_MAX_DEPTH {
+            events.push(LineEvent {
+                start_beat,
+                end_beat,
+                value: LineEventValue::transition(start, end, Easing::Linear),
+                ..*event
+            });
+            return;
+        }
+
+        let middle = Beat::from((Rational32::from(start_beat) + Rational32::from(end_beat)) / 2);
+        self.subdivide(event, start_beat, middle, depth + 1, events);
+        self.subdivide(event, middle, end_beat, depth + 1, events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phichain_chart::beat;
+    use phichain_chart::event::LineEventKind;
+    use phichain_chart::expression::Expression;
+
+    #[test]
+    fn test_bake_expressions() {
+        let event = LineEvent {
+            kind: LineEventKind::X,
+            start_beat: beat!(1),
+            end_beat: beat!(5),
+            value: LineEventValue::expression(
+                Expression::parse("sin(progress * tau) * 100").unwrap(),
+            ),
+            id: None,
+        };
+        let step = BakeExpressions { tolerance: 0.1 };
+        let events = step.bake_event(&event);
+
+        assert!(events.len() > 1);
+        assert_eq!(events.first().unwrap().start_beat, beat!(1));
+        assert_eq!(events.last().unwrap().end_beat, beat!(5));
+        assert!(events.windows(2).all(|x| x[0].end_beat == x[1].start_beat));
+        assert!(events.iter().all(|e| e.value.is_transition()));
+
+        for i in 0..=400 {
+            let beat = 1.0 + i as f32 / 100.0;
+            let baked = events
+                .iter()
+                .find(|e| e.end_beat.value() >= beat)
+                .and_then(|e| e.evaluate(beat).value())
+                .unwrap();
+            let expected = event.evaluate(beat).value().unwrap();
+            assert!((baked - expected).abs() < 0.1, "at {}", beat);
+        }
+    }
+}
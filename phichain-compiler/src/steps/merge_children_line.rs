@@ -1,157 +1,347 @@
+use crate::pipeline::CompileStep;
 use crate::utils::EventSequence;
-use nalgebra::{Isometry2, Rotation2, Vector2};
+use nalgebra::{Isometry2, Point2, Rotation2, Vector2};
 use phichain_chart::beat;
+use phichain_chart::beat::Beat;
 use phichain_chart::easing::Easing;
 use phichain_chart::event::{LineEvent, LineEventKind, LineEventValue};
+use phichain_chart::lint::Location;
+use phichain_chart::report::{ConversionReport, LossKind};
 use phichain_chart::serialization::{LineWrapper, PhichainChart};
 
-fn merge(parent: LineWrapper) -> Vec<LineWrapper> {
-    if parent.children.is_empty() {
-        vec![parent]
-    } else {
-        let children = parent
-            .children
-            .iter()
-            .flat_map(|x| merge(x.clone()))
-            .collect::<Vec<_>>();
+/// State of a line in the world at a beat
+#[derive(Debug, Clone, Copy)]
+struct LineState {
+    x: f32,
+    y: f32,
+    /// In degrees
+    rotation: f32,
+    opacity: f32,
+    speed: f32,
+    scale_x: f32,
+    scale_y: f32,
+}
 
-        let mut merged_children = vec![];
+/// Evaluate the state of the last line of `path` in the world, `path` holds the line and all its ancestors from the root
+///
+/// Events starting at the beat have no effect if `start` is true, for the start of fragments
+fn evaluate(path: &[&LineWrapper], beat: Beat, start: bool) -> LineState {
+    let (line, ancestors) = path.split_last().expect("path should not be empty");
 
-        for child in children {
-            let mut merged_move_events = vec![];
-            let mut merged_rotate_events = vec![];
+    let value = |events: Vec<LineEvent>, default: f32| {
+        if start {
+            events.evaluate_start_no_effect(beat, default)
+        } else {
+            events.evaluate(beat, default)
+        }
+    };
 
-            let mut splits = vec![];
+    let state = LineState {
+        x: value(line.events.x(), 0.0),
+        y: value(line.events.y(), 0.0),
+        rotation: value(line.events.rotation(), 0.0),
+        opacity: value(line.events.opacity(), 0.0),
+        // same defaults as phichain-game
+        speed: value(line.events.speed(), 10.0),
+        scale_x: value(line.events.scale_x(), 1.0),
+        scale_y: value(line.events.scale_y(), 1.0),
+    };
 
-            for event in parent
-                .events
-                .iter()
-                .filter(|x| x.kind.is_x() || x.kind.is_y() || x.kind.is_rotation())
-            {
-                splits.push(event.start_beat);
-                splits.push(event.end_beat);
-            }
-            for event in child
-                .events
-                .iter()
-                .filter(|x| x.kind.is_x() || x.kind.is_y() || x.kind.is_rotation())
-            {
-                splits.push(event.start_beat);
-                splits.push(event.end_beat);
-            }
+    if ancestors.is_empty() {
+        return state;
+    }
 
-            splits.dedup();
-            splits.sort();
-
-            let minimum = beat!(1, 32);
-
-            if let (Some(first), Some(last)) = (splits.first().copied(), splits.last().copied()) {
-                let mut current = first;
-                while current < last {
-                    let start_beat = current;
-                    let end_beat = current + minimum;
-
-                    macro_rules! evaluate {
-                        ($target:ident, $filter:ident) => {
-                            (
-                                $target
-                                    .events
-                                    .$filter()
-                                    .evaluate_start_no_effect(start_beat),
-                                $target.events.$filter().evaluate(end_beat),
-                            )
-                        };
-                    }
-
-                    macro_rules! evaluate_line {
-                        ($target:ident) => {{
-                            let (start_x, end_x) = evaluate!($target, x);
-                            let (start_y, end_y) = evaluate!($target, y);
-                            let (start_rotation, end_rotation) = evaluate!($target, rotation);
-
-                            let start = Isometry2::new(
-                                Vector2::new(start_x, start_y),
-                                Rotation2::new(start_rotation.to_radians()).angle(),
-                            );
-                            let end = Isometry2::new(
-                                Vector2::new(end_x, end_y),
-                                Rotation2::new(end_rotation.to_radians()).angle(),
-                            );
-
-                            (start, end)
-                        }};
-                    }
-
-                    let (parent_start, parent_end) = evaluate_line!(parent);
-                    let (child_start, child_end) = evaluate_line!(child);
-
-                    let start = parent_start * child_start;
-                    let end = parent_end * child_end;
-
-                    merged_move_events.push(LineEvent {
-                        kind: LineEventKind::X,
-                        start_beat,
-                        end_beat,
-                        value: LineEventValue::transition(
-                            start.translation.x,
-                            end.translation.x,
-                            Easing::Linear,
-                        ),
-                    });
-                    merged_move_events.push(LineEvent {
-                        kind: LineEventKind::Y,
-                        start_beat,
-                        end_beat,
-                        value: LineEventValue::transition(
-                            start.translation.y,
-                            end.translation.y,
-                            Easing::Linear,
-                        ),
-                    });
-                    merged_rotate_events.push(LineEvent {
-                        kind: LineEventKind::Rotation,
+    let parent = evaluate(ancestors, beat, start);
+    let inheritance = line.line.inheritance;
+
+    let transform = Isometry2::new(
+        Vector2::new(parent.x, parent.y),
+        Rotation2::new(parent.rotation.to_radians()).angle(),
+    );
+    let position = transform * Point2::new(state.x, state.y);
+
+    LineState {
+        x: position.x,
+        y: position.y,
+        rotation: if inheritance.rotation {
+            parent.rotation + state.rotation
+        } else {
+            state.rotation
+        },
+        opacity: if inheritance.opacity {
+            state.opacity * parent.opacity / 255.0
+        } else {
+            state.opacity
+        },
+        speed: if inheritance.speed {
+            state.speed + parent.speed
+        } else {
+            state.speed
+        },
+        scale_x: if inheritance.scale {
+            state.scale_x * parent.scale_x
+        } else {
+            state.scale_x
+        },
+        scale_y: if inheritance.scale {
+            state.scale_y * parent.scale_y
+        } else {
+            state.scale_y
+        },
+    }
+}
+
+/// Bake the state of a child line in the world into linear events, `path` holds the line and all its ancestors from the root
+fn bake(path: &[&LineWrapper], resolution: Beat) -> LineWrapper {
+    let line = *path.last().expect("path should not be empty");
+    let inheritance = line.line.inheritance;
+
+    // the position always depends on the parent, other kinds only when inherited
+    let baked = |kind: &LineEventKind| match kind {
+        LineEventKind::X | LineEventKind::Y => true,
+        LineEventKind::Rotation => inheritance.rotation,
+        LineEventKind::Opacity => inheritance.opacity,
+        LineEventKind::Speed => inheritance.speed,
+        LineEventKind::ScaleX | LineEventKind::ScaleY => inheritance.scale,
+        LineEventKind::Color | LineEventKind::Text => false,
+    };
+
+    let mut splits = path
+        .iter()
+        .flat_map(|line| line.events.iter())
+        .filter(|event| !event.kind.is_color() && !event.kind.is_text())
+        .flat_map(|event| [event.start_beat, event.end_beat])
+        .collect::<Vec<_>>();
+    splits.sort();
+    splits.dedup();
+
+    let mut events = line
+        .events
+        .iter()
+        .filter(|event| !baked(&event.kind))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if let (Some(first), Some(last)) = (splits.first().copied(), splits.last().copied()) {
+        let mut current = first;
+        while current < last {
+            let start_beat = current;
+            let end_beat = current + resolution;
+
+            let start = evaluate(path, start_beat, true);
+            let end = evaluate(path, end_beat, false);
+
+            let values = [
+                (LineEventKind::X, start.x, end.x),
+                (LineEventKind::Y, start.y, end.y),
+                (LineEventKind::Rotation, start.rotation, end.rotation),
+                (LineEventKind::Opacity, start.opacity, end.opacity),
+                (LineEventKind::Speed, start.speed, end.speed),
+                (LineEventKind::ScaleX, start.scale_x, end.scale_x),
+                (LineEventKind::ScaleY, start.scale_y, end.scale_y),
+            ];
+
+            for (kind, start, end) in values {
+                if baked(&kind) {
+                    events.push(LineEvent {
+                        kind,
                         start_beat,
                         end_beat,
-                        value: LineEventValue::transition(
-                            start.rotation.angle().to_degrees(),
-                            end.rotation.angle().to_degrees(),
-                            Easing::Linear,
-                        ),
+                        value: LineEventValue::transition(start, end, Easing::Linear),
+                        id: None,
                     });
-
-                    current += minimum;
                 }
             }
 
-            let other_events = child
-                .events
-                .iter()
-                .filter(|x| !x.kind.is_x() && !x.kind.is_y() && !x.kind.is_rotation())
-                .cloned()
-                .collect::<Vec<_>>();
+            current += resolution;
+        }
+    }
+
+    LineWrapper {
+        events,
+        children: vec![],
+        ..line.clone()
+    }
+}
 
-            let merged = LineWrapper {
-                events: [other_events, merged_move_events, merged_rotate_events].concat(),
-                children: vec![],
-                ..child
-            };
+/// Flatten a line and its descendants, children come before their parent
+fn merge<'a>(
+    line: &'a LineWrapper,
+    ancestors: &mut Vec<&'a LineWrapper>,
+    resolution: Beat,
+) -> Vec<LineWrapper> {
+    ancestors.push(line);
+    let mut lines = vec![];
+    for child in &line.children {
+        lines.append(&mut merge(child, ancestors, resolution));
+    }
+
+    if ancestors.len() == 1 {
+        lines.push(LineWrapper {
+            children: vec![],
+            ..line.clone()
+        });
+    } else {
+        lines.push(bake(ancestors, resolution));
+    }
+    ancestors.pop();
+
+    lines
+}
+
+/// Flatten all children lines into the root level, baking the state each child inherits from its parents
+///
+/// Position is always relative to the parent, rotation, opacity, speed and scale follow [`phichain_chart::line::LineInheritance`]
+#[derive(Debug, Clone, Copy)]
+pub struct MergeChildrenLine {
+    /// Length of the linear events the transforms of child lines are cut into
+    pub resolution: Beat,
+}
+
+impl MergeChildrenLine {
+    pub const NAME: &'static str = "merge_children_line";
+}
+
+impl Default for MergeChildrenLine {
+    fn default() -> Self {
+        Self {
+            resolution: beat!(1, 32),
+        }
+    }
+}
+
+impl CompileStep for MergeChildrenLine {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
 
-            merged_children.push(merged);
+    fn apply(
+        &self,
+        chart: PhichainChart,
+        report: &mut ConversionReport,
+    ) -> anyhow::Result<PhichainChart> {
+        fn report_children(line: &LineWrapper, path: Vec<usize>, report: &mut ConversionReport) {
+            for (index, child) in line.children.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(index);
+                report.push(
+                    Some(Location::Line { line: path.clone() }),
+                    LossKind::BakedLine,
+                );
+                report_children(child, path, report);
+            }
         }
 
-        merged_children.push(parent);
+        let mut lines = vec![];
+
+        for (index, line) in chart.lines.iter().enumerate() {
+            report_children(line, vec![index], report);
+        }
+
+        for line in chart.lines {
+            lines.append(&mut merge(&line, &mut vec![], self.resolution));
+        }
 
-        merged_children
+        Ok(PhichainChart { lines, ..chart })
     }
 }
 
-/// Flatten all children lines into the root level, calculate event propagation for X, Y and Rotate events
-pub fn merge_children_line(chart: PhichainChart) -> PhichainChart {
-    let mut lines = vec![];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phichain_chart::line::{Line, LineInheritance};
 
-    for line in chart.lines {
-        lines.append(&mut merge(line));
+    fn constant(kind: LineEventKind, value: f32) -> LineEvent {
+        LineEvent {
+            kind,
+            start_beat: beat!(0),
+            end_beat: beat!(1),
+            value: LineEventValue::constant(value),
+            id: None,
+        }
     }
 
-    PhichainChart { lines, ..chart }
+    fn last_value(line: &LineWrapper, kind: LineEventKind) -> f32 {
+        line.events
+            .iter()
+            .rfind(|event| event.kind == kind)
+            .and_then(|event| event.evaluate(1.0).value())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let child = |inheritance| LineWrapper {
+            line: Line {
+                inheritance,
+                ..Default::default()
+            },
+            events: vec![
+                constant(LineEventKind::X, 100.0),
+                constant(LineEventKind::Rotation, 10.0),
+                constant(LineEventKind::Opacity, 255.0),
+                constant(LineEventKind::Speed, 5.0),
+                constant(LineEventKind::ScaleX, 2.0),
+            ],
+            ..Default::default()
+        };
+        let parent = LineWrapper {
+            events: vec![
+                constant(LineEventKind::Rotation, 90.0),
+                constant(LineEventKind::Opacity, 51.0),
+                constant(LineEventKind::Speed, 10.0),
+                constant(LineEventKind::ScaleX, 3.0),
+            ],
+            children: vec![
+                child(LineInheritance::default()),
+                child(LineInheritance {
+                    rotation: false,
+                    opacity: true,
+                    speed: true,
+                    scale: true,
+                }),
+            ],
+            ..Default::default()
+        };
+        let chart = PhichainChart {
+            lines: vec![parent],
+            ..Default::default()
+        };
+
+        let mut report = ConversionReport::default();
+        let lines = MergeChildrenLine::default()
+            .apply(chart, &mut report)
+            .unwrap()
+            .lines;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            report
+                .losses
+                .iter()
+                .map(|loss| loss.location.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Some(Location::Line { line: vec![0, 0] }),
+                Some(Location::Line { line: vec![0, 1] }),
+            ]
+        );
+
+        for child in &lines[..2] {
+            // the position is always rotated by the parent
+            assert!(last_value(child, LineEventKind::X).abs() < 1e-3);
+            assert!((last_value(child, LineEventKind::Y) - 100.0).abs() < 1e-3);
+        }
+
+        let default = &lines[0];
+        assert!((last_value(default, LineEventKind::Rotation) - 100.0).abs() < 1e-3);
+        assert_eq!(last_value(default, LineEventKind::Opacity), 255.0);
+        assert_eq!(last_value(default, LineEventKind::Speed), 5.0);
+        assert_eq!(last_value(default, LineEventKind::ScaleX), 2.0);
+
+        let inheriting = &lines[1];
+        assert_eq!(last_value(inheriting, LineEventKind::Rotation), 10.0);
+        assert_eq!(last_value(inheriting, LineEventKind::Opacity), 51.0);
+        assert_eq!(last_value(inheriting, LineEventKind::Speed), 15.0);
+        assert_eq!(last_value(inheriting, LineEventKind::ScaleX), 6.0);
+    }
 }
@@ -1,3 +1,7 @@
+mod bake_expressions;
 mod merge_children_line;
+mod simplify_events;
 
-pub use merge_children_line::merge_children_line;
+pub use bake_expressions::BakeExpressions;
+pub use merge_children_line::MergeChildrenLine;
+pub use simplify_events::SimplifyEvents;
@@ -0,0 +1,463 @@
+use crate::pipeline::CompileStep;
+use phichain_chart::beat::Beat;
+use phichain_chart::easing::{Easing, Tween};
+use phichain_chart::event::{LineEvent, LineEventKind, LineEventValue};
+use phichain_chart::report::ConversionReport;
+use phichain_chart::serialization::{LineWrapper, PhichainChart};
+use strum::IntoEnumIterator;
+
+/// Minimum number of linear fragments re-fitted into a non-linear easing,
+/// fewer fragments have too few points to tell a curve from a polyline
+const MIN_CURVE_FRAGMENTS: usize = 4;
+/// Maximum number of linear fragments re-fitted into a single event, limiting the cost of searching runs
+const MAX_REFIT_FRAGMENTS: usize = 256;
+
+/// Simplify events without changing how the chart plays
+///
+/// - Linear fragments, e.g. produced by [`super::MergeChildrenLine`], are re-fitted into a single eased event
+/// - Transitions with equal start and end values are converted into constant events
+/// - Adjacent constant events with the same value are merged
+/// - Constant events fully covered by another constant event winning over them are dropped
+///
+/// Merging and re-fitting only apply to kinds of events without overlapping events on a line
+#[derive(Debug, Clone, Copy)]
+pub struct SimplifyEvents {
+    /// Maximum error allowed when re-fitting linear fragments,
+    /// measured in pixels for position, degrees for rotation and 0-255 for opacity
+    pub tolerance: f32,
+}
+
+impl SimplifyEvents {
+    pub const NAME: &'static str = "simplify_events";
+}
+
+impl Default for SimplifyEvents {
+    fn default() -> Self {
+        Self { tolerance: 0.01 }
+    }
+}
+
+impl CompileStep for SimplifyEvents {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    // re-fitting is bounded by the tolerance, so it is not reported as a loss
+    fn apply(
+        &self,
+        chart: PhichainChart,
+        _: &mut ConversionReport,
+    ) -> anyhow::Result<PhichainChart> {
+        Ok(PhichainChart {
+            lines: chart
+                .lines
+                .into_iter()
+                .map(|line| self.simplify_line(line))
+                .collect(),
+            ..chart
+        })
+    }
+}
+
+impl SimplifyEvents {
+    fn simplify_line(&self, line: LineWrapper) -> LineWrapper {
+        let events = LineEventKind::iter()
+            .flat_map(|kind| {
+                let events = line
+                    .events
+                    .iter()
+                    .filter(|event| event.kind == kind)
+                    .cloned()
+                    .collect();
+                self.simplify_events(events)
+            })
+            .collect();
+
+        LineWrapper {
+            events,
+            children: line
+                .children
+                .into_iter()
+                .map(|child| self.simplify_line(child))
+                .collect(),
+            ..line
+        }
+    }
+
+    /// Simplify events of the same kind
+    fn simplify_events(&self, events: Vec<LineEvent>) -> Vec<LineEvent> {
+        let events = drop_shadowed(events.into_iter().map(normalized).collect());
+
+        let mut sorted = events.clone();
+        sorted.sort_by_key(|event| event.start_beat);
+        let overlapping = sorted.windows(2).any(|pair| {
+            pair[0].end_beat > pair[1].start_beat || pair[0].end_beat == pair[1].end_beat
+        });
+        if overlapping {
+            // the order of overlapping events or events ending together matters when evaluating, keep them as is
+            return events;
+        }
+
+        let refitted = self.refit(sorted).into_iter().map(normalized).collect();
+        merge_constants(refitted)
+    }
+
+    /// Re-fit runs of continuous linear fragments into single events
+    fn refit(&self, events: Vec<LineEvent>) -> Vec<LineEvent> {
+        let kind = events.first().map(|event| event.kind);
+        if matches!(
+            kind,
+            None | Some(LineEventKind::Color | LineEventKind::Text)
+        ) {
+            // colors are interpolated channel by channel and texts have no value to fit
+            return events;
+        }
+
+        // the last fragment of the continuous run starting at each index
+        let mut run_ends = vec![0; events.len()];
+        for index in (0..events.len()).rev() {
+            run_ends[index] = match events.get(index + 1) {
+                Some(next) if self.continues(&events[index], next) => run_ends[index + 1],
+                _ => index,
+            };
+        }
+
+        let mut result = vec![];
+        let mut start = 0;
+        // curves are only searched from where an original event likely starts,
+        // which is the start of a run or the end of a re-fitted event
+        let mut boundary = true;
+        while start < events.len() {
+            if start == 0 || run_ends[start - 1] < start {
+                boundary = true;
+            }
+
+            let longest = run_ends[start].min(start + MAX_REFIT_FRAGMENTS - 1);
+            let fitted = if boundary {
+                // prefer the longest run, a part of an eased curve is not the same easing
+                (start + 1..=longest).rev().find_map(|end| {
+                    self.fit(&events[start..=end], true)
+                        .map(|easing| (end, easing))
+                })
+            } else {
+                (start + 1..=longest)
+                    .take_while(|end| self.fit(&events[start..=*end], false).is_some())
+                    .last()
+                    .map(|end| (end, Easing::Linear))
+            };
+
+            match fitted {
+                Some((end, easing)) => {
+                    result.push(LineEvent {
+                        end_beat: events[end].end_beat,
+                        value: LineEventValue::transition(
+                            events[start].value.start(),
+                            events[end].value.end(),
+                            easing,
+                        ),
+                        ..events[start]
+                    });
+                    start = end + 1;
+                    boundary = true;
+                }
+                None => {
+                    result.push(events[start].clone());
+                    start += 1;
+                    boundary = false;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// If the event is a linear fragment continuing the previous one
+    fn continues(&self, previous: &LineEvent, event: &LineEvent) -> bool {
+        let linear = |event: &LineEvent| {
+            matches!(
+                event.value,
+                LineEventValue::Transition {
+                    easing: Easing::Linear,
+                    ..
+                }
+            )
+        };
+
+        linear(previous)
+            && linear(event)
+            && previous.end_beat == event.start_beat
+            && (previous.value.end() - event.value.start()).abs() <= self.tolerance
+    }
+
+    /// Find an easing going through the start and end of all fragments, only linear if `curves` is false
+    fn fit(&self, fragments: &[LineEvent], curves: bool) -> Option<Easing> {
+        let first = fragments.first()?;
+        let last = fragments.last()?;
+        let (start_beat, end_beat) = (first.start_beat.value(), last.end_beat.value());
+        let (start, end) = (first.value.start(), last.value.end());
+        if end_beat <= start_beat {
+            return None;
+        }
+
+        Easing::iter()
+            .filter(|easing| !matches!(easing, Easing::Custom(..)))
+            .filter(|easing| {
+                *easing == Easing::Linear || (curves && fragments.len() >= MIN_CURVE_FRAGMENTS)
+            })
+            .find(|easing| {
+                let matches = |beat: Beat, value: f32| {
+                    let percent = (beat.value() - start_beat) / (end_beat - start_beat);
+                    (start.ease_to(end, percent, *easing) - value).abs() <= self.tolerance
+                };
+                // most candidates are rejected by the middle point alone
+                let middle = &fragments[fragments.len() / 2];
+                matches(middle.start_beat, middle.value.start())
+                    && fragments.iter().all(|event| {
+                        matches(event.start_beat, event.value.start())
+                            && matches(event.end_beat, event.value.end())
+                    })
+            })
+    }
+}
+
+/// Convert transitions without a visible change into constant events
+fn normalized(event: LineEvent) -> LineEvent {
+    match event.value {
+        LineEventValue::Transition { start, end, .. }
+            if start == end || event.start_beat == event.end_beat =>
+        {
+            LineEvent {
+                value: LineEventValue::constant(end),
+                ..event
+            }
+        }
+        _ => event,
+    }
+}
+
+/// Drop constant events fully covered by another constant event winning over them
+///
+/// Overlapping events affecting a line take the largest value, and the event ending later takes over after both ended,
+/// which makes an event covered by one with a larger or equal value not visible
+fn drop_shadowed(mut events: Vec<LineEvent>) -> Vec<LineEvent> {
+    let mut index = 0;
+    while index < events.len() {
+        let event = &events[index];
+        let shadowed = event.value.is_constant()
+            && events.iter().enumerate().any(|(other_index, other)| {
+                other_index != index
+                    && other.value.is_constant()
+                    && other.start_beat <= event.start_beat
+                    && other.end_beat >= event.end_beat
+                    && other.value.start() >= event.value.start()
+                    && (other.end_beat > event.end_beat
+                        || other.value.start() == event.value.start())
+            });
+
+        if shadowed {
+            events.remove(index);
+        } else {
+            index += 1;
+        }
+    }
+
+    events
+}
+
+/// Merge adjacent constant events with the same value, the events must be sorted and not overlapping
+fn merge_constants(events: Vec<LineEvent>) -> Vec<LineEvent> {
+    let mut result: Vec<LineEvent> = vec![];
+    for event in events {
+        match result.last_mut() {
+            Some(last)
+                if !last.value.is_transition()
+                    && last.value == event.value
+                    && last.end_beat == event.start_beat =>
+            {
+                last.end_beat = event.end_beat;
+            }
+            _ => result.push(event),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steps::MergeChildrenLine;
+    use phichain_chart::beat;
+    use phichain_chart::beat::Beat;
+
+    fn event(start: Beat, end: Beat, value: LineEventValue) -> LineEvent {
+        LineEvent {
+            kind: LineEventKind::X,
+            start_beat: start,
+            end_beat: end,
+            value,
+            id: None,
+        }
+    }
+
+    fn simplify(events: Vec<LineEvent>) -> Vec<LineEvent> {
+        SimplifyEvents::default().simplify_events(events)
+    }
+
+    #[test]
+    fn test_merge_constants() {
+        let events = simplify(vec![
+            event(beat!(0), beat!(1), LineEventValue::constant(10.0)),
+            event(
+                beat!(1),
+                beat!(2),
+                LineEventValue::transition(10.0, 10.0, Easing::EaseInSine),
+            ),
+            event(beat!(2), beat!(3), LineEventValue::constant(10.0)),
+            event(beat!(4), beat!(5), LineEventValue::constant(10.0)),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                event(beat!(0), beat!(3), LineEventValue::constant(10.0)),
+                event(beat!(4), beat!(5), LineEventValue::constant(10.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_shadowed() {
+        let events = simplify(vec![
+            event(beat!(0), beat!(4), LineEventValue::constant(10.0)),
+            event(beat!(1), beat!(2), LineEventValue::constant(5.0)),
+            event(beat!(0), beat!(4), LineEventValue::constant(10.0)),
+        ]);
+        assert_eq!(
+            events,
+            vec![event(beat!(0), beat!(4), LineEventValue::constant(10.0))]
+        );
+
+        // the smaller event ends later and takes over after the larger one
+        let events = vec![
+            event(beat!(0), beat!(4), LineEventValue::constant(10.0)),
+            event(beat!(1), beat!(4), LineEventValue::constant(5.0)),
+        ];
+        assert_eq!(simplify(events.clone()), events);
+    }
+
+    #[test]
+    fn test_refit() {
+        // an ease-in-quad transition followed by an ease-out-sine one, baked into 1/4 beat linear fragments
+        let bake = |offset: i32, start: f32, end: f32, easing: Easing| {
+            (0..8).map(move |i| {
+                let value = |i: i32| start.ease_to(end, i as f32 / 8.0, easing);
+                event(
+                    beat!(offset, i, 4),
+                    beat!(offset, i + 1, 4),
+                    LineEventValue::transition(value(i), value(i + 1), Easing::Linear),
+                )
+            })
+        };
+        let fragments = bake(0, 0.0, 100.0, Easing::EaseInQuad)
+            .chain(bake(2, 100.0, -50.0, Easing::EaseOutSine))
+            .collect();
+        assert_eq!(
+            simplify(fragments),
+            vec![
+                event(
+                    beat!(0),
+                    beat!(2),
+                    LineEventValue::transition(0.0, 100.0, Easing::EaseInQuad)
+                ),
+                event(
+                    beat!(2),
+                    beat!(4),
+                    LineEventValue::transition(100.0, -50.0, Easing::EaseOutSine)
+                ),
+            ]
+        );
+
+        // a polyline with a kink is kept
+        let events = vec![
+            event(
+                beat!(0),
+                beat!(1),
+                LineEventValue::transition(0.0, 10.0, Easing::Linear),
+            ),
+            event(
+                beat!(1),
+                beat!(2),
+                LineEventValue::transition(10.0, 0.0, Easing::Linear),
+            ),
+        ];
+        assert_eq!(simplify(events.clone()), events);
+
+        // collinear fragments are merged
+        let events = simplify(vec![
+            event(
+                beat!(0),
+                beat!(1),
+                LineEventValue::transition(0.0, 10.0, Easing::Linear),
+            ),
+            event(
+                beat!(1),
+                beat!(2),
+                LineEventValue::transition(10.0, 20.0, Easing::Linear),
+            ),
+        ]);
+        assert_eq!(
+            events,
+            vec![event(
+                beat!(0),
+                beat!(2),
+                LineEventValue::transition(0.0, 20.0, Easing::Linear)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_simplify_merged_children() {
+        let child = LineWrapper {
+            events: vec![event(beat!(0), beat!(4), LineEventValue::constant(0.0))],
+            ..Default::default()
+        };
+        let parent = LineWrapper {
+            events: vec![event(
+                beat!(0),
+                beat!(4),
+                LineEventValue::transition(0.0, 100.0, Easing::EaseOutCubic),
+            )],
+            children: vec![child],
+            ..Default::default()
+        };
+        let chart = PhichainChart {
+            lines: vec![parent],
+            ..Default::default()
+        };
+
+        let mut report = ConversionReport::default();
+        let chart = MergeChildrenLine::default()
+            .apply(chart, &mut report)
+            .unwrap();
+        let chart = SimplifyEvents::default().apply(chart, &mut report).unwrap();
+
+        let x = chart.lines[0]
+            .events
+            .iter()
+            .filter(|event| event.kind.is_x())
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(x.len(), 1);
+        assert_eq!(x[0].start_beat, beat!(0));
+        assert_eq!(x[0].end_beat, beat!(4));
+        assert_eq!(
+            x[0].value,
+            LineEventValue::transition(0.0, 100.0, Easing::EaseOutCubic)
+        );
+        assert!(chart.lines[0]
+            .events
+            .iter()
+            .filter(|event| event.kind.is_y() || event.kind.is_rotation())
+            .all(|event| event.value.is_constant()));
+    }
+}
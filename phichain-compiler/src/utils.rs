@@ -2,21 +2,23 @@ use phichain_chart::beat::Beat;
 use phichain_chart::event::LineEvent;
 
 pub trait EventSequence {
-    fn evaluate(&self, beat: Beat) -> f32;
-    fn evaluate_start_no_effect(&self, beat: Beat) -> f32;
+    /// Evaluate the sequence, `default` is used before any event takes effect
+    fn evaluate(&self, beat: Beat, default: f32) -> f32;
+    /// Same as [`EventSequence::evaluate`], but events starting at the beat have no effect
+    fn evaluate_start_no_effect(&self, beat: Beat, default: f32) -> f32;
 
     fn x(&self) -> Self;
     fn y(&self) -> Self;
     fn rotation(&self) -> Self;
-    #[allow(dead_code)]
     fn opacity(&self) -> Self;
-    #[allow(dead_code)]
     fn speed(&self) -> Self;
+    fn scale_x(&self) -> Self;
+    fn scale_y(&self) -> Self;
 }
 
 impl EventSequence for Vec<LineEvent> {
-    fn evaluate(&self, beat: Beat) -> f32 {
-        let mut ret = 0.0;
+    fn evaluate(&self, beat: Beat, default: f32) -> f32 {
+        let mut ret = default;
 
         for event in self {
             let result = event.evaluate(beat.value());
@@ -28,8 +30,8 @@ impl EventSequence for Vec<LineEvent> {
         ret
     }
 
-    fn evaluate_start_no_effect(&self, beat: Beat) -> f32 {
-        let mut ret = 0.0;
+    fn evaluate_start_no_effect(&self, beat: Beat, default: f32) -> f32 {
+        let mut ret = default;
 
         for event in self {
             let result = event.evaluate_start_no_effect(beat.value());
@@ -42,28 +44,42 @@ impl EventSequence for Vec<LineEvent> {
     }
 
     fn x(&self) -> Self {
-        self.iter().filter(|x| x.kind.is_x()).copied().collect()
+        self.iter().filter(|x| x.kind.is_x()).cloned().collect()
     }
 
     fn y(&self) -> Self {
-        self.iter().filter(|x| x.kind.is_y()).copied().collect()
+        self.iter().filter(|x| x.kind.is_y()).cloned().collect()
     }
 
     fn rotation(&self) -> Self {
         self.iter()
             .filter(|x| x.kind.is_rotation())
-            .copied()
+            .cloned()
             .collect()
     }
 
     fn opacity(&self) -> Self {
         self.iter()
             .filter(|x| x.kind.is_opacity())
-            .copied()
+            .cloned()
             .collect()
     }
 
     fn speed(&self) -> Self {
-        self.iter().filter(|x| x.kind.is_speed()).copied().collect()
+        self.iter().filter(|x| x.kind.is_speed()).cloned().collect()
+    }
+
+    fn scale_x(&self) -> Self {
+        self.iter()
+            .filter(|x| x.kind.is_scale_x())
+            .cloned()
+            .collect()
+    }
+
+    fn scale_y(&self) -> Self {
+        self.iter()
+            .filter(|x| x.kind.is_scale_y())
+            .cloned()
+            .collect()
     }
 }
@@ -0,0 +1,163 @@
+use crate::input::detect_format;
+use crate::{default_output_path, export, parse_for_output, ConvertOptions, Formats};
+use anyhow::{bail, Context};
+use phichain_chart::report::ConversionReport;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, clap::Args)]
+pub struct BatchArgs {
+    /// The input chart format, detected from the content of each chart if omitted
+    #[arg(short, long)]
+    input: Option<Formats>,
+
+    #[command(flatten)]
+    options: ConvertOptions,
+
+    /// The directory to write the output charts into, keeping the layout of the input directory.
+    /// Defaults to writing each output chart next to its input chart
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// The directory of the input charts, `.json` and `.pec` files in it are converted recursively
+    #[arg(required = true)]
+    path: PathBuf,
+}
+
+pub fn batch(args: BatchArgs) -> anyhow::Result<()> {
+    let mut charts = vec![];
+    collect_charts(&args.path, &args, &mut charts)
+        .with_context(|| format!("Failed to read {}", args.path.display()))?;
+
+    let mut failures = vec![];
+    for chart in &charts {
+        let output_path = match &args.output_dir {
+            Some(output_dir) => default_output_path(
+                &output_dir.join(chart.strip_prefix(&args.path)?),
+                &args.options.output,
+            ),
+            None => default_output_path(chart, &args.options.output),
+        };
+
+        match convert_chart(chart, &output_path, &args) {
+            Ok(report) => {
+                println!("{} -> {}", chart.display(), output_path.display());
+                for loss in &report.losses {
+                    eprintln!("Warning: {}: {}", chart.display(), loss);
+                }
+            }
+            Err(error) => failures.push((chart, error)),
+        }
+    }
+
+    println!(
+        "Converted {} of {} charts",
+        charts.len() - failures.len(),
+        charts.len()
+    );
+
+    if !failures.is_empty() {
+        eprintln!("Failed to convert {} charts:", failures.len());
+        for (chart, error) in &failures {
+            eprintln!("  {}: {:#}", chart.display(), error);
+        }
+        bail!(
+            "{} of {} charts failed to convert",
+            failures.len(),
+            charts.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Collect the input charts in a directory recursively, in a stable order
+///
+/// Outputs of earlier runs and the output directory are skipped, so a batch can be run again on the same directory
+fn collect_charts(dir: &Path, args: &BatchArgs, charts: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if args.output_dir.as_ref().is_some_and(|x| *x == path) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_charts(&path, args, charts)?;
+        } else if is_input_chart(&path, &args.options.output) {
+            charts.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the file is a chart to convert, rather than another file or an output chart
+fn is_input_chart(path: &Path, output: &Formats) -> bool {
+    let is_chart = path
+        .extension()
+        .is_some_and(|extension| extension == "json" || extension == "pec");
+    let is_output = path.to_str().is_some_and(|x| {
+        x.ends_with(&format!(".{}.json", output))
+            || (matches!(output, Formats::Pec) && x.ends_with(".pec.pec"))
+    });
+
+    is_chart && !is_output
+}
+
+fn convert_chart(
+    path: &Path,
+    output_path: &Path,
+    args: &BatchArgs,
+) -> anyhow::Result<ConversionReport> {
+    let content = std::fs::read_to_string(path).context("Failed to read chart")?;
+    let input = match &args.input {
+        Some(input) => input.clone(),
+        None => detect_format(&content)?,
+    };
+
+    let mut report = ConversionReport::default();
+    let primitive = parse_for_output(&input, &content, &args.options.output, &mut report)?;
+    let output = export(primitive, &args.options, &mut report)?;
+    if args.options.strict {
+        report.ensure_lossless()?;
+    }
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(output_path, output)
+        .with_context(|| format!("Failed to write {}", output_path.display()))?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_input_chart() {
+        assert!(is_input_chart(Path::new("charts/a.json"), &Formats::Rpe));
+        assert!(is_input_chart(Path::new("charts/a.pec"), &Formats::Rpe));
+        assert!(is_input_chart(
+            Path::new("charts/a.official.json"),
+            &Formats::Rpe
+        ));
+        assert!(!is_input_chart(
+            Path::new("charts/a.rpe.json"),
+            &Formats::Rpe
+        ));
+        assert!(is_input_chart(Path::new("charts/a.pec"), &Formats::Pec));
+        assert!(!is_input_chart(
+            Path::new("charts/a.pec.pec"),
+            &Formats::Pec
+        ));
+        assert!(!is_input_chart(
+            Path::new("charts/music.ogg"),
+            &Formats::Rpe
+        ));
+    }
+}
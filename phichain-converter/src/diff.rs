@@ -0,0 +1,60 @@
+use crate::read_phichain as read;
+use anyhow::bail;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// The path of the old phichain chart
+    #[arg(required = true)]
+    from: PathBuf,
+    /// The path of the new phichain chart
+    #[arg(required = true)]
+    to: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct MergeArgs {
+    /// The path of the common ancestor phichain chart
+    #[arg(required = true)]
+    base: PathBuf,
+    /// The path of our phichain chart, which is preferred on conflicts
+    #[arg(required = true)]
+    ours: PathBuf,
+    /// The path of their phichain chart
+    #[arg(required = true)]
+    theirs: PathBuf,
+
+    /// The path to write the merged chart to
+    #[arg(long, required = true)]
+    output_path: PathBuf,
+}
+
+pub fn diff(args: DiffArgs) -> anyhow::Result<()> {
+    let diff = phichain_chart::diff::diff(&read(&args.from)?, &read(&args.to)?);
+    print!("{}", diff);
+
+    Ok(())
+}
+
+/// Merge two charts, exits with a non-zero code if there are conflicts
+///
+/// This can be used as a git merge driver: `phichain-converter merge %O %A %B --output-path %A`
+pub fn merge(args: MergeArgs) -> anyhow::Result<()> {
+    let result =
+        phichain_chart::diff::merge(&read(&args.base)?, &read(&args.ours)?, &read(&args.theirs)?);
+
+    std::fs::write(&args.output_path, serde_json::to_string(&result.chart)?)?;
+
+    for conflict in &result.conflicts {
+        println!("conflict: {}", conflict);
+    }
+
+    if !result.conflicts.is_empty() {
+        bail!(
+            "{} conflicts found, our side is taken for conflicting changes",
+            result.conflicts.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::Formats;
+use anyhow::{bail, Context};
+use std::io::Read;
+use std::path::Path;
+
+/// Whether the path stands for stdin or stdout
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Read the content of a chart, `-` reads from stdin
+pub fn read_input(path: &Path) -> anyhow::Result<String> {
+    if is_stdio(path) {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .context("Failed to read from stdin")?;
+        Ok(content)
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+}
+
+/// Detect the format of a chart from its content
+///
+/// - `formatVersion`: official
+/// - `META.RPEVersion`: RPE
+/// - `format`: phichain, or primitive if it is of format 1 without `time_signatures`,
+///   phichain charts of format 1 are too old to tell apart and need an explicit `--input`
+/// - not JSON: PEC
+pub fn detect_format(content: &str) -> anyhow::Result<Formats> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Ok(Formats::Pec);
+    };
+
+    if value.get("formatVersion").is_some() {
+        return Ok(Formats::Official);
+    }
+    if value
+        .get("META")
+        .and_then(|meta| meta.get("RPEVersion"))
+        .is_some()
+    {
+        return Ok(Formats::Rpe);
+    }
+    if let Some(format) = value.get("format") {
+        // primitive charts are always of format 1, phichain charts have time signatures since format 9
+        let primitive = format.as_u64() == Some(1) && value.get("time_signatures").is_none();
+        return Ok(if primitive {
+            Formats::Primitive
+        } else {
+            Formats::Phichain
+        });
+    }
+
+    bail!("Unable to detect the chart format, specify it with `--input`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phichain_chart::format::official::OfficialChart;
+    use phichain_chart::format::pec::PecChart;
+    use phichain_chart::format::rpe::RpeChart;
+    use phichain_chart::primitive::{Format, PrimitiveChart};
+    use phichain_chart::serialization::PhichainChart;
+
+    #[test]
+    fn test_detect_format() {
+        let primitive = || PrimitiveChart::default();
+        let detect = |content: String| detect_format(&content).unwrap();
+
+        assert!(matches!(
+            detect(
+                serde_json::to_string(&OfficialChart::from_primitive(primitive()).unwrap())
+                    .unwrap()
+            ),
+            Formats::Official
+        ));
+        assert!(matches!(
+            detect(serde_json::to_string(&RpeChart::from_primitive(primitive()).unwrap()).unwrap()),
+            Formats::Rpe
+        ));
+        assert!(matches!(
+            detect(serde_json::to_string(&PhichainChart::default()).unwrap()),
+            Formats::Phichain
+        ));
+        assert!(matches!(
+            detect(serde_json::to_string(&primitive()).unwrap()),
+            Formats::Primitive
+        ));
+        assert!(matches!(
+            detect(PecChart::from_primitive(primitive()).unwrap().to_string()),
+            Formats::Pec
+        ));
+        assert!(detect_format("{}").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::input::read_input;
+use crate::{load, migrate_phichain, Formats};
+use anyhow::bail;
+use phichain_chart::lint::{LintOptions, Severity};
+use phichain_chart::primitive::Format;
+use phichain_chart::serialization::PhichainChart;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct LintArgs {
+    /// The chart format
+    #[arg(short, long, default_value_t = Formats::Phichain)]
+    format: Formats,
+
+    /// Duration of the music in seconds, notes placed after it are reported
+    #[arg(long)]
+    music_duration: Option<f32>,
+
+    /// Exit with a non-zero code on warnings as well
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// The path of the chart
+    #[arg(required = true)]
+    path: PathBuf,
+}
+
+pub fn lint(args: LintArgs) -> anyhow::Result<()> {
+    // phichain charts are linted as is, expression events are lost when converting them into primitive charts
+    let chart = match args.format {
+        Formats::Phichain => migrate_phichain(serde_json::from_str(&read_input(&args.path)?)?)?,
+        _ => PhichainChart::from_primitive(load(&args.format, &args.path)?)?,
+    };
+    let diagnostics = phichain_chart::lint::lint(
+        &chart,
+        &LintOptions {
+            music_duration: args.music_duration,
+        },
+    );
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|x| x.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    println!("{} errors, {} warnings", errors, warnings);
+
+    if errors > 0 || (args.deny_warnings && warnings > 0) {
+        bail!("Chart check failed");
+    }
+
+    Ok(())
+}
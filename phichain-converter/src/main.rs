@@ -1,10 +1,26 @@
-use clap::{Parser, ValueEnum};
-use phichain_chart::format::official::OfficialChart;
+mod batch;
+mod diff;
+mod input;
+mod lint;
+mod operation;
+mod project;
+mod stats;
+
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use input::{detect_format, is_stdio, read_input};
+use phichain_chart::format::official::{OfficialChart, OfficialExportOptions, DEFAULT_TOLERANCE};
+use phichain_chart::format::pec::PecChart;
 use phichain_chart::format::rpe::RpeChart;
+use phichain_chart::migration::migrate;
 use phichain_chart::primitive::{Format, PrimitiveChart};
+use phichain_chart::report::ConversionReport;
 use phichain_chart::serialization::PhichainChart;
+use phichain_compiler::steps::{BakeExpressions, MergeChildrenLine};
+use phichain_compiler::Pipeline;
+use serde::Serialize;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use strum::Display;
 
 #[derive(ValueEnum, Debug, Display, Clone)]
@@ -14,82 +30,388 @@ enum Formats {
     Official,
     Phichain,
     Rpe,
+    Pec,
     Primitive,
 }
 
 #[derive(Debug, Parser)]
 #[command(name = "phichain-converter")]
 #[command(about = "Converts Phigros charts between different formats")]
-struct Args {
-    /// The input chart format
-    #[arg(short, long, required = true)]
-    input: Formats,
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: Option<ConvertArgs>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check a chart for problems, exits with a non-zero code if any error is found
+    Lint(lint::LintArgs),
+    /// Print statistics of a chart, e.g. note counts, notes per second and a rough difficulty estimation
+    Stats(stats::StatsArgs),
+    /// Compare two phichain charts structurally
+    Diff(diff::DiffArgs),
+    /// Merge two phichain charts derived from the same base chart, exits with a non-zero code on conflicts
+    Merge(diff::MergeArgs),
+    /// Extract a range of beats from a phichain chart, e.g. as a preview
+    Slice(operation::SliceArgs),
+    /// Append a phichain chart to another, e.g. for medleys
+    Concat(operation::ConcatArgs),
+    /// Scale the tempo of a phichain chart, e.g. for a sped-up audio
+    ScaleTempo(operation::ScaleTempoArgs),
+    /// Convert all charts in a directory recursively, reporting the charts failed to convert
+    Batch(batch::BatchArgs),
+    /// Export a phichain project into a package with the chart, music, illustration and info
+    Project(project::ProjectArgs),
+}
+
+// `ConvertOptions` is not flattened here, clap can not detect an optional group with nested groups
+#[derive(Debug, clap::Args)]
+struct ConvertArgs {
+    /// The input chart format, detected from the content of the chart if omitted
+    #[arg(short, long)]
+    input: Option<Formats>,
     /// The output chart format
     #[arg(short, long, required = true)]
     output: Formats,
 
-    /// The path of the input chart
+    /// The path of the output chart, `-` for stdout.
+    /// Defaults to `<name>.<format>.json` next to the input chart, or stdout if the input is read from stdin
+    #[arg(long)]
+    output_path: Option<PathBuf>,
+
+    /// Write indented JSON instead of compact JSON
+    #[arg(long)]
+    pretty: bool,
+
+    /// Maximum error allowed when cutting non-linear events into linear events for official charts,
+    /// measured in pixels for position, degrees for rotation and 0-255 for opacity
+    #[arg(long, default_value_t = DEFAULT_TOLERANCE)]
+    tolerance: f32,
+
+    /// Fail instead of writing the output if the conversion is lossy
+    #[arg(long)]
+    strict: bool,
+
+    /// The path of the input chart, `-` for stdin
     #[arg(required = true)]
     path: PathBuf,
 }
 
-fn convert(args: Args) -> anyhow::Result<()> {
-    let file = std::fs::File::open(&args.path)?;
+impl ConvertArgs {
+    fn options(&self) -> ConvertOptions {
+        ConvertOptions {
+            output: self.output.clone(),
+            pretty: self.pretty,
+            tolerance: self.tolerance,
+            strict: self.strict,
+        }
+    }
+}
+
+/// Options of converting a chart into the output format
+#[derive(Debug, clap::Args)]
+struct ConvertOptions {
+    /// The output chart format
+    #[arg(short, long, required = true)]
+    output: Formats,
+
+    /// Write indented JSON instead of compact JSON
+    #[arg(long)]
+    pretty: bool,
+
+    /// Maximum error allowed when cutting non-linear events into linear events for official charts,
+    /// measured in pixels for position, degrees for rotation and 0-255 for opacity
+    #[arg(long, default_value_t = DEFAULT_TOLERANCE)]
+    tolerance: f32,
+
+    /// Fail instead of writing the output if the conversion is lossy
+    #[arg(long)]
+    strict: bool,
+}
+
+/// Read a chart of the given format and convert it into a primitive chart, lossy transformations are logged
+fn load(format: &Formats, path: &Path) -> anyhow::Result<PrimitiveChart> {
+    let mut report = ConversionReport::default();
+    let chart = load_with_report(format, path, &mut report)?;
+    report.log();
+    Ok(chart)
+}
 
-    println!("Converting chart into primitive chart...");
+/// Read a chart of the given format and convert it into a primitive chart, pushing lossy transformations into the report
+fn load_with_report(
+    format: &Formats,
+    path: &Path,
+    report: &mut ConversionReport,
+) -> anyhow::Result<PrimitiveChart> {
+    parse(format, &read_input(path)?, report)
+}
 
-    let primitive = match args.input {
+/// Parse a chart of the given format and convert it into a primitive chart, pushing lossy transformations into the report
+fn parse(
+    format: &Formats,
+    content: &str,
+    report: &mut ConversionReport,
+) -> anyhow::Result<PrimitiveChart> {
+    match format {
         Formats::Official => {
-            let chart: OfficialChart = serde_json::from_reader(file)?;
-            chart.into_primitive()?
+            let chart: OfficialChart = serde_json::from_str(content)?;
+            chart.into_primitive_with_report(report)
         }
         Formats::Phichain => {
-            let chart: PhichainChart = serde_json::from_reader(file)?;
-            chart.into_primitive()?
+            let chart = migrate_phichain(serde_json::from_str(content)?)?;
+            chart.into_primitive_with_report(report)
         }
         Formats::Rpe => {
-            let chart: RpeChart = serde_json::from_reader(file)?;
-            chart.into_primitive()?
+            let chart: RpeChart = serde_json::from_str(content)?;
+            chart.into_primitive_with_report(report)
+        }
+        Formats::Pec => {
+            let chart: PecChart = content.parse()?;
+            chart.into_primitive_with_report(report)
         }
         Formats::Primitive => {
-            let chart: PrimitiveChart = serde_json::from_reader(file)?;
-            chart.into_primitive()?
+            let chart: PrimitiveChart = serde_json::from_str(content)?;
+            chart.into_primitive_with_report(report)
+        }
+    }
+}
+
+/// Same as [`parse`], compiling phichain charts for the output format first
+///
+/// Expressions of phichain charts are baked, and child lines are merged for formats without a line hierarchy
+fn parse_for_output(
+    format: &Formats,
+    content: &str,
+    output: &Formats,
+    report: &mut ConversionReport,
+) -> anyhow::Result<PrimitiveChart> {
+    match format {
+        Formats::Phichain => {
+            let chart = migrate_phichain(serde_json::from_str(content)?)?;
+            let mut pipeline = Pipeline::empty().with(BakeExpressions::default());
+            if matches!(output, Formats::Official | Formats::Pec) {
+                pipeline.push(MergeChildrenLine::default());
+            }
+            let (chart, _) = pipeline.apply(chart, report)?;
+            chart.into_primitive_with_report(report)
+        }
+        _ => parse(format, content, report),
+    }
+}
+
+fn to_json(chart: &impl Serialize, pretty: bool) -> anyhow::Result<String> {
+    Ok(if pretty {
+        serde_json::to_string_pretty(chart)?
+    } else {
+        serde_json::to_string(chart)?
+    })
+}
+
+/// Read a phichain chart, migrating it to the current format
+fn read_phichain(path: &Path) -> anyhow::Result<PhichainChart> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    migrate_phichain(serde_json::from_reader(file)?)
+}
+
+/// Migrate a phichain chart of any format to the current format
+fn migrate_phichain(chart: serde_json::Value) -> anyhow::Result<PhichainChart> {
+    let migrated = migrate(&chart).context("Migration failed")?;
+    serde_json::from_value(migrated).context("Failed to deserialize chart")
+}
+
+fn convert(args: ConvertArgs) -> anyhow::Result<()> {
+    // progress goes to stderr, stdout may be the output chart
+    let content = read_input(&args.path)?;
+    let input = match args.input.clone() {
+        Some(input) => input,
+        None => {
+            let input = detect_format(&content)?;
+            eprintln!("Detected `{}` chart", input);
+            input
         }
     };
 
-    println!("Converting chart into `{}` chart...", args.output);
+    eprintln!("Converting chart into primitive chart...");
+
+    let mut report = ConversionReport::default();
+    let primitive = parse_for_output(&input, &content, &args.output, &mut report)?;
+
+    eprintln!("Converting chart into `{}` chart...", args.output);
 
-    let output = match args.output {
+    let output = export(primitive, &args.options(), &mut report)?;
+
+    for loss in &report.losses {
+        eprintln!("Warning: {}", loss);
+    }
+    if args.strict {
+        report.ensure_lossless()?;
+    }
+
+    let output_path = match args.output_path {
+        Some(path) => path,
+        None if is_stdio(&args.path) => PathBuf::from("-"),
+        None => default_output_path(&args.path, &args.output),
+    };
+    if !is_stdio(&output_path) && output_path == args.path {
+        bail!(
+            "The output path is the same as the input path, refusing to overwrite {}",
+            output_path.display()
+        );
+    }
+
+    if is_stdio(&output_path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(&output_path, output)
+            .with_context(|| format!("Failed to write {}", output_path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Convert a primitive chart into the output format, pushing lossy transformations into the report
+fn export(
+    primitive: PrimitiveChart,
+    options: &ConvertOptions,
+    report: &mut ConversionReport,
+) -> anyhow::Result<String> {
+    Ok(match options.output {
         Formats::Official => {
-            let chart = OfficialChart::from_primitive(primitive)?;
-            serde_json::to_string(&chart)?
+            let chart = OfficialChart::from_primitive_with_options(
+                primitive,
+                OfficialExportOptions {
+                    tolerance: options.tolerance,
+                },
+                report,
+            )?;
+            to_json(&chart, options.pretty)?
         }
         Formats::Phichain => {
-            let chart = PhichainChart::from_primitive(primitive)?;
-            serde_json::to_string(&chart)?
+            let chart = PhichainChart::from_primitive_with_report(primitive, report)?;
+            to_json(&chart, options.pretty)?
         }
         Formats::Rpe => {
-            let chart = RpeChart::from_primitive(primitive)?;
-            serde_json::to_string(&chart)?
+            let chart = RpeChart::from_primitive_with_report(primitive, report)?;
+            to_json(&chart, options.pretty)?
+        }
+        Formats::Pec => {
+            let chart = PecChart::from_primitive_with_report(primitive, report)?;
+            chart.to_string()
         }
         Formats::Primitive => {
-            let chart = PrimitiveChart::from_primitive(primitive)?;
-            serde_json::to_string(&chart)?
+            let chart = PrimitiveChart::from_primitive_with_report(primitive, report)?;
+            to_json(&chart, options.pretty)?
         }
-    };
-
-    let output_path = args.path.with_extension(format!("{}.json", args.output));
-
-    let mut output_file = std::fs::File::create(output_path)?;
-    output_file.write_all(output.as_bytes())?;
+    })
+}
 
-    Ok(())
+/// `<name>.<format>.json` next to the input chart, or `<name>.pec` for PEC charts
+///
+/// PEC charts converted into PEC are written into `<name>.pec.pec`, so the input chart is not overwritten
+fn default_output_path(path: &Path, format: &Formats) -> PathBuf {
+    match format {
+        Formats::Pec if path.extension().is_some_and(|x| x == "pec") => {
+            path.with_extension("pec.pec")
+        }
+        Formats::Pec => path.with_extension("pec"),
+        _ => path.with_extension(format!("{}.json", format)),
+    }
 }
 
 fn main() {
-    let args = Args::parse();
-    if let Err(err) = convert(args) {
+    let cli = Cli::parse();
+    let result = match (cli.command, cli.convert) {
+        (Some(Command::Lint(args)), _) => lint::lint(args),
+        (Some(Command::Stats(args)), _) => stats::stats(args),
+        (Some(Command::Diff(args)), _) => diff::diff(args),
+        (Some(Command::Merge(args)), _) => diff::merge(args),
+        (Some(Command::Slice(args)), _) => operation::slice(args),
+        (Some(Command::Concat(args)), _) => operation::concat(args),
+        (Some(Command::ScaleTempo(args)), _) => operation::scale_tempo(args),
+        (Some(Command::Batch(args)), _) => batch::batch(args),
+        (Some(Command::Project(args)), _) => project::project(args),
+        (None, Some(args)) => convert(args),
+        (None, None) => unreachable!("clap requires either a subcommand or convert arguments"),
+    };
+    if let Err(err) = result {
         eprintln!("Error: {}", err);
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phichain_chart::beat::Beat;
+    use phichain_chart::event::{LineEvent, LineEventKind, LineEventValue};
+    use phichain_chart::expression::Expression;
+    use phichain_chart::report::LossKind;
+
+    #[test]
+    fn test_parse_old_phichain() {
+        let content = r#"{
+          "format": 8,
+          "offset": 0.0,
+          "bpm_list": [{ "beat": [0, 0, 1], "bpm": 120.0 }],
+          "lines": []
+        }"#;
+
+        let format = detect_format(content).unwrap();
+        assert!(matches!(format, Formats::Phichain));
+        let chart = parse(&format, content, &mut ConversionReport::default()).unwrap();
+        assert_eq!(chart.bpm_list.0.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_phichain_for_output() {
+        let mut chart = PhichainChart::default();
+        chart.lines[0].events.push(LineEvent {
+            kind: LineEventKind::X,
+            start_beat: Beat::ZERO,
+            end_beat: Beat::ONE,
+            value: LineEventValue::expression(Expression::parse("progress * 100").unwrap()),
+            id: None,
+        });
+        chart.lines[0].children.push(Default::default());
+        let content = serde_json::to_string(&chart).unwrap();
+        let content = content.as_str();
+
+        let mut report = ConversionReport::default();
+        let chart =
+            parse_for_output(&Formats::Phichain, content, &Formats::Official, &mut report).unwrap();
+        assert!(!report
+            .losses
+            .iter()
+            .any(|loss| matches!(loss.kind, LossKind::UnbakedExpression { .. })));
+        assert_eq!(chart.lines.len(), 2);
+        assert!(chart.lines.iter().all(|line| line.children.is_empty()));
+
+        // the line hierarchy is kept for RPE
+        let chart =
+            parse_for_output(&Formats::Phichain, content, &Formats::Rpe, &mut report).unwrap();
+        assert_eq!(chart.lines[0].children.len(), 1);
+    }
+
+    #[test]
+    fn test_default_output_path() {
+        assert_eq!(
+            default_output_path(Path::new("a.json"), &Formats::Rpe),
+            Path::new("a.rpe.json")
+        );
+        assert_eq!(
+            default_output_path(Path::new("a.json"), &Formats::Pec),
+            Path::new("a.pec")
+        );
+        assert_eq!(
+            default_output_path(Path::new("a.pec"), &Formats::Pec),
+            Path::new("a.pec.pec")
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::read_phichain;
+use phichain_chart::beat::Beat;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct SliceArgs {
+    /// The first beat of the slice, e.g. `16`, `16+1/2` or `16.5`
+    #[arg(long, required = true)]
+    start: Beat,
+    /// The beat where the slice ends, exclusive
+    #[arg(long, required = true)]
+    end: Beat,
+
+    /// The path of the phichain chart
+    #[arg(required = true)]
+    path: PathBuf,
+
+    /// The path to write the sliced chart to
+    #[arg(long, required = true)]
+    output_path: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ConcatArgs {
+    /// The path of the phichain chart played first
+    #[arg(required = true)]
+    first: PathBuf,
+    /// The path of the phichain chart appended to the first one
+    #[arg(required = true)]
+    second: PathBuf,
+
+    /// The beat where the second chart starts, defaults to the first bar after the end of the first chart
+    #[arg(long)]
+    at: Option<Beat>,
+
+    /// The path to write the concatenated chart to
+    #[arg(long, required = true)]
+    output_path: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ScaleTempoArgs {
+    /// The factor to scale the tempo by, e.g. `1.25` for an audio sped up by 25%
+    #[arg(long, required = true)]
+    factor: f32,
+
+    /// The path of the phichain chart
+    #[arg(required = true)]
+    path: PathBuf,
+
+    /// The path to write the scaled chart to
+    #[arg(long, required = true)]
+    output_path: PathBuf,
+}
+
+pub fn slice(args: SliceArgs) -> anyhow::Result<()> {
+    let chart = read_phichain(&args.path)?;
+    let sliced = chart.slice(args.start, args.end)?;
+
+    std::fs::write(&args.output_path, serde_json::to_string(&sliced)?)?;
+
+    let time = |beat: Beat| chart.bpm_list.time_at(beat) + chart.offset.0 / 1000.0;
+    println!(
+        "The sliced chart is aligned to the audio from {:.3}s to {:.3}s",
+        time(args.start),
+        time(args.end)
+    );
+
+    Ok(())
+}
+
+pub fn concat(args: ConcatArgs) -> anyhow::Result<()> {
+    let first = read_phichain(&args.first)?;
+    let second = read_phichain(&args.second)?;
+    let chart = first.concat(&second, args.at);
+
+    std::fs::write(&args.output_path, serde_json::to_string(&chart)?)?;
+
+    let join = args.at.unwrap_or_else(|| first.append_beat());
+    println!(
+        "The second chart starts at beat {:?}, its audio should start at {:.3}s",
+        join,
+        chart.bpm_list.time_at(join) + (chart.offset.0 - second.offset.0) / 1000.0
+    );
+
+    Ok(())
+}
+
+pub fn scale_tempo(args: ScaleTempoArgs) -> anyhow::Result<()> {
+    let mut chart = read_phichain(&args.path)?;
+    chart.scale_tempo(args.factor)?;
+
+    std::fs::write(&args.output_path, serde_json::to_string(&chart)?)?;
+
+    Ok(())
+}
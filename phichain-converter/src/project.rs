@@ -0,0 +1,94 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use phichain_chart::format::official::{OfficialExportOptions, DEFAULT_TOLERANCE};
+use phichain_chart::project::Project;
+use phichain_export::{export_project, ExportOptions, PackageFormat};
+use std::path::PathBuf;
+use strum::Display;
+
+#[derive(ValueEnum, Debug, Display, Clone, Copy)]
+#[clap(rename_all = "kebab_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PackageFormats {
+    Official,
+    Rpe,
+}
+
+impl From<PackageFormats> for PackageFormat {
+    fn from(value: PackageFormats) -> Self {
+        match value {
+            PackageFormats::Official => PackageFormat::Official,
+            PackageFormats::Rpe => PackageFormat::Rpe,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ProjectArgs {
+    /// The chart format of the package
+    #[arg(short, long, required = true)]
+    output: PackageFormats,
+
+    /// The path of the package, defaults to `<project>.<format>.zip` next to the project directory
+    #[arg(long)]
+    output_path: Option<PathBuf>,
+
+    /// Maximum error allowed when cutting non-linear events into linear events for official charts,
+    /// measured in pixels for position, degrees for rotation and 0-255 for opacity
+    #[arg(long, default_value_t = DEFAULT_TOLERANCE)]
+    tolerance: f32,
+
+    /// Also write a source map linking the compiled chart back to the project chart, next to the package.
+    /// The map is indexed by locations in the compiled primitive chart, before the conversion into the package format
+    #[arg(long)]
+    source_map: bool,
+
+    /// Fail instead of writing the package if the export is lossy
+    #[arg(long)]
+    strict: bool,
+
+    /// The path of the project directory
+    #[arg(required = true)]
+    path: PathBuf,
+}
+
+pub fn project(args: ProjectArgs) -> anyhow::Result<()> {
+    let project = Project::load(args.path.clone()).context("Failed to load project")?;
+    let output_path = match args.output_path {
+        Some(path) => path,
+        // canonicalized, so `.` is named after the project directory as well
+        None => std::fs::canonicalize(&args.path)?.with_extension(format!("{}.zip", args.output)),
+    };
+
+    println!(
+        "Exporting project `{}` into `{}` package...",
+        project.meta.name, args.output
+    );
+
+    let output = export_project(
+        &project,
+        &output_path,
+        &ExportOptions {
+            format: args.output.into(),
+            official: OfficialExportOptions {
+                tolerance: args.tolerance,
+            },
+            source_map: args.source_map,
+            strict: args.strict,
+        },
+    )?;
+
+    for loss in &output.report.losses {
+        eprintln!("Warning: {}", loss);
+    }
+
+    if let Some(source_map) = &output.source_map {
+        let source_map_path = output_path.with_extension("map.json");
+        std::fs::write(&source_map_path, serde_json::to_string(source_map)?)
+            .with_context(|| format!("Failed to write {}", source_map_path.display()))?;
+    }
+
+    println!("Exported {}", output_path.display());
+
+    Ok(())
+}
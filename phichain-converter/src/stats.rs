@@ -0,0 +1,63 @@
+use crate::{load, Formats};
+use phichain_chart::statistics::{ChartStatistics, StatisticsOptions};
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct StatsArgs {
+    /// The chart format
+    #[arg(short, long, default_value_t = Formats::Phichain)]
+    format: Formats,
+
+    /// Length of the sliding window used to compute notes per second, in seconds
+    #[arg(long, default_value_t = StatisticsOptions::default().window)]
+    window: f32,
+
+    /// Print the statistics as json, including the notes per second of every window
+    #[arg(long)]
+    json: bool,
+
+    /// The path of the chart
+    #[arg(required = true)]
+    path: PathBuf,
+}
+
+pub fn stats(args: StatsArgs) -> anyhow::Result<()> {
+    let chart = load(&args.format, &args.path)?;
+    let statistics = ChartStatistics::of_primitive(
+        &chart,
+        StatisticsOptions {
+            window: args.window,
+            ..Default::default()
+        },
+    );
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&statistics)?);
+        return Ok(());
+    }
+
+    let notes = &statistics.notes;
+    println!("Notes: {}", notes.total());
+    println!(
+        "  Tap: {}, Drag: {}, Hold: {}, Flick: {}, Fake: {}",
+        notes.tap, notes.drag, notes.hold, notes.flick, notes.fake
+    );
+    println!("Lines: {}", statistics.lines);
+    println!("Events: {}", statistics.events);
+    println!(
+        "Hold Duration: {:.3}s total, {:.3}s longest",
+        statistics.total_hold_duration, statistics.longest_hold_duration
+    );
+    println!("Duration: {:.3}s", statistics.duration);
+    println!(
+        "NPS: {:.2} peak, {:.2} average",
+        statistics.peak_nps, statistics.average_nps
+    );
+    println!(
+        "Simultaneous Notes: {}, up to {} at once",
+        statistics.simultaneous_notes, statistics.max_simultaneous_notes
+    );
+    println!("Difficulty: {:.2}", statistics.difficulty);
+
+    Ok(())
+}
@@ -52,7 +52,7 @@ fn copy_system(
         if let Ok(note) = note_query.get(entity) {
             clipboard.notes.push(*note);
         } else if let Ok(event) = event_query.get(entity) {
-            clipboard.events.push(*event);
+            clipboard.events.push(event.clone());
         }
     }
 }
@@ -117,9 +117,11 @@ fn paste_system(
             )));
         }
         for event in events {
-            let mut new_event = event;
-            new_event.start_beat = event.start_beat + delta;
-            new_event.end_beat = event.end_beat + delta;
+            let new_event = LineEvent {
+                start_beat: event.start_beat + delta,
+                end_beat: event.end_beat + delta,
+                ..event
+            };
             sequence.0.push(EditorCommand::CreateEvent(CreateEvent::new(
                 target_line,
                 new_event,
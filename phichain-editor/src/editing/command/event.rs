@@ -3,7 +3,7 @@ use bevy::prelude::*;
 use phichain_chart::event::{LineEvent, LineEventBundle};
 use undo::Edit;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct CreateEvent {
     pub line_entity: Entity,
     pub event: LineEvent,
@@ -26,7 +26,7 @@ impl Edit for CreateEvent {
 
     fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
         target.entity_mut(self.line_entity).with_children(|parent| {
-            self.event_entity = Some(parent.spawn(LineEventBundle::new(self.event)).id());
+            self.event_entity = Some(parent.spawn(LineEventBundle::new(self.event.clone())).id());
         });
     }
 
@@ -37,7 +37,7 @@ impl Edit for CreateEvent {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct RemoveEvent {
     pub entity: Entity,
     pub event: Option<LineEvent>,
@@ -57,12 +57,12 @@ impl Edit for RemoveEvent {
     type Output = ();
 
     fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
-        self.event = target.entity(self.entity).get::<LineEvent>().copied();
+        self.event = target.entity(self.entity).get::<LineEvent>().cloned();
         target.entity_mut(self.entity).retain::<Parent>();
     }
 
     fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
-        if let Some(event) = self.event {
+        if let Some(event) = self.event.clone() {
             target
                 .entity_mut(self.entity)
                 .insert(LineEventBundle::new(event));
@@ -70,7 +70,7 @@ impl Edit for RemoveEvent {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct EditEvent {
     entity: Entity,
     from: LineEvent,
@@ -89,13 +89,13 @@ impl Edit for EditEvent {
 
     fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
         if let Some(mut event) = target.entity_mut(self.entity).get_mut::<LineEvent>() {
-            *event = self.to;
+            *event = self.to.clone();
         }
     }
 
     fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
         if let Some(mut event) = target.entity_mut(self.entity).get_mut::<LineEvent>() {
-            *event = self.from;
+            *event = self.from.clone();
         }
     }
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 use phichain_chart::event::LineEventBundle;
-use phichain_chart::line::LineBundle;
+use phichain_chart::line::{Line, LineBundle};
 use phichain_chart::note::NoteBundle;
 use phichain_chart::serialization::LineWrapper;
 use undo::Edit;
@@ -81,7 +81,7 @@ impl Edit for RemoveLine {
                         parent.spawn(NoteBundle::new(*note));
                     }
                     for event in &line.events {
-                        parent.spawn(LineEventBundle::new(*event));
+                        parent.spawn(LineEventBundle::new(event.clone()));
                     }
                 });
         }
@@ -130,3 +130,33 @@ impl Edit for MoveLineAsChild {
         }
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct EditLine {
+    entity: Entity,
+    from: Line,
+    to: Line,
+}
+
+impl EditLine {
+    pub fn new(entity: Entity, from: Line, to: Line) -> Self {
+        Self { entity, from, to }
+    }
+}
+
+impl Edit for EditLine {
+    type Target = World;
+    type Output = ();
+
+    fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
+        if let Some(mut line) = target.entity_mut(self.entity).get_mut::<Line>() {
+            *line = self.to.clone();
+        }
+    }
+
+    fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
+        if let Some(mut line) = target.entity_mut(self.entity).get_mut::<Line>() {
+            *line = self.from.clone();
+        }
+    }
+}
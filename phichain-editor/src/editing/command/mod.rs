@@ -3,12 +3,18 @@ pub mod event;
 pub mod line;
 pub mod meta;
 pub mod note;
+pub mod retime;
+pub mod time_signature;
 
 use crate::editing::command::bpm_list::{CreateBpmPoint, EditBpmPoint, RemoveBpmPoint};
 use crate::editing::command::event::{CreateEvent, EditEvent, RemoveEvent};
-use crate::editing::command::line::{CreateLine, MoveLineAsChild, RemoveLine};
+use crate::editing::command::line::{CreateLine, EditLine, MoveLineAsChild, RemoveLine};
 use crate::editing::command::meta::{EditMeta, EditOffset};
 use crate::editing::command::note::{CreateNote, EditNote, RemoveNote};
+use crate::editing::command::retime::Retime;
+use crate::editing::command::time_signature::{
+    CreateTimeSignaturePoint, EditTimeSignaturePoint, RemoveTimeSignaturePoint,
+};
 use bevy::prelude::*;
 use undo::Edit;
 
@@ -24,15 +30,22 @@ pub enum EditorCommand {
 
     CreateLine(CreateLine),
     RemoveLine(RemoveLine),
+    EditLine(EditLine),
     MoveLineAsChild(MoveLineAsChild),
 
     CreateBpmPoint(CreateBpmPoint),
     RemoveBpmPoint(RemoveBpmPoint),
     EditBpmPoint(EditBpmPoint),
 
+    CreateTimeSignaturePoint(CreateTimeSignaturePoint),
+    RemoveTimeSignaturePoint(RemoveTimeSignaturePoint),
+    EditTimeSignaturePoint(EditTimeSignaturePoint),
+
     EditMeta(EditMeta),
     EditOffset(EditOffset),
 
+    Retime(Retime),
+
     CommandSequence(CommandSequence),
 }
 
@@ -90,11 +103,16 @@ impl_edit_for_command!(
     EditEvent,
     CreateLine,
     RemoveLine,
+    EditLine,
     MoveLineAsChild,
     CreateBpmPoint,
     RemoveBpmPoint,
     EditBpmPoint,
+    CreateTimeSignaturePoint,
+    RemoveTimeSignaturePoint,
+    EditTimeSignaturePoint,
     EditMeta,
     EditOffset,
+    Retime,
     CommandSequence
 );
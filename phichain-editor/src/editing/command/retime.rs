@@ -0,0 +1,101 @@
+use crate::notification::{ToastsExt, ToastsStorage};
+use bevy::prelude::*;
+use phichain_chart::bpm_list::BpmList;
+use phichain_chart::event::LineEvent;
+use phichain_chart::note::Note;
+use phichain_chart::offset::Offset;
+use phichain_chart::retime::{RetimeOptions, Retimer};
+use undo::Edit;
+
+/// Change the BPM list and the offset, keeping the time of all notes and events in the audio
+#[derive(Debug, Clone)]
+pub struct Retime {
+    from: (BpmList, f32),
+    to: (BpmList, f32),
+    density: u32,
+
+    /// Notes and events before retiming, restored when undoing
+    notes: Vec<(Entity, Note)>,
+    events: Vec<(Entity, LineEvent)>,
+}
+
+impl Retime {
+    pub fn new(from: (BpmList, f32), to: (BpmList, f32), density: u32) -> Self {
+        Self {
+            from,
+            to,
+            density,
+            notes: vec![],
+            events: vec![],
+        }
+    }
+}
+
+impl Edit for Retime {
+    type Target = World;
+    type Output = ();
+
+    fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
+        let retimer = Retimer::new(
+            (&self.from.0, self.from.1),
+            (&self.to.0, self.to.1),
+            RetimeOptions {
+                density: self.density,
+                ..Default::default()
+            },
+        );
+
+        let mut off_grid = 0;
+
+        self.notes.clear();
+        let mut note_query = target.query::<(Entity, &mut Note)>();
+        for (entity, mut note) in note_query.iter_mut(target) {
+            self.notes.push((entity, *note));
+            let (retimed, error) = retimer.note(&note);
+            *note = retimed;
+            if retimer.is_off_grid(error) {
+                off_grid += 1;
+            }
+        }
+
+        self.events.clear();
+        let mut event_query = target.query::<(Entity, &mut LineEvent)>();
+        for (entity, mut event) in event_query.iter_mut(target) {
+            self.events.push((entity, event.clone()));
+            let (retimed, error) = retimer.event(&event);
+            *event = retimed;
+            if retimer.is_off_grid(error) {
+                off_grid += 1;
+            }
+        }
+
+        *target.resource_mut::<BpmList>() = self.to.0.clone();
+        target.resource_mut::<Offset>().0 = self.to.1;
+
+        if off_grid > 0 {
+            warn!(
+                "{} notes and events are off the beat grid after retiming",
+                off_grid
+            );
+            target
+                .resource_mut::<ToastsStorage>()
+                .warning(t!("edit.retime.off_grid", count = off_grid));
+        }
+    }
+
+    fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
+        for (entity, note) in &self.notes {
+            if let Some(mut current) = target.get_mut::<Note>(*entity) {
+                *current = *note;
+            }
+        }
+        for (entity, event) in &self.events {
+            if let Some(mut current) = target.get_mut::<LineEvent>(*entity) {
+                *current = event.clone();
+            }
+        }
+
+        *target.resource_mut::<BpmList>() = self.from.0.clone();
+        target.resource_mut::<Offset>().0 = self.from.1;
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::World;
+use phichain_chart::time_signature::{TimeSignatureList, TimeSignaturePoint};
+use undo::Edit;
+
+#[derive(Debug, Copy, Clone)]
+pub struct CreateTimeSignaturePoint {
+    point: TimeSignaturePoint,
+    /// Whether the point is inserted, points at the beat of an existing point are rejected
+    inserted: bool,
+}
+
+impl CreateTimeSignaturePoint {
+    pub fn new(point: TimeSignaturePoint) -> Self {
+        Self {
+            point,
+            inserted: false,
+        }
+    }
+}
+
+impl Edit for CreateTimeSignaturePoint {
+    type Target = World;
+    type Output = ();
+
+    fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        self.inserted = time_signatures.insert(self.point);
+    }
+
+    fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
+        if !self.inserted {
+            return;
+        }
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        if let Some(index) = time_signatures
+            .0
+            .iter()
+            .position(|point| *point == self.point)
+        {
+            time_signatures.0.remove(index);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RemoveTimeSignaturePoint {
+    index: usize,
+    point: Option<TimeSignaturePoint>,
+}
+
+impl RemoveTimeSignaturePoint {
+    pub fn new(index: usize) -> Self {
+        Self { index, point: None }
+    }
+}
+
+impl Edit for RemoveTimeSignaturePoint {
+    type Target = World;
+    type Output = ();
+
+    fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        self.point = Some(time_signatures.0.remove(self.index));
+    }
+
+    fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        if let Some(point) = self.point.take() {
+            time_signatures.insert(point);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct EditTimeSignaturePoint {
+    index: usize,
+    from: TimeSignaturePoint,
+    to: TimeSignaturePoint,
+}
+
+impl EditTimeSignaturePoint {
+    pub fn new(index: usize, from: TimeSignaturePoint, to: TimeSignaturePoint) -> Self {
+        Self { index, from, to }
+    }
+}
+
+impl Edit for EditTimeSignaturePoint {
+    type Target = World;
+    type Output = ();
+
+    fn edit(&mut self, target: &mut Self::Target) -> Self::Output {
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        time_signatures.0[self.index] = self.to;
+        time_signatures.sort();
+    }
+
+    fn undo(&mut self, target: &mut Self::Target) -> Self::Output {
+        let mut time_signatures = target.resource_mut::<TimeSignatureList>();
+        // the point may have moved when sorting, find it by its new value
+        let index = time_signatures
+            .0
+            .iter()
+            .position(|point| *point == self.to)
+            .unwrap_or(self.index);
+        time_signatures.0[index] = self.from;
+        time_signatures.sort();
+    }
+}
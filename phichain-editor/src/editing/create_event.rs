@@ -10,7 +10,10 @@ use crate::schedule::EditorSet;
 use crate::selection::SelectedLine;
 use crate::timeline::{TimelineContext, TimelineItem};
 use crate::utils::convert::BevyEguiConvert;
-use phichain_chart::event::{LineEvent, LineEventBundle, LineEventKind, LineEventValue};
+use phichain_chart::event::{
+    pack_color, LineEvent, LineEventBundle, LineEventKind, LineEventValue,
+};
+use strum::EnumCount;
 
 pub struct CreateEventPlugin;
 
@@ -60,8 +63,9 @@ fn create_event_system(
                 let beat = bpm_list.beat_at(time).value();
                 let beat = ctx.settings.attach(beat);
 
-                let track =
-                    ((cursor_position.x - viewport.min.x) / (viewport.width() / 5.0)).ceil() as u8;
+                let track = ((cursor_position.x - viewport.min.x)
+                    / (viewport.width() / LineEventKind::COUNT as f32))
+                    .ceil() as u8;
 
                 (track, beat)
             };
@@ -70,14 +74,18 @@ fn create_event_system(
                 let (track, beat) = calc_event_attrs();
                 pending_event.end_beat =
                     beat.max(pending_event.start_beat + ctx.settings.minimum_beat());
-                pending_event.kind = LineEventKind::try_from(track).expect("Unknown event track");
+                let kind = LineEventKind::try_from(track).expect("Unknown event track");
+                if kind != pending_event.kind {
+                    pending_event.kind = kind;
+                    pending_event.value = default_event_value(kind);
+                }
             }
 
             if keyboard.just_pressed(KeyCode::KeyR) {
                 if let Ok((pending_event, entity)) = pending_event_query.get_single() {
                     // TODO: compat constant events
                     // inherit event's start & end value from neighbor events
-                    let mut new_event = *pending_event;
+                    let mut new_event = pending_event.clone();
                     let mut events = event_query.iter().collect::<Vec<_>>();
                     events.sort_by_key(|x| x.0.start_beat);
                     if let Some(last_event) = events
@@ -90,11 +98,17 @@ fn create_event_system(
                     {
                         match new_event.value {
                             LineEventValue::Transition { ref mut start, .. } => {
-                                *start = last_event.value.end();
+                                *start = last_event.end_value();
                             }
                             LineEventValue::Constant(ref mut value) => {
-                                *value = last_event.value.end();
+                                *value = last_event.end_value();
+                            }
+                            LineEventValue::Text(ref mut text) => {
+                                if let LineEventValue::Text(last_text) = &last_event.value {
+                                    *text = last_text.clone();
+                                }
                             }
+                            LineEventValue::Expression(_) => {}
                         }
                     }
                     events.reverse();
@@ -108,11 +122,13 @@ fn create_event_system(
                     {
                         match new_event.value {
                             LineEventValue::Transition { ref mut end, .. } => {
-                                *end = next_event.value.start();
+                                *end = next_event.start_value();
                             }
                             LineEventValue::Constant(ref mut value) => {
-                                *value = next_event.value.start();
+                                *value = next_event.start_value();
                             }
+                            // texts are inherited from the previous event only
+                            LineEventValue::Text(_) | LineEventValue::Expression(_) => {}
                         }
                     }
                     commands.entity(entity).despawn();
@@ -126,9 +142,10 @@ fn create_event_system(
                         parent.spawn((
                             LineEventBundle::new(LineEvent {
                                 kind,
-                                value: LineEventValue::transition(0.0, 0.0, Easing::Linear),
+                                value: default_event_value(kind),
                                 start_beat: beat,
                                 end_beat: beat + ctx.settings.minimum_beat(),
+                                id: None,
                             }),
                             Pending,
                         ));
@@ -139,6 +156,21 @@ fn create_event_system(
     }
 }
 
+/// The value of a newly created event of the given kind
+fn default_event_value(kind: LineEventKind) -> LineEventValue {
+    match kind {
+        LineEventKind::ScaleX | LineEventKind::ScaleY => {
+            LineEventValue::transition(1.0, 1.0, Easing::Linear)
+        }
+        LineEventKind::Color => {
+            let white = pack_color([255, 255, 255]);
+            LineEventValue::transition(white, white, Easing::Linear)
+        }
+        LineEventKind::Text => LineEventValue::text(""),
+        _ => LineEventValue::transition(0.0, 0.0, Easing::Linear),
+    }
+}
+
 fn remove_pending_event_on_esc_system(
     mut commands: Commands,
     keyboard: Res<ButtonInput<KeyCode>>,
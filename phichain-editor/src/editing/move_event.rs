@@ -38,9 +38,13 @@ fn move_event_system(
                             let new_event = LineEvent {
                                 start_beat: event.start_beat + delta,
                                 end_beat: event.end_beat + delta,
-                                ..*event
+                                ..event.clone()
                             };
-                            EditorCommand::EditEvent(EditEvent::new(entity, *event, new_event))
+                            EditorCommand::EditEvent(EditEvent::new(
+                                entity,
+                                event.clone(),
+                                new_event,
+                            ))
                         })
                         .collect(),
                 ),
@@ -62,9 +66,13 @@ fn move_event_system(
                             let new_event = LineEvent {
                                 start_beat: event.start_beat + delta,
                                 end_beat: event.end_beat + delta,
-                                ..*event
+                                ..event.clone()
                             };
-                            EditorCommand::EditEvent(EditEvent::new(entity, *event, new_event))
+                            EditorCommand::EditEvent(EditEvent::new(
+                                entity,
+                                event.clone(),
+                                new_event,
+                            ))
                         })
                         .collect(),
                 ),
@@ -1,16 +1,19 @@
 use crate::file::{PickingEvent, PickingKind};
 use crate::notification::{ToastsExt, ToastsStorage};
 use crate::project::{project_loaded, Project};
-use anyhow::{bail, Context};
+use crate::settings::EditorSettings;
+use anyhow::bail;
 use bevy::app::App;
 use bevy::prelude::*;
-use phichain_chart::format::official::OfficialChart;
-use phichain_chart::primitive::Format;
-use phichain_chart::serialization::PhichainChart;
+use bevy_persistent::Persistent;
+use phichain_chart::format::official::OfficialExportOptions;
+use phichain_chart::report::ConversionReport;
+use phichain_export::{export_project, ExportOptions, PackageFormat};
 use std::fs;
-use std::io::{Read, Write};
 use std::path::Path;
-use zip::write::SimpleFileOptions;
+
+/// Maximum number of losses listed in the toast after exporting, the rest are only logged
+const MAX_TOAST_LOSSES: usize = 5;
 
 pub struct ExportPlugin;
 
@@ -20,75 +23,43 @@ impl Plugin for ExportPlugin {
     }
 }
 
-fn export_official(path: &Path, project: &Project) -> anyhow::Result<()> {
+fn export_official(
+    path: &Path,
+    project: &Project,
+    options: OfficialExportOptions,
+    source_map: bool,
+    strict: bool,
+) -> anyhow::Result<ConversionReport> {
     let zip_path = path.join("chart.zip");
     if zip_path.exists() {
         bail!("chart.zip already exists in the folder");
     }
 
-    let file = fs::File::create(zip_path)?;
-
-    let mut zip = zip::ZipWriter::new(file);
-
-    zip.start_file("chart.json", SimpleFileOptions::default())?;
-    let chart_file = fs::File::open(project.path.chart_path())?;
-    let chart: PhichainChart = serde_json::from_reader(chart_file)?;
-    let official = OfficialChart::from_primitive(phichain_compiler::compile(chart)?)?;
-    zip.write_all(serde_json::to_string(&official)?.as_bytes())?;
-
-    if let Some(illustration_path) = project.path.illustration_path() {
-        let filename = illustration_path
-            .file_name()
-            .context("Failed to get filename of illustration")?
-            .to_str()
-            .context("Failed to convert illustration filename to str")?;
-        zip.start_file(filename, SimpleFileOptions::default())?;
-        let mut illustration_file = fs::File::open(illustration_path)?;
-        let mut illustration_data = Vec::new();
-        illustration_file.read_to_end(&mut illustration_data)?;
-        zip.write_all(&illustration_data)?;
-    }
-
-    if let Some(music_path) = project.path.music_path() {
-        let filename = music_path
-            .file_name()
-            .context("Failed to get filename of music")?
-            .to_str()
-            .context("Failed to convert music filename to str")?;
-        zip.start_file(filename, SimpleFileOptions::default())?;
-        let mut music_file = fs::File::open(music_path)?;
-        let mut music_data = Vec::new();
-        music_file.read_to_end(&mut music_data)?;
-        zip.write_all(&music_data)?;
-    }
-
-    zip.start_file("info.txt", SimpleFileOptions::default())?;
-    zip.write_all(
-        format!(
-            "#
-Name: {}
-Level: {}
-Composer: {}
-Illustrator: {}
-Charter: {}
-",
-            project.meta.name,
-            project.meta.level,
-            project.meta.composer,
-            project.meta.illustrator,
-            project.meta.charter
-        )
-        .as_bytes(),
+    let output = export_project(
+        project,
+        &zip_path,
+        &ExportOptions {
+            format: PackageFormat::Official,
+            official: options,
+            source_map,
+            strict,
+        },
     )?;
 
-    zip.finish()?;
+    if let Some(source_map) = &output.source_map {
+        fs::write(
+            path.join("chart.map.json"),
+            serde_json::to_string(source_map)?,
+        )?;
+    }
 
-    Ok(())
+    Ok(output.report)
 }
 
 fn export_official_system(
     mut event_reader: EventReader<PickingEvent>,
     project: Res<Project>,
+    editor_settings: Res<Persistent<EditorSettings>>,
     mut toasts: ResMut<ToastsStorage>,
 ) {
     for PickingEvent { path, kind } in event_reader.read() {
@@ -100,9 +71,23 @@ fn export_official_system(
             return;
         };
 
-        match export_official(path, &project) {
-            Ok(_) => {
+        let options = OfficialExportOptions {
+            tolerance: editor_settings.export.official_tolerance,
+        };
+
+        match export_official(
+            path,
+            &project,
+            options,
+            editor_settings.export.source_map,
+            editor_settings.export.strict,
+        ) {
+            Ok(report) => {
                 toasts.success("Successfully exported official chart");
+                if !report.is_empty() {
+                    report.log();
+                    toasts.warning(lossy_message(&report));
+                }
             }
             Err(error) => {
                 toasts.error(format!("Failed to export official chart: {}", error));
@@ -110,3 +95,26 @@ fn export_official_system(
         }
     }
 }
+
+/// List the first losses of a report, followed by the number of the rest
+fn lossy_message(report: &ConversionReport) -> String {
+    let mut lines = vec![t!("export.lossy", count = report.losses.len()).to_string()];
+    lines.extend(
+        report
+            .losses
+            .iter()
+            .take(MAX_TOAST_LOSSES)
+            .map(|loss| loss.to_string()),
+    );
+    if report.losses.len() > MAX_TOAST_LOSSES {
+        lines.push(
+            t!(
+                "export.lossy_more",
+                count = report.losses.len() - MAX_TOAST_LOSSES
+            )
+            .to_string(),
+        );
+    }
+
+    lines.join("\n")
+}
@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use phichain_chart::bpm_list::BpmList;
 use phichain_chart::line::Line;
 use phichain_chart::offset::Offset;
+use phichain_chart::time_signature::TimeSignatureList;
 
 use phichain_chart::serialization::{LineWrapper, PhichainChart};
 
@@ -10,11 +11,13 @@ use super::Exporter;
 
 pub struct PhichainExporter;
 
-impl Exporter for PhichainExporter {
-    fn export(world: &mut World) -> anyhow::Result<String> {
+impl PhichainExporter {
+    /// Serialize the chart in the world
+    pub fn chart(world: &mut World) -> PhichainChart {
         let bpm_list = world.resource::<BpmList>().clone();
         let offset = world.resource::<Offset>().0;
         let mut chart = PhichainChart::new(offset, bpm_list, vec![]);
+        chart.time_signatures = world.resource::<TimeSignatureList>().clone();
 
         let mut line_query = world.query_filtered::<Entity, (With<Line>, Without<Parent>)>();
 
@@ -24,6 +27,12 @@ impl Exporter for PhichainExporter {
             chart.lines.push(LineWrapper::serialize_line(world, entity));
         }
 
-        serde_json::to_string(&chart).context("Failed to export chart as phichain")
+        chart
+    }
+}
+
+impl Exporter for PhichainExporter {
+    fn export(world: &mut World) -> anyhow::Result<String> {
+        serde_json::to_string(&Self::chart(world)).context("Failed to export chart as phichain")
     }
 }
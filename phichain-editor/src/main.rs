@@ -21,6 +21,7 @@ mod schedule;
 mod screenshot;
 mod selection;
 mod settings;
+mod source_map;
 mod tab;
 mod timeline;
 mod timing;
@@ -48,6 +49,7 @@ use crate::schedule::EditorSet;
 use crate::screenshot::ScreenshotPlugin;
 use crate::selection::Selected;
 use crate::settings::{AspectRatio, EditorSettings, EditorSettingsPlugin};
+use crate::source_map::SourceMapPlugin;
 use crate::tab::game::GameCamera;
 use crate::tab::game::GameTabPlugin;
 use crate::tab::game::GameViewport;
@@ -124,6 +126,7 @@ fn main() {
         .add_plugins(EguiPlugin)
         .add_plugins(ProjectPlugin)
         .add_plugins(ExportPlugin)
+        .add_plugins(SourceMapPlugin)
         .add_plugins(selection::SelectionPlugin)
         .add_plugins(TabPlugin)
         .add_plugins(EditingPlugin)
@@ -18,6 +18,7 @@ impl Plugin for NotificationPlugin {
 pub trait ToastsExt {
     fn error(&mut self, message: impl Into<WidgetText>);
     fn success(&mut self, message: impl Into<WidgetText>);
+    fn warning(&mut self, message: impl Into<WidgetText>);
 }
 
 impl ToastsExt for Toasts {
@@ -40,6 +41,16 @@ impl ToastsExt for Toasts {
                 .show_progress(true),
         });
     }
+
+    fn warning(&mut self, text: impl Into<WidgetText>) {
+        self.add(Toast {
+            text: text.into(),
+            kind: ToastKind::Warning,
+            options: ToastOptions::default()
+                .duration_in_seconds(8.0)
+                .show_progress(true),
+        });
+    }
 }
 
 impl Default for ToastsStorage {
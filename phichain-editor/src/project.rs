@@ -168,9 +168,12 @@ fn unload_project_system(
 
         // unload chart basic components
         use crate::selection::SelectedLine;
-        use phichain_chart::{bpm_list::BpmList, offset::Offset};
+        use phichain_chart::{
+            bpm_list::BpmList, offset::Offset, time_signature::TimeSignatureList,
+        };
         world.remove_resource::<Offset>();
         world.remove_resource::<BpmList>();
+        world.remove_resource::<TimeSignatureList>();
         world.remove_resource::<SelectedLine>();
 
         // unload lines, notes and events
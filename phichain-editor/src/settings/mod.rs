@@ -1,6 +1,7 @@
 use crate::misc::WorkingDirectory;
 use bevy::prelude::*;
 use bevy_persistent::{Persistent, StorageFormat};
+use phichain_chart::format::official::DEFAULT_TOLERANCE;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -41,6 +42,10 @@ pub struct GeneralSettings {
     pub timeline_scroll_sensitivity: f32,
     pub highlight_selected_line: bool,
     pub show_line_anchor: ShowLineAnchorOption,
+    /// Retime notes and events to keep their time when BPM points or the offset change
+    pub keep_note_times: bool,
+    /// Density of the beat grid which retimed notes and events are quantised to
+    pub retime_density: u32,
 }
 
 impl Default for GeneralSettings {
@@ -50,6 +55,8 @@ impl Default for GeneralSettings {
             timeline_scroll_sensitivity: 10.0,
             highlight_selected_line: true,
             show_line_anchor: ShowLineAnchorOption::Always,
+            keep_note_times: false,
+            retime_density: 32,
         }
     }
 }
@@ -136,6 +143,26 @@ impl Default for LineListSettings {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub official_tolerance: f32,
+    /// Write a source map linking the exported chart back to the project chart
+    pub source_map: bool,
+    /// Fail instead of exporting if the export is lossy
+    pub strict: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            official_tolerance: DEFAULT_TOLERANCE,
+            source_map: false,
+            strict: false,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct UiSettings {
@@ -148,5 +175,6 @@ pub struct EditorSettings {
     pub general: GeneralSettings,
     pub audio: AudioSettings,
     pub game: GameSettings,
+    pub export: ExportSettings,
     pub ui: UiSettings,
 }
@@ -0,0 +1,124 @@
+use crate::exporter::phichain::PhichainExporter;
+use crate::notification::{ToastsExt, ToastsStorage};
+use crate::project::project_loaded;
+use crate::selection::{SelectEvent, SelectedLine};
+use crate::timing::SeekToEvent;
+use bevy::prelude::*;
+use phichain_chart::beat::Beat;
+use phichain_chart::bpm_list::BpmList;
+use phichain_chart::event::LineEvent;
+use phichain_chart::line::Line;
+use phichain_chart::lint::Location;
+use phichain_chart::note::Note;
+use phichain_compiler::source_map::Source;
+use phichain_compiler::Pipeline;
+
+/// Select the source of a line, a note or an event at a location in the compiled primitive chart
+#[derive(Event, Debug, Clone)]
+pub struct JumpToCompiledEvent(pub Location);
+
+pub struct SourceMapPlugin;
+
+impl Plugin for SourceMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<JumpToCompiledEvent>().add_systems(
+            Update,
+            jump_to_compiled_system
+                .run_if(project_loaded().and_then(on_event::<JumpToCompiledEvent>())),
+        );
+    }
+}
+
+fn jump_to_compiled_system(world: &mut World) {
+    let locations = world
+        .resource_mut::<Events<JumpToCompiledEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect::<Vec<_>>();
+
+    for location in locations {
+        // compile the chart in the world, so the source map matches the entities
+        let chart = PhichainExporter::chart(world);
+        let source = Pipeline::default()
+            .compile_with_source_map(chart)
+            .map(|output| {
+                output
+                    .source_map
+                    .and_then(|source_map| source_map.source(&location).cloned())
+            });
+
+        match source {
+            Ok(Some(source)) => {
+                if !jump(world, &source) {
+                    world.resource_mut::<ToastsStorage>().warning(t!(
+                        "source_map.entity_not_found",
+                        location = source.location
+                    ));
+                }
+            }
+            Ok(None) => {
+                world
+                    .resource_mut::<ToastsStorage>()
+                    .warning(t!("source_map.not_found", location = location));
+            }
+            Err(error) => {
+                world
+                    .resource_mut::<ToastsStorage>()
+                    .error(t!("source_map.compile_failed", error = error));
+            }
+        }
+    }
+}
+
+/// Select the entity of a source and seek to it, returns false if the entity is not found
+fn jump(world: &mut World, source: &Source) -> bool {
+    let id = Some(source.id);
+
+    let (line, target): (Entity, Option<(Entity, Beat)>) = match source.location {
+        Location::Line { .. } => {
+            let mut query = world.query::<(Entity, &Line)>();
+            let Some(line) = query
+                .iter(world)
+                .find(|(_, line)| line.id == id)
+                .map(|(entity, _)| entity)
+            else {
+                return false;
+            };
+            (line, None)
+        }
+        Location::Note { .. } => {
+            let mut query = world.query::<(Entity, &Note, &Parent)>();
+            let Some((entity, note, parent)) = query.iter(world).find(|(_, note, _)| note.id == id)
+            else {
+                return false;
+            };
+            (parent.get(), Some((entity, note.beat)))
+        }
+        Location::Event { .. } => {
+            let mut query = world.query::<(Entity, &LineEvent, &Parent)>();
+            let Some((entity, event, parent)) =
+                query.iter(world).find(|(_, event, _)| event.id == id)
+            else {
+                return false;
+            };
+            (parent.get(), Some((entity, event.start_beat)))
+        }
+        Location::BpmPoint { .. } => return false,
+    };
+
+    world.insert_resource(SelectedLine(line));
+    if let Some((entity, beat)) = target {
+        let time = world.resource::<BpmList>().time_at(beat);
+        world.send_event(SelectEvent(vec![entity]));
+        world.send_event(SeekToEvent(time));
+    }
+
+    true
+}
+
+/// Parse the path of a line, e.g. `1/0` is the first child of the second line
+pub fn parse_line_path(path: &str) -> Option<Vec<usize>> {
+    path.split('/')
+        .map(|index| index.trim().parse().ok())
+        .collect()
+}
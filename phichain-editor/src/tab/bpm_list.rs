@@ -1,20 +1,39 @@
 use crate::editing::command::bpm_list::{CreateBpmPoint, EditBpmPoint, RemoveBpmPoint};
+use crate::editing::command::retime::Retime;
 use crate::editing::command::EditorCommand;
 use crate::editing::DoCommandEvent;
+use crate::settings::EditorSettings;
 use crate::ui::latch;
 use crate::ui::widgets::beat_value::BeatValue;
+use crate::ui::widgets::easing_value::EasingValue;
 use bevy::prelude::*;
+use bevy_persistent::Persistent;
 use egui::Ui;
 use phichain_chart::beat;
 use phichain_chart::beat::Beat;
 use phichain_chart::bpm_list::{BpmList, BpmPoint};
+use phichain_chart::easing::Easing;
+use phichain_chart::offset::Offset;
 
 pub fn bpm_list_tab(
     In(mut ui): In<Ui>,
     mut bpm_list: ResMut<BpmList>,
+    offset: Res<Offset>,
+    settings: Res<Persistent<EditorSettings>>,
     mut event_writer: EventWriter<DoCommandEvent>,
 ) {
+    // retime notes and events to keep their time, if enabled
+    let retime = |from: BpmList, mut to: BpmList| {
+        to.compute();
+        EditorCommand::Retime(Retime::new(
+            (from, offset.0),
+            (to, offset.0),
+            settings.general.retime_density,
+        ))
+    };
+
     let mut changes = Vec::new();
+    let mut retimes = Vec::new();
     let mut deletes = Vec::new();
 
     for index in 0..bpm_list.0.len() {
@@ -53,6 +72,27 @@ pub fn bpm_list_tab(
                         finished |= response.drag_stopped() || response.lost_focus();
                         ui.end_row();
 
+                        ui.label(t!("tab.bpm_list.point.ramp"));
+                        ui.vertical(|ui| {
+                            let mut ramp = point.ramp.is_some();
+                            if ui
+                                .add_enabled(
+                                    next_beat.is_some(),
+                                    egui::Checkbox::without_text(&mut ramp),
+                                )
+                                .on_disabled_hover_text(t!("tab.bpm_list.last_point_no_ramp"))
+                                .changed()
+                            {
+                                point.ramp = ramp.then_some(Easing::Linear);
+                                finished = true;
+                            }
+                            if let Some(ref mut easing) = point.ramp {
+                                let response = ui.add(EasingValue::new(easing));
+                                finished |= response.drag_stopped() || response.lost_focus();
+                            }
+                        });
+                        ui.end_row();
+
                         if beat != point.beat {
                             point.beat = beat;
                             changes.push((index, beat));
@@ -63,9 +103,13 @@ pub fn bpm_list_tab(
 
                     if let Some(from) = result {
                         if from != *point {
-                            event_writer.send(DoCommandEvent(EditorCommand::EditBpmPoint(
-                                EditBpmPoint::new(index, from, *point),
-                            )));
+                            if settings.general.keep_note_times {
+                                retimes.push((index, from));
+                            } else {
+                                event_writer.send(DoCommandEvent(EditorCommand::EditBpmPoint(
+                                    EditBpmPoint::new(index, from, *point),
+                                )));
+                            }
                         }
                     }
                 });
@@ -92,9 +136,16 @@ pub fn bpm_list_tab(
             .last()
             .map(|x| x.beat + Beat::ONE)
             .unwrap_or(Beat::ONE);
-        event_writer.send(DoCommandEvent(EditorCommand::CreateBpmPoint(
-            CreateBpmPoint::new(BpmPoint::new(beat, 120.0)),
-        )));
+        let point = BpmPoint::new(beat, 120.0);
+        if settings.general.keep_note_times {
+            let mut to = bpm_list.clone();
+            to.insert(point);
+            event_writer.send(DoCommandEvent(retime(bpm_list.clone(), to)));
+        } else {
+            event_writer.send(DoCommandEvent(EditorCommand::CreateBpmPoint(
+                CreateBpmPoint::new(point),
+            )));
+        }
     }
 
     // recompute after all changes are applied
@@ -102,11 +153,28 @@ pub fn bpm_list_tab(
         bpm_list.compute();
     }
 
+    for (index, from) in retimes {
+        let mut from_list = bpm_list.clone();
+        from_list.0[index] = from;
+        from_list.compute();
+        event_writer.send(DoCommandEvent(retime(from_list, bpm_list.clone())));
+    }
+
     if !deletes.is_empty() {
-        for i in deletes {
-            event_writer.send(DoCommandEvent(EditorCommand::RemoveBpmPoint(
-                RemoveBpmPoint::new(i),
-            )));
+        if settings.general.keep_note_times {
+            // remove all points from one list, so the notes are retimed once against the final list
+            let mut to = bpm_list.clone();
+            for index in deletes.iter().rev() {
+                to.0.remove(*index);
+            }
+            event_writer.send(DoCommandEvent(retime(bpm_list.clone(), to)));
+        } else {
+            // remove from the back, so indexes of the remaining points are still valid
+            for index in deletes.into_iter().rev() {
+                event_writer.send(DoCommandEvent(EditorCommand::RemoveBpmPoint(
+                    RemoveBpmPoint::new(index),
+                )));
+            }
         }
         bpm_list.compute();
     }
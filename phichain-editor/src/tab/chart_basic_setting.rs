@@ -1,16 +1,22 @@
 use crate::editing::command::meta::{EditMeta, EditOffset};
+use crate::editing::command::retime::Retime;
 use crate::editing::command::EditorCommand;
 use crate::editing::DoCommandEvent;
 use crate::project::Project;
+use crate::settings::EditorSettings;
 use crate::ui::latch;
 use bevy::prelude::*;
+use bevy_persistent::Persistent;
 use egui::Ui;
+use phichain_chart::bpm_list::BpmList;
 use phichain_chart::offset::Offset;
 
 pub fn chart_basic_setting_tab(
     In(mut ui): In<Ui>,
     mut offset: ResMut<Offset>,
     mut project: ResMut<Project>,
+    bpm_list: Res<BpmList>,
+    settings: Res<Persistent<EditorSettings>>,
 
     mut event_writer: EventWriter<DoCommandEvent>,
 ) {
@@ -69,10 +75,17 @@ pub fn chart_basic_setting_tab(
                 }
 
                 if offset_from != offset.0 {
-                    event_writer.send(DoCommandEvent(EditorCommand::EditOffset(EditOffset::new(
-                        offset_from,
-                        offset.0,
-                    ))));
+                    if settings.general.keep_note_times {
+                        event_writer.send(DoCommandEvent(EditorCommand::Retime(Retime::new(
+                            (bpm_list.clone(), offset_from),
+                            (bpm_list.clone(), offset.0),
+                            settings.general.retime_density,
+                        ))));
+                    } else {
+                        event_writer.send(DoCommandEvent(EditorCommand::EditOffset(
+                            EditOffset::new(offset_from, offset.0),
+                        )));
+                    }
                 }
             }
         });
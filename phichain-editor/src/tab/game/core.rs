@@ -18,7 +18,12 @@ impl Plugin for CoreGamePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, zoom_scale_system.run_if(project_loaded()))
             .add_systems(Update, sync_game_config_system.run_if(project_loaded()))
-            .add_systems(Update, update_note_tint_system.run_if(project_loaded()))
+            .add_systems(
+                Update,
+                update_note_tint_system
+                    .after(phichain_game::core::update_note_alpha_system)
+                    .run_if(project_loaded()),
+            )
             .add_systems(
                 Update,
                 sync_hold_components_tint_system
@@ -51,16 +56,16 @@ fn zoom_scale_system(
 }
 
 fn update_note_tint_system(
-    mut query: Query<(&mut Sprite, Option<&Selected>, Option<&Pending>), With<Note>>,
+    mut query: Query<(&mut Sprite, &Note, Option<&Selected>, Option<&Pending>)>,
 ) {
-    for (mut sprite, selected, pending) in &mut query {
+    for (mut sprite, note, selected, pending) in &mut query {
         let tint = if selected.is_some() {
             Color::LIME_GREEN
         } else {
             Color::WHITE
         };
         let alpha = if pending.is_some() { 40.0 / 255.0 } else { 1.0 };
-        sprite.color = tint.with_a(alpha);
+        sprite.color = tint.with_a(alpha * note.alpha / 255.0);
     }
 }
 
@@ -3,6 +3,7 @@ use egui::{Align, Layout, Ui};
 use phichain_chart::beat;
 
 use crate::editing::command::event::EditEvent;
+use crate::editing::command::line::EditLine;
 use crate::editing::command::note::EditNote;
 use crate::editing::command::{CommandSequence, EditorCommand};
 use crate::editing::DoCommandEvent;
@@ -10,7 +11,9 @@ use crate::selection::{Selected, SelectedLine};
 use crate::ui::latch;
 use crate::ui::widgets::beat_value::BeatExt;
 use crate::ui::widgets::easing_value::EasingValue;
-use phichain_chart::event::{LineEvent, LineEventKind, LineEventValue};
+use phichain_chart::easing::Easing;
+use phichain_chart::event::{pack_color, unpack_color, LineEvent, LineEventKind, LineEventValue};
+use phichain_chart::expression::Expression;
 use phichain_chart::line::Line;
 use phichain_chart::note::{Note, NoteKind};
 
@@ -35,7 +38,7 @@ pub fn inspector_ui_system(
     } else if selected_notes.is_empty() && selected_events.len() > 1 {
         multiple_events_inspector(&mut ui, &selected_events, event_writer);
     } else if let Ok(mut line) = line_query.get_mut(selected_line.0) {
-        line_inspector(&mut ui, &mut line);
+        line_inspector(&mut ui, selected_line.0, &mut line, event_writer);
     }
 }
 
@@ -50,7 +53,7 @@ fn single_event_inspector(
         .spacing([20.0, 2.0])
         .striped(true)
         .show(ui, |ui| {
-            let result = latch::latch(ui, "event", *event, |ui| {
+            let result = latch::latch(ui, "event", event.clone(), |ui| {
                 let mut finished = false;
 
                 ui.label(t!("tab.inspector.single_event.start_beat"));
@@ -63,36 +66,64 @@ fn single_event_inspector(
                 finished |= response.drag_stopped() || response.lost_focus();
                 ui.end_row();
 
-                ui.label(t!("tab.inspector.single_event.value_type"));
-                ui.columns(2, |columns| {
-                    if columns[0]
-                        .selectable_label(
-                            event.value.is_transition(),
-                            t!("tab.inspector.single_event.transition"),
-                        )
-                        .clicked()
-                    {
-                        let mut new_event = *event;
-                        new_event.value = new_event.value.into_transition();
-                        event_writer.send(DoCommandEvent(EditorCommand::EditEvent(
-                            EditEvent::new(entity, *event, new_event),
-                        )));
-                    }
-                    if columns[1]
-                        .selectable_label(
-                            event.value.is_constant(),
-                            t!("tab.inspector.single_event.constant"),
-                        )
-                        .clicked()
-                    {
-                        let mut new_event = *event;
-                        new_event.value = new_event.value.into_constant();
-                        event_writer.send(DoCommandEvent(EditorCommand::EditEvent(
-                            EditEvent::new(entity, *event, new_event),
-                        )));
-                    }
-                });
-                ui.end_row();
+                if !event.kind.is_text() {
+                    ui.label(t!("tab.inspector.single_event.value_type"));
+                    ui.columns(3, |columns| {
+                        if columns[0]
+                            .selectable_label(
+                                event.value.is_transition(),
+                                t!("tab.inspector.single_event.transition"),
+                            )
+                            .clicked()
+                        {
+                            let mut new_event = event.clone();
+                            new_event.value = match &event.value {
+                                // expressions keep their values at both ends
+                                LineEventValue::Expression(_) => LineEventValue::transition(
+                                    event.start_value(),
+                                    event.end_value(),
+                                    Easing::Linear,
+                                ),
+                                value => value.clone().into_transition(),
+                            };
+                            event_writer.send(DoCommandEvent(EditorCommand::EditEvent(
+                                EditEvent::new(entity, event.clone(), new_event),
+                            )));
+                        }
+                        if columns[1]
+                            .selectable_label(
+                                event.value.is_constant(),
+                                t!("tab.inspector.single_event.constant"),
+                            )
+                            .clicked()
+                        {
+                            let mut new_event = event.clone();
+                            new_event.value = match &event.value {
+                                LineEventValue::Expression(_) => {
+                                    LineEventValue::constant(event.start_value())
+                                }
+                                value => value.clone().into_constant(),
+                            };
+                            event_writer.send(DoCommandEvent(EditorCommand::EditEvent(
+                                EditEvent::new(entity, event.clone(), new_event),
+                            )));
+                        }
+                        if columns[2]
+                            .selectable_label(
+                                event.value.is_expression(),
+                                t!("tab.inspector.single_event.expression"),
+                            )
+                            .clicked()
+                        {
+                            let mut new_event = event.clone();
+                            new_event.value = new_event.value.into_expression();
+                            event_writer.send(DoCommandEvent(EditorCommand::EditEvent(
+                                EditEvent::new(entity, event.clone(), new_event),
+                            )));
+                        }
+                    });
+                    ui.end_row();
+                }
 
                 match event.value {
                     LineEventValue::Transition {
@@ -101,24 +132,12 @@ fn single_event_inspector(
                         ref mut easing,
                     } => {
                         ui.label(t!("tab.inspector.single_event.start_value"));
-                        let range = match event.kind {
-                            LineEventKind::Opacity => 0.0..=255.0,
-                            _ => f32::MIN..=f32::MAX,
-                        };
-                        let response = ui.add(
-                            egui::DragValue::new(start)
-                                .clamp_range(range.clone())
-                                .speed(1.0),
-                        );
+                        let response = event_value_ui(ui, event.kind, start);
                         finished |= response.drag_stopped() || response.lost_focus();
                         ui.end_row();
 
                         ui.label(t!("tab.inspector.single_event.end_value"));
-                        let response = ui.add(
-                            egui::DragValue::new(end)
-                                .clamp_range(range.clone())
-                                .speed(1.0),
-                        );
+                        let response = event_value_ui(ui, event.kind, end);
                         finished |= response.drag_stopped() || response.lost_focus();
                         ui.end_row();
 
@@ -129,18 +148,48 @@ fn single_event_inspector(
                     }
                     LineEventValue::Constant(ref mut value) => {
                         ui.label(t!("tab.inspector.single_event.value"));
-                        let range = match event.kind {
-                            LineEventKind::Opacity => 0.0..=255.0,
-                            _ => f32::MIN..=f32::MAX,
-                        };
-                        let response = ui.add(
-                            egui::DragValue::new(value)
-                                .clamp_range(range.clone())
-                                .speed(1.0),
-                        );
+                        let response = event_value_ui(ui, event.kind, value);
                         finished |= response.drag_stopped() || response.lost_focus();
                         ui.end_row();
                     }
+                    LineEventValue::Text(ref text) => {
+                        ui.label(t!("tab.inspector.single_event.text"));
+                        let mut text = text.to_string();
+                        let response = ui.text_edit_singleline(&mut text);
+                        if response.changed() {
+                            event.value = LineEventValue::text(text);
+                        }
+                        finished |= response.lost_focus();
+                        ui.end_row();
+                    }
+                    LineEventValue::Expression(ref expression) => {
+                        ui.label(t!("tab.inspector.single_event.expression"))
+                            .on_hover_text(t!("tab.inspector.single_event.expression_hint"));
+                        // keep the text being edited, it is not a valid expression while typing
+                        let id = egui::Id::new("inspector-expression").with(entity);
+                        let mut source = ui
+                            .data(|data| data.get_temp::<String>(id))
+                            .unwrap_or_else(|| expression.source().to_owned());
+                        let response = ui.text_edit_singleline(&mut source);
+                        let parsed = Expression::parse(&source);
+                        if let (true, Ok(parsed)) = (response.changed(), &parsed) {
+                            event.value = LineEventValue::expression(parsed.clone());
+                        }
+                        finished |= response.lost_focus();
+                        ui.end_row();
+
+                        if let Err(error) = parsed {
+                            ui.label("");
+                            ui.colored_label(egui::Color32::LIGHT_RED, error.to_string());
+                            ui.end_row();
+                        }
+
+                        if response.has_focus() {
+                            ui.data_mut(|data| data.insert_temp(id, source));
+                        } else {
+                            ui.data_mut(|data| data.remove::<String>(id));
+                        }
+                    }
                 }
 
                 finished
@@ -149,13 +198,38 @@ fn single_event_inspector(
             if let Some(from) = result {
                 if from != *event {
                     event_writer.send(DoCommandEvent(EditorCommand::EditEvent(EditEvent::new(
-                        entity, from, *event,
+                        entity,
+                        from,
+                        event.clone(),
                     ))));
                 }
             }
         });
 }
 
+/// Edit a numeric value of an event, colors are edited with a color picker
+fn event_value_ui(ui: &mut Ui, kind: LineEventKind, value: &mut f32) -> egui::Response {
+    match kind {
+        LineEventKind::Color => {
+            let mut color = unpack_color(*value);
+            let mut response = ui.color_edit_button_srgb(&mut color);
+            if response.changed() {
+                *value = pack_color(color);
+                // the color picker has no drag state, commit every change
+                response.drag_stopped = true;
+            }
+            response
+        }
+        _ => {
+            let range = match kind {
+                LineEventKind::Opacity => 0.0..=255.0,
+                _ => f32::MIN..=f32::MAX,
+            };
+            ui.add(egui::DragValue::new(value).clamp_range(range).speed(1.0))
+        }
+    }
+}
+
 fn single_note_inspector(
     ui: &mut Ui,
     entity: Entity,
@@ -201,6 +275,51 @@ fn single_note_inspector(
                 ui.label(t!("tab.inspector.single_note.speed"));
                 let response = ui.add(egui::DragValue::new(&mut note.speed).speed(0.1));
                 finished |= response.drag_stopped() || response.lost_focus();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.single_note.fake"));
+                let response = ui.checkbox(&mut note.fake, "");
+                finished |= response.changed();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.single_note.alpha"));
+                let response = ui.add(
+                    egui::DragValue::new(&mut note.alpha)
+                        .speed(1)
+                        .clamp_range(0.0..=255.0),
+                );
+                finished |= response.drag_stopped() || response.lost_focus();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.single_note.size"));
+                let response = ui.add(egui::DragValue::new(&mut note.size).speed(0.01));
+                finished |= response.drag_stopped() || response.lost_focus();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.single_note.visible_time"));
+                ui.horizontal(|ui| {
+                    let mut limited = note.visible_time.is_some();
+                    let response = ui.checkbox(&mut limited, "");
+                    finished |= response.changed();
+                    if limited != note.visible_time.is_some() {
+                        note.visible_time = limited.then_some(1.0);
+                    }
+
+                    if let Some(visible_time) = &mut note.visible_time {
+                        let response = ui.add(
+                            egui::DragValue::new(visible_time)
+                                .speed(0.01)
+                                .clamp_range(0.0..=f32::MAX)
+                                .suffix("s"),
+                        );
+                        finished |= response.drag_stopped() || response.lost_focus();
+                    }
+                });
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.single_note.y_offset"));
+                let response = ui.add(egui::DragValue::new(&mut note.y_offset).speed(1));
+                finished |= response.drag_stopped() || response.lost_focus();
 
                 finished
             });
@@ -346,14 +465,19 @@ fn multiple_events_inspector(
         {
             let commands = notes
                 .iter()
-                .filter(|(event, _)| event.kind != LineEventKind::Opacity)
+                .filter(|(event, _)| {
+                    !matches!(
+                        event.kind,
+                        LineEventKind::Opacity | LineEventKind::Color | LineEventKind::Text
+                    )
+                })
                 .map(|(event, entity)| {
                     EditorCommand::EditEvent(EditEvent::new(
                         *entity,
-                        **event,
+                        (**event).clone(),
                         LineEvent {
                             value: event.value.negated(),
-                            ..**event
+                            ..(**event).clone()
                         },
                     ))
                 })
@@ -366,7 +490,12 @@ fn multiple_events_inspector(
     });
 }
 
-fn line_inspector(ui: &mut Ui, line: &mut Line) {
+fn line_inspector(
+    ui: &mut Ui,
+    entity: Entity,
+    line: &mut Line,
+    mut event_writer: EventWriter<DoCommandEvent>,
+) {
     egui::Grid::new("inspector_grid")
         .num_columns(2)
         .spacing([20.0, 2.0])
@@ -380,12 +509,52 @@ fn line_inspector(ui: &mut Ui, line: &mut Line) {
                 finished |= response.lost_focus();
                 ui.end_row();
 
+                ui.label(t!("tab.inspector.line.texture"));
+                let mut texture = line.texture.clone().unwrap_or_default();
+                let response = ui
+                    .add(egui::TextEdit::singleline(&mut texture).hint_text("line.png"))
+                    .on_hover_text(t!("tab.inspector.line.texture_hint"));
+                if response.changed() {
+                    line.texture = (!texture.is_empty()).then_some(texture);
+                }
+                finished |= response.lost_focus();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.line.z_order"));
+                let response = ui.add(egui::DragValue::new(&mut line.z_order));
+                finished |= response.drag_stopped() || response.lost_focus();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.line.hide_notes_below"));
+                let response = ui.checkbox(&mut line.hide_notes_below, "");
+                finished |= response.changed();
+                ui.end_row();
+
+                ui.label(t!("tab.inspector.line.inherit"))
+                    .on_hover_text(t!("tab.inspector.line.inherit_hint"));
+                ui.horizontal(|ui| {
+                    let inheritance = &mut line.inheritance;
+                    let rotation = t!("tab.inspector.line.inherit_rotation");
+                    finished |= ui.checkbox(&mut inheritance.rotation, rotation).changed();
+                    let opacity = t!("tab.inspector.line.inherit_opacity");
+                    finished |= ui.checkbox(&mut inheritance.opacity, opacity).changed();
+                    let speed = t!("tab.inspector.line.inherit_speed");
+                    finished |= ui.checkbox(&mut inheritance.speed, speed).changed();
+                    let scale = t!("tab.inspector.line.inherit_scale");
+                    finished |= ui.checkbox(&mut inheritance.scale, scale).changed();
+                });
+                ui.end_row();
+
                 finished
             });
 
             if let Some(from) = result {
-                if from != line.clone() {
-                    // TODO: write to history to support undo/redo
+                if from != *line {
+                    event_writer.send(DoCommandEvent(EditorCommand::EditLine(EditLine::new(
+                        entity,
+                        from,
+                        line.clone(),
+                    ))));
                 }
             }
         });
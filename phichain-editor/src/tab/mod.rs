@@ -5,6 +5,9 @@ pub mod inspector;
 pub mod line_list;
 pub mod quick_action;
 pub mod settings;
+pub mod source_map;
+pub mod statistics;
+pub mod time_signatures;
 pub mod timeline;
 pub mod timeline_setting;
 
@@ -13,6 +16,9 @@ use crate::tab::chart_basic_setting::chart_basic_setting_tab;
 use crate::tab::inspector::inspector_ui_system;
 use crate::tab::line_list::line_list_tab;
 use crate::tab::settings::settings_tab;
+use crate::tab::source_map::source_map_tab;
+use crate::tab::statistics::statistics_tab;
+use crate::tab::time_signatures::time_signatures_tab;
 use crate::tab::timeline::timeline_tab;
 use crate::tab::timeline_setting::timeline_setting_tab;
 use bevy::{prelude::*, utils::HashMap};
@@ -58,7 +64,10 @@ pub enum EditorTab {
     ChartBasicSetting,
     LineList,
     BpmList,
+    TimeSignatures,
     Settings,
+    Statistics,
+    SourceMap,
 }
 
 pub struct TabPlugin;
@@ -84,8 +93,19 @@ impl Plugin for TabPlugin {
                 chart_basic_setting_tab,
             )
             .register_tab(EditorTab::BpmList, "tab.bpm_list.title", bpm_list_tab)
+            .register_tab(
+                EditorTab::TimeSignatures,
+                "tab.time_signatures.title",
+                time_signatures_tab,
+            )
             .register_tab(EditorTab::LineList, "tab.line_list.title", line_list_tab)
-            .register_tab(EditorTab::Settings, "tab.settings.title", settings_tab);
+            .register_tab(EditorTab::Settings, "tab.settings.title", settings_tab)
+            .register_tab(
+                EditorTab::Statistics,
+                "tab.statistics.title",
+                statistics_tab,
+            )
+            .register_tab(EditorTab::SourceMap, "tab.source_map.title", source_map_tab);
     }
 }
 
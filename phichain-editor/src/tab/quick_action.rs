@@ -7,6 +7,7 @@ use bevy::prelude::*;
 use bevy_persistent::Persistent;
 use egui::{vec2, Ui};
 use phichain_chart::bpm_list::BpmList;
+use phichain_chart::time_signature::{BarPosition, TimeSignatureList};
 
 pub fn quick_action(ui: &mut Ui, world: &mut World) {
     let mut state: SystemState<(
@@ -14,11 +15,12 @@ pub fn quick_action(ui: &mut Ui, world: &mut World) {
         ResMut<ToastsStorage>,
         Res<ChartTime>,
         Res<BpmList>,
+        Res<TimeSignatureList>,
         Res<AudioDuration>,
         EventWriter<SeekToEvent>,
     )> = SystemState::new(world);
 
-    let (mut editor_settings, mut toasts, time, bpm_list, duration, mut events) =
+    let (mut editor_settings, mut toasts, time, bpm_list, time_signatures, duration, mut events) =
         state.get_mut(world);
 
     ui.horizontal(|ui| {
@@ -30,7 +32,7 @@ pub fn quick_action(ui: &mut Ui, world: &mut World) {
                 .speed(0.01),
         );
 
-        let space = ui.available_width() - 300.0;
+        let space = ui.available_width() - 370.0;
         if space > 0.0 {
             ui.add_space(space)
         }
@@ -98,6 +100,8 @@ pub fn quick_action(ui: &mut Ui, world: &mut World) {
         let mut second_binding = seconds;
         let beats = bpm_list.beat_at(seconds).value();
         let mut beat_binding = beats;
+        let bars = time_signatures.bar_at(beats);
+        let mut bar_binding = bars;
 
         ui.horizontal(|ui| {
             ui.add(
@@ -119,6 +123,23 @@ pub fn quick_action(ui: &mut Ui, world: &mut World) {
                     .custom_formatter(|x, _| format!("{:.2}", x))
                     .clamp_range(0.0..=max_beat.value()),
             );
+            ui.add_sized(
+                vec2(65.0, 18.0),
+                egui::DragValue::new(&mut bar_binding)
+                    .speed(0.01)
+                    .custom_formatter(|x, _| {
+                        let beat = time_signatures.beat_at_bar(x as f32);
+                        time_signatures.position(beat).to_string()
+                    })
+                    .custom_parser(|x| {
+                        let position = x.parse::<BarPosition>().ok()?;
+                        Some(
+                            time_signatures.bar_at(time_signatures.beat_at_position(position))
+                                as f64,
+                        )
+                    })
+                    .clamp_range(0.0..=time_signatures.bar_at(max_beat.value())),
+            );
         });
 
         if second_binding != seconds {
@@ -128,5 +149,10 @@ pub fn quick_action(ui: &mut Ui, world: &mut World) {
         if beat_binding != beats {
             events.send(SeekToEvent(bpm_list.time_at(beat_binding.into())));
         }
+
+        if bar_binding != bars {
+            let beat = time_signatures.beat_at_bar(bar_binding);
+            events.send(SeekToEvent(bpm_list.time_at(beat.into())));
+        }
     });
 }
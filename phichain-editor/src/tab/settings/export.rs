@@ -0,0 +1,50 @@
+use crate::settings::EditorSettings;
+use crate::tab::settings::SettingCategory;
+use crate::ui::latch;
+use bevy::prelude::World;
+use egui::Ui;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Export;
+
+impl SettingCategory for Export {
+    fn name(&self) -> &str {
+        "tab.settings.category.export.title"
+    }
+
+    fn ui(&self, ui: &mut Ui, settings: &mut EditorSettings, _world: &mut World) -> bool {
+        egui::Grid::new("export-settings-grid")
+            .num_columns(2)
+            .spacing([20.0, 2.0])
+            .striped(true)
+            .show(ui, |ui| {
+                latch::latch(ui, "export-settings", settings.export.clone(), |ui| {
+                    let mut finished = false;
+                    ui.label(t!("tab.settings.category.export.official_tolerance"));
+                    let response = ui.add(
+                        egui::DragValue::new(&mut settings.export.official_tolerance)
+                            .clamp_range(0.01..=100.0)
+                            .speed(0.01),
+                    );
+                    finished |= response.drag_stopped() || response.lost_focus();
+                    ui.end_row();
+
+                    ui.label(t!("tab.settings.category.export.source_map"))
+                        .on_hover_text(t!("tab.settings.category.export.source_map_hint"));
+                    let response = ui.checkbox(&mut settings.export.source_map, "");
+                    finished |= response.changed();
+                    ui.end_row();
+
+                    ui.label(t!("tab.settings.category.export.strict"))
+                        .on_hover_text(t!("tab.settings.category.export.strict_hint"));
+                    let response = ui.checkbox(&mut settings.export.strict, "");
+                    finished |= response.changed();
+                    ui.end_row();
+
+                    finished
+                })
+                .is_some()
+            })
+            .inner
+    }
+}
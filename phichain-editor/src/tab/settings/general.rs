@@ -93,6 +93,19 @@ impl SettingCategory for General {
                     finished |= changed;
                     ui.end_row();
 
+                    ui.label(t!("tab.settings.category.general.keep_note_times"));
+                    let response = ui.checkbox(&mut settings.general.keep_note_times, "");
+                    finished |= response.changed();
+                    ui.end_row();
+
+                    ui.label(t!("tab.settings.category.general.retime_density"));
+                    let response = ui.add(
+                        egui::DragValue::new(&mut settings.general.retime_density)
+                            .clamp_range(1..=192),
+                    );
+                    finished |= response.drag_stopped() || response.lost_focus();
+                    ui.end_row();
+
                     finished
                 })
                 .is_some()
@@ -1,10 +1,12 @@
 mod audio;
+mod export;
 mod game;
 mod general;
 
 use crate::notification::{ToastsExt, ToastsStorage};
 use crate::settings::EditorSettings;
 use crate::tab::settings::audio::Audio;
+use crate::tab::settings::export::Export;
 use crate::tab::settings::game::Game;
 use crate::tab::settings::general::General;
 use bevy::prelude::*;
@@ -25,6 +27,7 @@ enum SettingCategories {
     General,
     Audio,
     Game,
+    Export,
 }
 
 pub fn settings_tab(In(mut ui): In<Ui>, world: &mut World) {
@@ -0,0 +1,90 @@
+use crate::source_map::{parse_line_path, JumpToCompiledEvent};
+use bevy::prelude::*;
+use egui::Ui;
+use phichain_chart::lint::Location;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum CompiledItem {
+    Line,
+    Note,
+    #[default]
+    Event,
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMapTabState {
+    line: String,
+    item: CompiledItem,
+    index: usize,
+}
+
+pub fn source_map_tab(
+    In(mut ui): In<Ui>,
+    mut state: Local<SourceMapTabState>,
+    mut events: EventWriter<JumpToCompiledEvent>,
+) {
+    ui.label(t!("tab.source_map.description"));
+    ui.separator();
+
+    let path = parse_line_path(&state.line);
+
+    egui::Grid::new("source_map_grid")
+        .num_columns(2)
+        .spacing([20.0, 2.0])
+        .striped(true)
+        .show(&mut ui, |ui| {
+            ui.label(t!("tab.source_map.line"));
+            ui.add(
+                egui::TextEdit::singleline(&mut state.line)
+                    .hint_text("0")
+                    .text_color_opt(path.is_none().then_some(egui::Color32::LIGHT_RED)),
+            )
+            .on_hover_text(t!("tab.source_map.line_hint"));
+            ui.end_row();
+
+            ui.label(t!("tab.source_map.item"));
+            ui.horizontal(|ui| {
+                ui.selectable_value(
+                    &mut state.item,
+                    CompiledItem::Line,
+                    t!("tab.source_map.items.line"),
+                );
+                ui.selectable_value(
+                    &mut state.item,
+                    CompiledItem::Note,
+                    t!("tab.source_map.items.note"),
+                );
+                ui.selectable_value(
+                    &mut state.item,
+                    CompiledItem::Event,
+                    t!("tab.source_map.items.event"),
+                );
+            });
+            ui.end_row();
+
+            ui.label(t!("tab.source_map.index"));
+            ui.add_enabled(
+                state.item != CompiledItem::Line,
+                egui::DragValue::new(&mut state.index),
+            );
+            ui.end_row();
+        });
+
+    ui.add_space(5.0);
+
+    let Some(line) = path else {
+        ui.add_enabled(false, egui::Button::new(t!("tab.source_map.jump")))
+            .on_disabled_hover_text(t!("tab.source_map.invalid_line"));
+        return;
+    };
+
+    if ui.button(t!("tab.source_map.jump")).clicked() {
+        let index = state.index;
+        let location = match state.item {
+            CompiledItem::Line => Location::Line { line },
+            CompiledItem::Note => Location::Note { line, index },
+            CompiledItem::Event => Location::Event { line, index },
+        };
+        events.send(JumpToCompiledEvent(location));
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use egui::{Color32, Sense, Stroke, Ui};
+use phichain_chart::bpm_list::BpmList;
+use phichain_chart::event::LineEvent;
+use phichain_chart::line::Line;
+use phichain_chart::note::Note;
+use phichain_chart::statistics::{ChartStatistics, StatisticsOptions};
+
+/// Statistics of the chart, recomputed only when the chart changes
+#[derive(Debug, Default)]
+pub struct StatisticsCache {
+    statistics: ChartStatistics,
+    /// Numbers of notes, lines and events the statistics are computed with, to notice removals
+    counts: Option<(usize, usize, usize)>,
+}
+
+pub fn statistics_tab(
+    In(mut ui): In<Ui>,
+    mut cache: Local<StatisticsCache>,
+    bpm_list: Res<BpmList>,
+    note_query: Query<&Note>,
+    changed_note_query: Query<(), Changed<Note>>,
+    line_query: Query<(), With<Line>>,
+    event_query: Query<(), With<LineEvent>>,
+) {
+    let counts = (
+        note_query.iter().count(),
+        line_query.iter().count(),
+        event_query.iter().count(),
+    );
+    if cache.counts != Some(counts) || bpm_list.is_changed() || !changed_note_query.is_empty() {
+        cache.statistics = ChartStatistics::new(
+            &bpm_list,
+            &note_query,
+            counts.1,
+            counts.2,
+            StatisticsOptions::default(),
+        );
+        cache.counts = Some(counts);
+    }
+    let statistics = &cache.statistics;
+
+    egui::Grid::new("statistics_grid")
+        .num_columns(2)
+        .spacing([20.0, 2.0])
+        .striped(true)
+        .show(&mut ui, |ui| {
+            let notes = &statistics.notes;
+            let mut row = |label: &str, value: String| {
+                ui.label(t!(label));
+                ui.label(value);
+                ui.end_row();
+            };
+
+            row("tab.statistics.notes", notes.total().to_string());
+            row("tab.statistics.tap", notes.tap.to_string());
+            row("tab.statistics.drag", notes.drag.to_string());
+            row("tab.statistics.hold", notes.hold.to_string());
+            row("tab.statistics.flick", notes.flick.to_string());
+            row("tab.statistics.fake", notes.fake.to_string());
+            row("tab.statistics.lines", statistics.lines.to_string());
+            row("tab.statistics.events", statistics.events.to_string());
+            row(
+                "tab.statistics.hold_duration",
+                format!(
+                    "{:.2}s / {:.2}s",
+                    statistics.total_hold_duration, statistics.longest_hold_duration
+                ),
+            );
+            row(
+                "tab.statistics.peak_nps",
+                format!("{:.2}", statistics.peak_nps),
+            );
+            row(
+                "tab.statistics.average_nps",
+                format!("{:.2}", statistics.average_nps),
+            );
+            row(
+                "tab.statistics.simultaneous_notes",
+                format!(
+                    "{} ({})",
+                    statistics.simultaneous_notes, statistics.max_simultaneous_notes
+                ),
+            );
+            row(
+                "tab.statistics.difficulty",
+                format!("{:.2}", statistics.difficulty),
+            );
+        });
+
+    ui.separator();
+    ui.label(t!("tab.statistics.density"));
+    density_graph(&mut ui, statistics);
+}
+
+/// Draw notes per second of each sliding window as a bar graph
+fn density_graph(ui: &mut Ui, statistics: &ChartStatistics) {
+    let size = egui::vec2(ui.available_width(), 80.0);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let rect = response.rect;
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::DARK_GRAY));
+
+    if statistics.nps.is_empty() || statistics.peak_nps <= 0.0 {
+        return;
+    }
+
+    let width = rect.width() / statistics.nps.len() as f32;
+    for (index, nps) in statistics.nps.iter().enumerate() {
+        let height = nps / statistics.peak_nps * rect.height();
+        let x = rect.left() + index as f32 * width;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(x, rect.bottom() - height),
+                egui::pos2(x + width, rect.bottom()),
+            ),
+            0.0,
+            Color32::LIGHT_BLUE,
+        );
+    }
+}
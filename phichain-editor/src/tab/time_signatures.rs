@@ -0,0 +1,136 @@
+use crate::editing::command::time_signature::{
+    CreateTimeSignaturePoint, EditTimeSignaturePoint, RemoveTimeSignaturePoint,
+};
+use crate::editing::command::EditorCommand;
+use crate::editing::DoCommandEvent;
+use crate::ui::latch;
+use crate::ui::widgets::beat_value::BeatValue;
+use bevy::prelude::*;
+use egui::Ui;
+use phichain_chart::beat;
+use phichain_chart::beat::Beat;
+use phichain_chart::time_signature::{TimeSignatureList, TimeSignaturePoint};
+
+/// Note values available as the denominator of a time signature
+const DENOMINATORS: [u32; 6] = [1, 2, 4, 8, 16, 32];
+
+pub fn time_signatures_tab(
+    In(mut ui): In<Ui>,
+    mut time_signatures: ResMut<TimeSignatureList>,
+    mut event_writer: EventWriter<DoCommandEvent>,
+) {
+    let mut deletes = Vec::new();
+
+    for index in 0..time_signatures.0.len() {
+        let previous_beat = (index > 0)
+            .then(|| time_signatures.0.get(index - 1).map(|x| x.beat))
+            .flatten();
+        let next_beat = time_signatures.0.get(index + 1).map(|x| x.beat);
+        let point = time_signatures.0.get_mut(index).unwrap();
+
+        ui.horizontal_top(|ui| {
+            egui::Grid::new(format!("time_signature_grid_{}", index))
+                .num_columns(2)
+                .spacing([20.0, 2.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    let result =
+                        latch::latch(ui, format!("time_signature_{}", index), *point, |ui| {
+                            let mut finished = false;
+
+                            ui.label(t!("tab.time_signatures.point.beat"));
+                            ui.add_enabled_ui(point.beat != Beat::ZERO, |ui| {
+                                let start = previous_beat
+                                    .map(|x| x + beat!(0, 1, 32))
+                                    .unwrap_or(Beat::MIN);
+                                let end =
+                                    next_beat.map(|x| x - beat!(0, 1, 32)).unwrap_or(Beat::MAX);
+                                let range = start..=end;
+                                let response = ui
+                                    .add(BeatValue::new(&mut point.beat).clamp_range(range))
+                                    .on_disabled_hover_text(t!(
+                                        "tab.time_signatures.zero_beat_not_editable"
+                                    ));
+                                finished |= response.drag_stopped() || response.lost_focus();
+                            });
+                            ui.end_row();
+
+                            ui.label(t!("tab.time_signatures.point.signature"));
+                            ui.horizontal(|ui| {
+                                let response = ui.add(
+                                    egui::DragValue::new(&mut point.numerator).clamp_range(1..=64),
+                                );
+                                finished |= response.drag_stopped() || response.lost_focus();
+                                ui.label("/");
+                                egui::ComboBox::from_id_source(format!(
+                                    "time_signature_denominator_{}",
+                                    index
+                                ))
+                                .width(40.0)
+                                .selected_text(point.denominator.to_string())
+                                .show_ui(ui, |ui| {
+                                    for denominator in DENOMINATORS {
+                                        if ui
+                                            .selectable_value(
+                                                &mut point.denominator,
+                                                denominator,
+                                                denominator.to_string(),
+                                            )
+                                            .clicked()
+                                        {
+                                            finished = true;
+                                        }
+                                    }
+                                });
+                            });
+                            ui.end_row();
+
+                            finished
+                        });
+
+                    if let Some(from) = result {
+                        if from != *point {
+                            event_writer.send(DoCommandEvent(
+                                EditorCommand::EditTimeSignaturePoint(EditTimeSignaturePoint::new(
+                                    index, from, *point,
+                                )),
+                            ));
+                        }
+                    }
+                });
+
+            ui.add_space(10.0);
+            ui.add_enabled_ui(point.beat != Beat::ZERO, |ui| {
+                if ui
+                    .button(" × ")
+                    .on_disabled_hover_text(t!("tab.time_signatures.zero_beat_not_editable"))
+                    .clicked()
+                {
+                    deletes.push(index);
+                }
+            });
+        });
+
+        ui.separator();
+    }
+
+    if ui.button(t!("tab.time_signatures.new")).clicked() {
+        // starts a new time signature at the bar after the last time signature point
+        let last = *time_signatures.0.last().expect("No time signature points");
+        let beat = last.beat + Beat::from(last.bar_length());
+        event_writer.send(DoCommandEvent(EditorCommand::CreateTimeSignaturePoint(
+            CreateTimeSignaturePoint::new(TimeSignaturePoint::new(
+                beat,
+                last.numerator,
+                last.denominator,
+            )),
+        )));
+    }
+
+    // remove from the back, so indexes of the remaining points are still valid
+    for index in deletes.into_iter().rev() {
+        event_writer.send(DoCommandEvent(EditorCommand::RemoveTimeSignaturePoint(
+            RemoveTimeSignaturePoint::new(index),
+        )));
+    }
+}
@@ -9,8 +9,9 @@ use bevy::hierarchy::Parent;
 use bevy::prelude::{Entity, EventWriter, Query, Res, World};
 use egui::{Align2, Color32, FontId, Rangef, Rect, Sense, Stroke, Ui};
 use phichain_chart::bpm_list::BpmList;
-use phichain_chart::event::LineEvent;
+use phichain_chart::event::{unpack_color, LineEvent, LineEventKind};
 use std::iter;
+use strum::EnumCount;
 
 #[derive(Debug, Clone)]
 pub struct EventTimeline(pub Option<Entity>);
@@ -60,13 +61,13 @@ impl Timeline for EventTimeline {
             }
 
             let track: u8 = event.kind.into();
+            let track_width = viewport.width() / LineEventKind::COUNT as f32;
 
-            let x = viewport.width() / 5.0 * track as f32 - viewport.width() / 5.0 / 2.0
-                + viewport.min.x;
+            let x = track_width * track as f32 - track_width / 2.0 + viewport.min.x;
             let y = ctx.time_to_y(bpm_list.time_at(event.start_beat));
 
             let size = egui::Vec2::new(
-                viewport.width() / 8000.0 * 989.0,
+                track_width * 0.6,
                 y - ctx.time_to_y(bpm_list.time_at(event.end_beat)),
             );
 
@@ -109,7 +110,9 @@ impl Timeline for EventTimeline {
                         .on_hover_and_drag_cursor(egui::CursorIcon::ResizeVertical);
 
                     if response.drag_started() {
-                        ui.data_mut(|data| data.insert_temp(egui::Id::new("event-drag"), *event));
+                        ui.data_mut(|data| {
+                            data.insert_temp(egui::Id::new("event-drag"), event.clone())
+                        });
                     }
 
                     if response.dragged() {
@@ -140,7 +143,7 @@ impl Timeline for EventTimeline {
                             event.end_beat = ctx.settings.attach(event.end_beat.value());
                         }
                         if from != *event {
-                            on_event_change(from, *event);
+                            on_event_change(from, event.clone());
                         }
                     }
                 };
@@ -151,14 +154,14 @@ impl Timeline for EventTimeline {
                 ui.painter().text(
                     rect.center_top(),
                     Align2::CENTER_TOP,
-                    event.value.end(),
+                    format_value(&event, event.end_value()),
                     FontId::default(),
                     Color32::DARK_GREEN,
                 );
                 ui.painter().text(
                     rect.center_bottom(),
                     Align2::CENTER_BOTTOM,
-                    event.value.start(),
+                    format_value(&event, event.start_value()),
                     FontId::default(),
                     Color32::DARK_GREEN,
                 );
@@ -169,11 +172,11 @@ impl Timeline for EventTimeline {
             }
         }
 
-        // [0.2, 0.4, 0.6, 0.8]
+        // [1/n, 2/n, ..., (n-1)/n]
         let lane_percents = iter::repeat(0.0)
-            .take(5 - 1)
+            .take(LineEventKind::COUNT - 1)
             .enumerate()
-            .map(|(i, _)| (i + 1) as f32 * 1.0 / 5.0)
+            .map(|(i, _)| (i + 1) as f32 * 1.0 / LineEventKind::COUNT as f32)
             .collect::<Vec<_>>();
         for percent in lane_percents {
             ui.painter().rect_filled(
@@ -206,7 +209,8 @@ impl Timeline for EventTimeline {
             .filter(|x| {
                 let event = x.0;
                 let track: u8 = event.kind.into();
-                let target_x = (track - 1) as f32 * (1.0 / 5.0) + (1.0 / (5.0 * 2.0));
+                let tracks = LineEventKind::COUNT as f32;
+                let target_x = (track - 1) as f32 * (1.0 / tracks) + (1.0 / (tracks * 2.0));
                 x_range.contains(target_x * viewport.width())
                     && time_range.contains(bpm_list.time_at(event.start_beat))
             })
@@ -214,3 +218,15 @@ impl Timeline for EventTimeline {
             .collect()
     }
 }
+
+/// Format a value of the event to display on the timeline
+fn format_value(event: &LineEvent, value: f32) -> String {
+    match event.kind {
+        LineEventKind::Color => {
+            let [r, g, b] = unpack_color(value);
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        }
+        LineEventKind::Text => event.value.text_value().unwrap_or_default().to_owned(),
+        _ => value.to_string(),
+    }
+}
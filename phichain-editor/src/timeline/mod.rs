@@ -22,6 +22,7 @@ use phichain_chart::beat;
 use phichain_chart::beat::Beat;
 use phichain_chart::bpm_list::BpmList;
 use phichain_chart::line::Line;
+use phichain_chart::time_signature::{BarBeat, TimeSignatureList};
 
 pub struct TimelinePlugin;
 
@@ -50,6 +51,7 @@ impl Plugin for TimelinePlugin {
 #[derive(SystemParam)]
 pub struct TimelineContext<'w> {
     bpm_list: Res<'w, BpmList>,
+    time_signatures: Res<'w, TimeSignatureList>,
     pub settings: ResMut<'w, TimelineSettings>,
     current_time: Res<'w, ChartTime>,
     pub viewport: Res<'w, TimelineViewport>,
@@ -58,11 +60,13 @@ pub struct TimelineContext<'w> {
 }
 
 impl<'w> TimelineContext<'w> {
-    pub fn primary_beat_times(&self) -> Vec<f32> {
-        std::iter::repeat(0)
-            .enumerate()
-            .map(|(i, _)| self.bpm_list.time_at(Beat::from(i as f32)))
-            .take_while(|x| x <= &self.audio_duration.0.as_secs_f32())
+    /// Beats of the time signatures with their times, until the end of the audio
+    pub fn primary_beats(&self) -> Vec<(f32, BarBeat)> {
+        let end = self.bpm_list.beat_at(self.audio_duration.0.as_secs_f32());
+        self.time_signatures
+            .bar_beats(end)
+            .into_iter()
+            .map(|x| (self.bpm_list.time_at(x.beat), x))
             .collect()
     }
 
@@ -140,24 +144,31 @@ pub mod common {
     pub fn beat_line_ui(ui: &mut Ui, world: &mut World) {
         let mut state: SystemState<TimelineContext> = SystemState::new(world);
         let ctx = state.get_mut(world);
-        for (index, beat_time) in ctx.primary_beat_times().iter().enumerate() {
+        for (beat_time, beat) in ctx.primary_beats() {
+            // downbeats are emphasised and numbered with their bars
+            let downbeat = beat.index == 0;
             let rect = egui::Rect::from_center_size(
                 egui::Pos2::new(
                     ctx.viewport.0.width() / 2.0 + ctx.viewport.0.min.x,
-                    ctx.time_to_y(*beat_time),
+                    ctx.time_to_y(beat_time),
                 ),
-                egui::Vec2::new(ctx.viewport.0.width(), 2.0),
+                egui::Vec2::new(ctx.viewport.0.width(), if downbeat { 3.0 } else { 2.0 }),
             );
             ui.painter().rect_filled(
                 rect,
                 0.0,
-                Color32::from_rgba_unmultiplied(255, 255, 255, 40),
+                Color32::from_rgba_unmultiplied(255, 255, 255, if downbeat { 100 } else { 40 }),
             );
+            let (text, size) = if downbeat {
+                ((beat.bar + 1).to_string(), 14.0)
+            } else {
+                (format!("{}.{}", beat.bar + 1, beat.index + 1), 10.0)
+            };
             ui.painter().text(
                 rect.left_top() + egui::Vec2::new(4.0, 0.0),
                 Align2::LEFT_BOTTOM,
-                index,
-                FontId::monospace(14.0),
+                text,
+                FontId::monospace(size),
                 Color32::WHITE,
             );
         }
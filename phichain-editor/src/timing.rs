@@ -2,6 +2,7 @@ use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
 use bevy_persistent::Persistent;
 use phichain_chart::bpm_list::BpmList;
+use phichain_chart::time_signature::TimeSignatureList;
 
 use crate::action::ActionRegistrationExt;
 use crate::hotkey::HotkeyRegistrationExt;
@@ -54,7 +55,11 @@ impl Plugin for TimingPlugin {
                 scroll_progress_control_system.run_if(project_loaded()),
             )
             .register_action("phichain.toggle", toggle_system)
-            .register_hotkey("phichain.toggle", vec![KeyCode::Space]);
+            .register_hotkey("phichain.toggle", vec![KeyCode::Space])
+            .register_action("phichain.previous_bar", previous_bar_system)
+            .register_hotkey("phichain.previous_bar", vec![KeyCode::PageUp])
+            .register_action("phichain.next_bar", next_bar_system)
+            .register_hotkey("phichain.next_bar", vec![KeyCode::PageDown]);
     }
 }
 
@@ -105,6 +110,39 @@ fn scroll_progress_control_system(
     }
 }
 
+/// Tolerance in bars when seeking by bars, so seeking from the start of a bar is not affected by float errors
+const BAR_SEEK_TOLERANCE: f32 = 1e-3;
+
+/// Seek to the start of the current bar, or the previous bar if already at the start of a bar
+fn previous_bar_system(
+    time: Res<ChartTime>,
+    bpm_list: Option<Res<BpmList>>,
+    time_signatures: Option<Res<TimeSignatureList>>,
+    mut events: EventWriter<SeekToEvent>,
+) {
+    let (Some(bpm_list), Some(time_signatures)) = (bpm_list, time_signatures) else {
+        return;
+    };
+    let bar = time_signatures.bar_at(bpm_list.beat_at_f32(time.0)) - BAR_SEEK_TOLERANCE;
+    let target = time_signatures.bar_start(bar.max(0.0).floor() as u32);
+    events.send(SeekToEvent(bpm_list.time_at(target)));
+}
+
+/// Seek to the start of the next bar
+fn next_bar_system(
+    time: Res<ChartTime>,
+    bpm_list: Option<Res<BpmList>>,
+    time_signatures: Option<Res<TimeSignatureList>>,
+    mut events: EventWriter<SeekToEvent>,
+) {
+    let (Some(bpm_list), Some(time_signatures)) = (bpm_list, time_signatures) else {
+        return;
+    };
+    let bar = time_signatures.bar_at(bpm_list.beat_at_f32(time.0)) + BAR_SEEK_TOLERANCE;
+    let target = time_signatures.bar_start(bar.max(0.0).floor() as u32 + 1);
+    events.send(SeekToEvent(bpm_list.time_at(target)));
+}
+
 fn compute_bpm_list_system(mut bpm_list: ResMut<BpmList>) {
     bpm_list.compute();
 }
@@ -126,6 +126,34 @@ impl<'a> Widget for EasingValue<'a> {
                 response.drag_stopped |= drag_stopped;
             }
 
+            if let Easing::Partial(kind, left, right) = self.value {
+                let mut left_ = *left;
+                let mut right_ = *right;
+
+                ui.add_space(4.0);
+
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::DragValue::new(&mut left_)
+                            .speed(0.01)
+                            .clamp_range(0.0..=1.0),
+                    );
+                    drag_stopped |= response.drag_stopped() || response.lost_focus();
+                    let response = ui.add(
+                        egui::DragValue::new(&mut right_)
+                            .speed(0.01)
+                            .clamp_range(0.0..=1.0),
+                    );
+                    drag_stopped |= response.drag_stopped() || response.lost_focus();
+                });
+
+                if left_ != *left || right_ != *right {
+                    *self.value = Easing::Partial(*kind, left_, right_);
+                }
+
+                response.drag_stopped |= drag_stopped;
+            }
+
             response
         })
         .inner
@@ -0,0 +1,301 @@
+//! Export Phichain projects into distributable packages, shared by the editor and the converter
+//!
+//! A package is a zip containing the compiled chart, the music, the illustration, the line textures and an `info.txt` describing the chart
+
+use anyhow::Context;
+use phichain_chart::format::official::{OfficialChart, OfficialExportOptions};
+use phichain_chart::format::rpe::RpeChart;
+use phichain_chart::migration::migrate;
+use phichain_chart::primitive::line::Line;
+use phichain_chart::primitive::Format;
+use phichain_chart::project::Project;
+use phichain_chart::report::ConversionReport;
+use phichain_chart::serialization::PhichainChart;
+use phichain_compiler::source_map::SourceMap;
+use phichain_compiler::steps::MergeChildrenLine;
+use phichain_compiler::Pipeline;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Seek, Write};
+use std::path::Path;
+use strum::Display;
+use tracing::debug;
+use zip::write::SimpleFileOptions;
+
+/// Name of the chart file inside a package
+pub const CHART_FILENAME: &str = "chart.json";
+
+/// Chart formats a package can be exported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum PackageFormat {
+    #[default]
+    Official,
+    Rpe,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: PackageFormat,
+    /// Options for [`PackageFormat::Official`], ignored by other formats
+    pub official: OfficialExportOptions,
+    /// Build a source map linking the compiled chart back to the project chart
+    pub source_map: bool,
+    /// Fail instead of writing the package if the export is lossy
+    pub strict: bool,
+}
+
+#[derive(Debug)]
+pub struct ExportOutput {
+    /// Lossy transformations made by the compilation and the conversion into the package format
+    pub report: ConversionReport,
+    /// Indexed by locations in the compiled primitive chart rather than the packaged chart, see [`SourceMap`]
+    ///
+    /// Only built if [`ExportOptions::source_map`] is enabled
+    pub source_map: Option<SourceMap>,
+}
+
+/// Compile the chart of a project and write it into a package at the given path, along with its music, illustration, line textures and info
+///
+/// The package is not written if [`ExportOptions::strict`] is enabled and the export is lossy
+pub fn export_project(
+    project: &Project,
+    path: &Path,
+    options: &ExportOptions,
+) -> anyhow::Result<ExportOutput> {
+    let (chart, output) = compile_project(project, options)?;
+
+    let file =
+        fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    write_package(file, project, &chart)?;
+
+    Ok(output)
+}
+
+/// A chart compiled and serialized in the package format
+struct PackageChart {
+    content: String,
+    /// Paths of the custom line textures used by the chart, relative to the project directory
+    textures: BTreeSet<String>,
+}
+
+/// Compile the chart of a project and serialize it in the package format
+fn compile_project(
+    project: &Project,
+    options: &ExportOptions,
+) -> anyhow::Result<(PackageChart, ExportOutput)> {
+    let chart_file = fs::File::open(project.path.chart_path()).context("Failed to open chart")?;
+    let chart: serde_json::Value = serde_json::from_reader(chart_file)?;
+    let migrated = migrate(&chart).context("Migration failed")?;
+    let chart: PhichainChart =
+        serde_json::from_value(migrated).context("Failed to deserialize chart")?;
+
+    let mut pipeline = Pipeline::default();
+    // RPE keeps the line hierarchy as the `father` of lines
+    if options.format == PackageFormat::Rpe {
+        pipeline.set_enabled(MergeChildrenLine::NAME, false)?;
+    }
+
+    let compiled = if options.source_map {
+        pipeline.compile_with_source_map(chart)?
+    } else {
+        pipeline.compile(chart)?
+    };
+    for timing in &compiled.timings {
+        debug!("Compile step `{}` took {:?}", timing.name, timing.duration);
+    }
+
+    let mut report = compiled.report;
+    let chart = match options.format {
+        PackageFormat::Official => {
+            // textures are dropped by the official format
+            let official = OfficialChart::from_primitive_with_options(
+                compiled.chart,
+                options.official,
+                &mut report,
+            )?;
+            PackageChart {
+                content: serde_json::to_string(&official)?,
+                textures: BTreeSet::new(),
+            }
+        }
+        PackageFormat::Rpe => {
+            let mut textures = BTreeSet::new();
+            collect_textures(&compiled.chart.lines, &mut textures);
+            let rpe = RpeChart::from_primitive_with_report(compiled.chart, &mut report)?;
+            PackageChart {
+                content: serde_json::to_string(&rpe)?,
+                textures,
+            }
+        }
+    };
+    if options.strict {
+        report.ensure_lossless()?;
+    }
+
+    Ok((
+        chart,
+        ExportOutput {
+            report,
+            source_map: compiled.source_map,
+        },
+    ))
+}
+
+fn collect_textures(lines: &[Line], textures: &mut BTreeSet<String>) {
+    for line in lines {
+        if let Some(texture) = &line.line.texture {
+            textures.insert(texture.clone());
+        }
+        collect_textures(&line.children, textures);
+    }
+}
+
+fn write_package<W: Write + Seek>(
+    writer: W,
+    project: &Project,
+    chart: &PackageChart,
+) -> anyhow::Result<()> {
+    let mut zip = zip::ZipWriter::new(writer);
+
+    zip.start_file(CHART_FILENAME, SimpleFileOptions::default())?;
+    zip.write_all(chart.content.as_bytes())?;
+
+    // textures are referenced by their paths relative to the project directory, which is the root of the package
+    for texture in &chart.textures {
+        let content = fs::read(project.path.sub_path(texture))
+            .with_context(|| format!("Failed to read texture {}", texture))?;
+        zip.start_file(texture.as_str(), SimpleFileOptions::default())?;
+        zip.write_all(&content)?;
+    }
+
+    let illustration = match project.path.illustration_path() {
+        Some(illustration_path) => {
+            let filename = filename(&illustration_path).context("Invalid illustration filename")?;
+            zip.start_file(filename, SimpleFileOptions::default())?;
+            zip.write_all(&fs::read(&illustration_path).context("Failed to read illustration")?)?;
+            Some(filename.to_owned())
+        }
+        None => None,
+    };
+
+    let music = match project.path.music_path() {
+        Some(music_path) => {
+            let filename = filename(&music_path).context("Invalid music filename")?;
+            zip.start_file(filename, SimpleFileOptions::default())?;
+            zip.write_all(&fs::read(&music_path).context("Failed to read music")?)?;
+            Some(filename.to_owned())
+        }
+        None => None,
+    };
+
+    zip.start_file("info.txt", SimpleFileOptions::default())?;
+    zip.write_all(info(project, music.as_deref(), illustration.as_deref()).as_bytes())?;
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+fn filename(path: &Path) -> Option<&str> {
+    path.file_name()?.to_str()
+}
+
+/// The `info.txt` of a package, read by RPE and most simulators
+fn info(project: &Project, music: Option<&str>, illustration: Option<&str>) -> String {
+    let meta = &project.meta;
+    let mut info = format!("#\nName: {}\n", meta.name);
+    if let Some(music) = music {
+        info.push_str(&format!("Song: {}\n", music));
+    }
+    if let Some(illustration) = illustration {
+        info.push_str(&format!("Picture: {}\n", illustration));
+    }
+    info.push_str(&format!(
+        "Chart: {}\nLevel: {}\nComposer: {}\nIllustrator: {}\nCharter: {}\n",
+        CHART_FILENAME, meta.level, meta.composer, meta.illustrator, meta.charter
+    ));
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phichain_chart::project::ProjectMeta;
+    use phichain_chart::serialization::LineWrapper;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_export_project() {
+        let root = std::env::temp_dir().join(format!("phichain-export-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let mut chart = PhichainChart::default();
+        let mut child = LineWrapper::default();
+        child.line.texture = Some("textures/arrow.png".to_owned());
+        chart.lines[0].children.push(child);
+        fs::create_dir_all(root.join("textures")).unwrap();
+        fs::write(root.join("textures/arrow.png"), b"texture").unwrap();
+        fs::write(
+            root.join("chart.json"),
+            serde_json::to_string(&chart).unwrap(),
+        )
+        .unwrap();
+        fs::write(root.join("music.ogg"), b"music").unwrap();
+        let meta = ProjectMeta {
+            name: "Name".to_owned(),
+            level: "IN Lv.15".to_owned(),
+            ..Default::default()
+        };
+        fs::write(
+            root.join("meta.json"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        let project = Project::load(root.clone()).unwrap();
+
+        let options = ExportOptions {
+            format: PackageFormat::Rpe,
+            ..Default::default()
+        };
+        let (chart, _) = compile_project(&project, &options).unwrap();
+        let mut package = Cursor::new(vec![]);
+        write_package(&mut package, &project, &chart).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let mut zip = zip::ZipArchive::new(package).unwrap();
+        let mut names = zip.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["chart.json", "info.txt", "music.ogg", "textures/arrow.png"]
+        );
+
+        let mut content = String::new();
+        zip.by_name("chart.json")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        let rpe: RpeChart = serde_json::from_str(&content).unwrap();
+        assert!(rpe.into_primitive().is_ok());
+        // the child line is kept as a line with a father
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let fathers = json["judgeLineList"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|line| line["father"].as_i64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(fathers, vec![-1, 0]);
+
+        let mut info = String::new();
+        zip.by_name("info.txt")
+            .unwrap()
+            .read_to_string(&mut info)
+            .unwrap();
+        assert_eq!(
+            info,
+            "#\nName: Name\nSong: music.ogg\nChart: chart.json\nLevel: IN Lv.15\nComposer: \nIllustrator: \nCharter: \n"
+        );
+    }
+}
@@ -1,18 +1,24 @@
-use bevy::{prelude::*, sprite::Anchor};
+use bevy::{prelude::*, render::render_asset::RenderAssetUsages, sprite::Anchor};
 use num::{FromPrimitive, Rational32};
 use phichain_assets::ImageAssets;
 use phichain_chart::bpm_list::BpmList;
 use phichain_chart::constants::{CANVAS_HEIGHT, CANVAS_WIDTH};
-use phichain_chart::event::{EventEvaluationResult, LineEvent, LineEventKind};
-use phichain_chart::line::{Line, LineOpacity, LinePosition, LineRotation};
+use phichain_chart::event::{
+    unpack_color, EventEvaluationResult, LineEvent, LineEventKind, LineEventValue,
+};
+use phichain_chart::line::{
+    Line, LineColor, LineOpacity, LinePosition, LineRotation, LineScale, LineText,
+};
 
 use crate::constants::PERFECT_COLOR;
 use crate::highlight::Highlighted;
-use crate::layer::{HOLD_LAYER, NOTE_LAYER};
+use crate::inheritance::LineInheritanceQuery;
+use crate::layer::{HOLD_LAYER, LINE_LAYER, LINE_Z_ORDER_STEP, MAX_LINE_Z_ORDER, NOTE_LAYER};
 use crate::scale::NoteScale;
 use crate::{ChartTime, GameConfig, GameSet, GameViewport};
 use phichain_chart::line::LineSpeed;
 use phichain_chart::note::{Note, NoteKind};
+use phichain_chart::project::Project;
 
 pub struct CoreGamePlugin;
 
@@ -26,19 +32,28 @@ impl Plugin for CoreGamePlugin {
                 update_note_system,
                 update_note_y_system,
                 update_note_texture_system,
+                update_note_alpha_system,
             )
                 .chain()
                 .in_set(GameSet),
         )
         .add_systems(
             Update,
-            (compute_line_system, update_line_system)
+            (
+                compute_line_system,
+                update_line_system,
+                update_line_text_system,
+            )
                 .chain()
                 .in_set(GameSet),
         )
         .add_systems(
             Update,
-            (update_line_texture_system, update_note_texture_system).in_set(GameSet),
+            (
+                (load_line_texture_system, update_line_texture_system).chain(),
+                update_note_texture_system,
+            )
+                .in_set(GameSet),
         )
         .add_systems(Update, calculate_speed_events_system.in_set(GameSet))
         // hold components
@@ -50,6 +65,7 @@ impl Plugin for CoreGamePlugin {
                     // otherwise heads & tails will keep twitching
                     .after(update_note_y_system),
                 update_hold_component_texture_system,
+                update_hold_component_alpha_system.after(update_note_alpha_system),
                 hide_hold_head_system,
                 despawn_hold_component_system,
             )
@@ -59,12 +75,13 @@ impl Plugin for CoreGamePlugin {
 }
 
 pub fn update_note_scale_system(
-    mut query: Query<&mut Transform, With<Note>>,
+    mut query: Query<(&mut Transform, &Note)>,
     game_viewport: Res<GameViewport>,
     note_scale: Res<NoteScale>,
 ) {
-    for mut transform in &mut query {
-        transform.scale = Vec3::splat(note_scale.0 / (game_viewport.0.width() * 3.0 / 1920.0))
+    for (mut transform, note) in &mut query {
+        transform.scale = Vec3::splat(note_scale.0 / (game_viewport.0.width() * 3.0 / 1920.0));
+        transform.scale.x *= note.size;
     }
 }
 
@@ -88,7 +105,9 @@ pub fn update_note_system(
             NoteKind::Hold { hold_beat } => hold_beat.value(),
             _ => 0.0,
         };
-        *visibility = if note.beat.value() + hold_beat < beat.into() {
+        *visibility = if note.beat.value() + hold_beat < beat.into()
+            || !note.is_visible_at(time.0, bpm_list.time_at(note.beat))
+        {
             Visibility::Hidden
         } else {
             Visibility::Visible
@@ -105,6 +124,9 @@ pub fn compute_line_system(
             &mut LineRotation,
             &mut LineOpacity,
             &mut LineSpeed,
+            &mut LineScale,
+            &mut LineColor,
+            &mut LineText,
             &Children,
         ),
         With<Line>,
@@ -113,12 +135,26 @@ pub fn compute_line_system(
     bpm_list: Res<BpmList>,
 ) {
     let beat: f32 = bpm_list.beat_at(time.0).into();
-    for (mut position, mut rotation, mut opacity, mut speed, children) in &mut line_query {
+    for (
+        mut position,
+        mut rotation,
+        mut opacity,
+        mut speed,
+        mut scale,
+        mut color,
+        mut text,
+        children,
+    ) in &mut line_query
+    {
         let mut x_value = EventEvaluationResult::Unaffected;
         let mut y_value = EventEvaluationResult::Unaffected;
         let mut rotation_value = EventEvaluationResult::Unaffected;
         let mut opacity_value = EventEvaluationResult::Unaffected;
         let mut speed_value = EventEvaluationResult::Unaffected;
+        let mut scale_x_value = EventEvaluationResult::Unaffected;
+        let mut scale_y_value = EventEvaluationResult::Unaffected;
+        let mut color_value = EventEvaluationResult::Unaffected;
+        let mut text_value = (EventEvaluationResult::Unaffected, None);
 
         for event in children.iter().filter_map(|x| event_query.get(*x).ok()) {
             let value = event.evaluate(beat);
@@ -128,6 +164,15 @@ pub fn compute_line_system(
                 LineEventKind::Rotation => rotation_value = rotation_value.max(value),
                 LineEventKind::Opacity => opacity_value = opacity_value.max(value),
                 LineEventKind::Speed => speed_value = speed_value.max(value),
+                LineEventKind::ScaleX => scale_x_value = scale_x_value.max(value),
+                LineEventKind::ScaleY => scale_y_value = scale_y_value.max(value),
+                LineEventKind::Color => color_value = color_value.max(value),
+                LineEventKind::Text => {
+                    // text events always evaluate to 0.0, pick the text the same way as `max`
+                    if value.value().is_some() && value >= text_value.0 {
+                        text_value = (value, event.value.text_value());
+                    }
+                }
             }
         }
 
@@ -148,70 +193,179 @@ pub fn compute_line_system(
         if let Some(speed_value) = speed_value.value() {
             speed.0 = speed_value;
         }
+        // unlike the transform, scale, color and text fall back to defaults without events
+        scale.0 = Vec2::new(
+            scale_x_value.value().unwrap_or(1.0),
+            scale_y_value.value().unwrap_or(1.0),
+        );
+        color.0 = color_value.value().map(|value| {
+            let [r, g, b] = unpack_color(value);
+            Color::rgb_u8(r, g, b)
+        });
+        if text.0.as_deref() != text_value.1 {
+            text.0 = text_value.1.map(ToOwned::to_owned);
+        }
     }
 }
 
 pub fn update_line_system(
     mut line_query: Query<
         (
+            Entity,
+            &Line,
             &LinePosition,
-            &LineRotation,
-            &LineOpacity,
+            &LineColor,
+            &LineText,
+            &Handle<Image>,
             &mut Transform,
             &mut Sprite,
             Option<&Parent>,
         ),
         With<Line>,
     >,
+    inheritance: LineInheritanceQuery,
     game_viewport: Res<GameViewport>,
+    images: Res<Assets<Image>>,
 
     config: Res<GameConfig>,
 ) {
-    for (position, rotation, opacity, mut transform, mut sprite, parent) in &mut line_query {
+    for (entity, line, position, color, text, image, mut transform, mut sprite, parent) in
+        &mut line_query
+    {
         let scale = game_viewport.0.width() * 3.0 / 1920.0;
         transform.scale = Vec3::splat(if parent.is_some() { 1.0 } else { scale });
         transform.translation.x = position.0.x / CANVAS_WIDTH * game_viewport.0.width()
             / if parent.is_some() { scale } else { 1.0 };
         transform.translation.y = position.0.y / CANVAS_HEIGHT * game_viewport.0.height()
             / if parent.is_some() { scale } else { 1.0 };
-        transform.rotation = Quat::from_rotation_z(rotation.0);
-
-        sprite.color = if config.fc_ap_indicator {
-            PERFECT_COLOR
+        transform.rotation = Quat::from_rotation_z(inheritance.local_rotation(entity));
+        transform.translation.z = if parent.is_some() { 0.0 } else { LINE_LAYER }
+            + line.z_order.clamp(-MAX_LINE_Z_ORDER, MAX_LINE_Z_ORDER) as f32 * LINE_Z_ORDER_STEP;
+
+        // scale the sprite instead of the transform, otherwise notes and child lines are scaled as well
+        sprite.custom_size = images
+            .get(image)
+            .map(|image| image.size_f32() * inheritance.scale(entity));
+
+        // text lines are rendered by the text entity instead of the texture
+        let alpha = if text.0.is_some() {
+            0.0
         } else {
-            Color::WHITE
+            inheritance.opacity(entity)
+        };
+        sprite.color = match color.0 {
+            Some(color) => color,
+            None if config.fc_ap_indicator => PERFECT_COLOR,
+            None => Color::WHITE,
+        }
+        .with_a(alpha);
+    }
+}
+
+/// Marker component for the text entity of a text line
+#[derive(Debug, Component)]
+pub struct LineTextMarker;
+
+/// Font size of text lines before applying line scale
+const LINE_TEXT_FONT_SIZE: f32 = 40.0;
+
+/// Spawn, update and despawn the text entities of text lines
+pub fn update_line_text_system(
+    mut commands: Commands,
+    line_query: Query<(Entity, &LineText, &LineColor, Option<&Children>), With<Line>>,
+    inheritance: LineInheritanceQuery,
+    mut text_query: Query<(&mut Text, &mut Transform), With<LineTextMarker>>,
+) {
+    for (entity, line_text, color, children) in &line_query {
+        let scale = inheritance.scale(entity);
+        let opacity = inheritance.opacity(entity);
+        let text_entity = children.and_then(|children| {
+            children
+                .iter()
+                .find(|child| text_query.contains(**child))
+                .copied()
+        });
+        let style = TextStyle {
+            font_size: LINE_TEXT_FONT_SIZE,
+            color: color.0.unwrap_or(Color::WHITE).with_a(opacity),
+            ..default()
+        };
+
+        match (&line_text.0, text_entity) {
+            (Some(content), Some(text_entity)) => {
+                if let Ok((mut text, mut transform)) = text_query.get_mut(text_entity) {
+                    if text.sections[0].value != *content {
+                        text.sections[0].value.clone_from(content);
+                    }
+                    text.sections[0].style.color = style.color;
+                    transform.scale = scale.extend(1.0);
+                }
+            }
+            (Some(content), None) => {
+                commands.entity(entity).with_children(|parent| {
+                    parent.spawn((
+                        LineTextMarker,
+                        Text2dBundle {
+                            text: Text::from_section(content.clone(), style),
+                            transform: Transform::from_scale(scale.extend(1.0)),
+                            ..default()
+                        },
+                    ));
+                });
+            }
+            (None, Some(text_entity)) => {
+                commands.entity(text_entity).despawn_recursive();
+            }
+            (None, None) => {}
         }
-        .with_a(opacity.0);
     }
 }
 
 pub fn update_note_y_system(
-    query: Query<(&Children, Entity), With<Line>>,
+    query: Query<(&Children, Entity, &Line)>,
     game_viewport: Res<GameViewport>,
     speed_event_query: Query<(&SpeedEvent, &LineEvent, &Parent)>,
-    mut note_query: Query<(&mut Transform, &mut Sprite, &Note)>,
+    inheritance: LineInheritanceQuery,
+    mut note_query: Query<(&mut Transform, &mut Sprite, &mut Visibility, &Note)>,
     time: Res<ChartTime>,
     bpm_list: Res<BpmList>,
 ) {
     let all_speed_events: Vec<_> = speed_event_query.iter().collect();
-    for (children, entity) in &query {
-        let mut speed_events: Vec<&SpeedEvent> = all_speed_events
-            .iter()
-            .filter(|(_, _, parent)| parent.get() == entity)
-            .map(|(s, _, _)| *s)
-            .collect();
-        speed_events.sort_by(|a, b| {
-            Rational32::from_f32(a.start_time).cmp(&Rational32::from_f32(b.start_time))
-        });
+    for (children, entity, line) in &query {
+        // speed inherited from parents is added, so the distance is the sum of the distances of each source
+        let speed_events = inheritance
+            .speed_sources(entity)
+            .into_iter()
+            .map(|source| {
+                let mut speed_events: Vec<&SpeedSegment> = all_speed_events
+                    .iter()
+                    .filter(|(_, _, parent)| parent.get() == source)
+                    .flat_map(|(s, _, _)| &s.0)
+                    .collect();
+                speed_events.sort_by(|a, b| {
+                    Rational32::from_f32(a.start_time).cmp(&Rational32::from_f32(b.start_time))
+                });
+                speed_events
+            })
+            .collect::<Vec<_>>();
 
         let distance = |time| {
-            distance_at(&speed_events, time) * (game_viewport.0.height() * (120.0 / 900.0))
+            speed_events
+                .iter()
+                .map(|speed_events| distance_at(speed_events, time))
+                .sum::<f32>()
+                * (game_viewport.0.height() * (120.0 / 900.0))
                 / (game_viewport.0.width() * 3.0 / 1920.0)
         };
         let current_distance = distance(time.0);
         for child in children {
-            if let Ok((mut transform, mut sprite, note)) = note_query.get_mut(*child) {
+            if let Ok((mut transform, mut sprite, mut visibility, note)) =
+                note_query.get_mut(*child)
+            {
                 let mut y = (distance(bpm_list.time_at(note.beat)) - current_distance) * note.speed;
+                if line.hide_notes_below && y < 0.0 && bpm_list.time_at(note.beat) > time.0 {
+                    *visibility = Visibility::Hidden;
+                }
                 match note.kind {
                     NoteKind::Hold { hold_beat } => {
                         y = y.max(0.0);
@@ -231,7 +385,9 @@ pub fn update_note_y_system(
                     }
                 }
 
-                transform.translation.y = y * if note.above { 1.0 } else { -1.0 };
+                let y_offset = note.y_offset / CANVAS_HEIGHT * game_viewport.0.height()
+                    / (game_viewport.0.width() * 3.0 / 1920.0);
+                transform.translation.y = (y + y_offset) * if note.above { 1.0 } else { -1.0 };
             }
         }
     }
@@ -255,6 +411,12 @@ pub fn update_note_texture_system(
     }
 }
 
+pub fn update_note_alpha_system(mut query: Query<(&mut Sprite, &Note)>) {
+    for (mut sprite, note) in &mut query {
+        sprite.color.set_a(note.alpha / 255.0);
+    }
+}
+
 #[derive(Debug, Component, Default, Clone)]
 pub struct HoldHead;
 #[derive(Debug, Component, Default, Clone)]
@@ -380,6 +542,20 @@ pub fn update_hold_component_texture_system(
     }
 }
 
+/// Apply the alpha of hold notes to their heads and tails, the body is the sprite of the note itself
+pub fn update_hold_component_alpha_system(
+    note_query: Query<(&Note, &Children)>,
+    mut component_query: Query<&mut Sprite, With<HoldComponent>>,
+) {
+    for (note, children) in &note_query {
+        for child in children {
+            if let Ok(mut sprite) = component_query.get_mut(*child) {
+                sprite.color.set_a(note.alpha / 255.0);
+            }
+        }
+    }
+}
+
 fn hide_hold_head_system(
     note_query: Query<(&Note, &Children)>,
     mut head_query: Query<&mut Visibility, With<HoldHead>>,
@@ -414,24 +590,82 @@ pub fn despawn_hold_component_system(
     }
 }
 
+/// The custom texture of a line, along with the path it is loaded from
+#[derive(Component, Debug)]
+pub struct LineTexture {
+    path: String,
+    /// [`None`] if the texture failed to load
+    handle: Option<Handle<Image>>,
+}
+
+/// Load custom textures of lines from the project directory when their texture path changes
+pub fn load_line_texture_system(
+    mut commands: Commands,
+    query: Query<(Entity, &Line, Option<&LineTexture>), Changed<Line>>,
+    project: Option<Res<Project>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (entity, line, texture) in &query {
+        let Some(path) = &line.texture else {
+            if texture.is_some() {
+                commands.entity(entity).remove::<LineTexture>();
+            }
+            continue;
+        };
+        if texture.is_some_and(|texture| texture.path == *path) {
+            continue;
+        }
+        let Some(project) = &project else {
+            continue;
+        };
+
+        let handle = match image::open(project.path.sub_path(path)) {
+            Ok(image) => Some(images.add(Image::from_dynamic(
+                image,
+                true,
+                RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+            ))),
+            Err(error) => {
+                warn!("Failed to load line texture {}: {}", path, error);
+                None
+            }
+        };
+        commands.entity(entity).insert(LineTexture {
+            path: path.clone(),
+            handle,
+        });
+    }
+}
+
 pub fn update_line_texture_system(
-    mut query: Query<&mut Handle<Image>, With<Line>>,
+    mut query: Query<(&mut Handle<Image>, Option<&LineTexture>), With<Line>>,
     assets: Res<ImageAssets>,
 ) {
-    for mut image in &mut query {
-        *image = assets.line.clone();
+    for (mut image, texture) in &mut query {
+        *image = texture
+            .and_then(|texture| texture.handle.clone())
+            .unwrap_or_else(|| assets.line.clone());
     }
 }
 
+/// The speed of a speed event over time, made of linear segments
+///
+/// Transitions are a single segment, expressions are sampled into several segments per beat
 #[derive(Component, Debug)]
-pub struct SpeedEvent {
+pub struct SpeedEvent(Vec<SpeedSegment>);
+
+/// Number of linear segments per beat speed expressions are previewed with, they are baked precisely when compiling
+const SPEED_EXPRESSION_SEGMENTS_PER_BEAT: f32 = 16.0;
+
+#[derive(Debug)]
+struct SpeedSegment {
     start_time: f32,
     end_time: f32,
     start_value: f32,
     end_value: f32,
 }
 
-impl SpeedEvent {
+impl SpeedSegment {
     fn new(start_time: f32, end_time: f32, start_value: f32, end_value: f32) -> Self {
         Self {
             start_time,
@@ -444,22 +678,57 @@ impl SpeedEvent {
 
 pub fn calculate_speed_events_system(
     mut commands: Commands,
-    query: Query<(&LineEvent, Entity)>,
+    query: Query<(Ref<LineEvent>, Entity)>,
     bpm_list: Res<BpmList>,
 ) {
     for (event, entity) in &query {
+        if !event.is_changed() && !bpm_list.is_changed() {
+            continue;
+        }
+
         if let LineEventKind::Speed = event.kind {
-            commands.entity(entity).insert(SpeedEvent::new(
-                bpm_list.time_at(event.start_beat),
-                bpm_list.time_at(event.end_beat),
-                event.value.start(),
-                event.value.end(),
-            ));
+            let segments = match event.value {
+                LineEventValue::Expression(_) => {
+                    let start_beat = event.start_beat.value();
+                    let end_beat = event.end_beat.value();
+                    let count = ((end_beat - start_beat) * SPEED_EXPRESSION_SEGMENTS_PER_BEAT)
+                        .ceil()
+                        .max(1.0) as usize;
+                    let beat_at = |index: usize| {
+                        start_beat + (end_beat - start_beat) * index as f32 / count as f32
+                    };
+                    let value_at = |beat: f32| {
+                        event
+                            .evaluate(beat)
+                            .value()
+                            .unwrap_or_else(|| event.start_value())
+                    };
+
+                    (0..count)
+                        .map(|index| {
+                            let (start, end) = (beat_at(index), beat_at(index + 1));
+                            SpeedSegment::new(
+                                bpm_list.time_at(start.into()),
+                                bpm_list.time_at(end.into()),
+                                value_at(start),
+                                value_at(end),
+                            )
+                        })
+                        .collect()
+                }
+                _ => vec![SpeedSegment::new(
+                    bpm_list.time_at(event.start_beat),
+                    bpm_list.time_at(event.end_beat),
+                    event.start_value(),
+                    event.end_value(),
+                )],
+            };
+            commands.entity(entity).insert(SpeedEvent(segments));
         }
     }
 }
 
-fn distance_at(speed_events: &Vec<&SpeedEvent>, time: f32) -> f32 {
+fn distance_at(speed_events: &Vec<&SpeedSegment>, time: f32) -> f32 {
     let mut t = 0.0;
     let mut v = 10.0;
     let mut area = 0.0;
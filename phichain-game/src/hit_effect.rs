@@ -161,6 +161,10 @@ fn spawn_hit_effect_system(
     }
 
     for (note, global_transform, entity, played) in &query {
+        if note.fake {
+            continue;
+        }
+
         let mut spawn = || {
             let translation = global_transform.translation();
 
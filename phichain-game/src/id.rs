@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use phichain_chart::event::LineEvent;
+use phichain_chart::id::{Id, Identifiable};
+use phichain_chart::line::Line;
+use phichain_chart::note::Note;
+
+/// Keeps the persistent ids of lines, notes and events unique
+///
+/// Lines, notes and events created in the world (e.g. created or pasted in the editor) are assigned a new id
+/// if they do not have one or their id is already taken
+pub struct IdPlugin;
+
+impl Plugin for IdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                assign_id_system::<Line>,
+                assign_id_system::<Note>,
+                assign_id_system::<LineEvent>,
+            ),
+        );
+    }
+}
+
+fn assign_id_system<T: Component + Identifiable>(
+    mut set: ParamSet<(Query<(), Added<T>>, Query<&mut T>)>,
+) {
+    if set.p0().is_empty() {
+        return;
+    }
+
+    let mut query = set.p1();
+    // ids taken by existing items, added items taking these ids are copies
+    let mut taken = HashSet::new();
+    for item in query.iter_mut().filter(|x| !x.is_added()) {
+        if let Some(id) = item.id() {
+            taken.insert(id);
+        }
+    }
+
+    for mut item in query.iter_mut().filter(|x| x.is_added()) {
+        match item.id() {
+            Some(id) if taken.insert(id) => {}
+            _ => {
+                let id = Id::new();
+                taken.insert(id);
+                item.set_id(id);
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use bevy::{prelude::*, render::render_asset::RenderAssetUsages};
+use phichain_chart::line::Line;
 
 use crate::{
     constants::{ILLUSTRATION_ALPHA, ILLUSTRATION_BLUR},
@@ -76,8 +77,9 @@ fn resize_illustration_system(
     illustration.custom_size = Some(viewport.0.size());
 }
 
+/// Lines are excluded since their z is determined by their z-order
 fn place_everything_above_illustration_system(
-    mut query: Query<&mut Transform, Without<Illustration>>,
+    mut query: Query<&mut Transform, (Without<Illustration>, Without<Line>)>,
 ) {
     for mut transform in &mut query {
         transform.translation.z = 1.0;
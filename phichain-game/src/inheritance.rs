@@ -0,0 +1,91 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use phichain_chart::line::{Line, LineOpacity, LineRotation, LineScale};
+
+/// Resolves the state a line inherits from its parent lines, following [`phichain_chart::line::LineInheritance`]
+///
+/// The position is inherited through the transform hierarchy, this handles the parts the hierarchy does not
+#[derive(SystemParam)]
+pub struct LineInheritanceQuery<'w, 's> {
+    query: Query<
+        'w,
+        's,
+        (
+            &'static Line,
+            &'static LineRotation,
+            &'static LineOpacity,
+            &'static LineScale,
+            Option<&'static Parent>,
+        ),
+    >,
+}
+
+impl LineInheritanceQuery<'_, '_> {
+    fn parent(&self, entity: Entity) -> Option<Entity> {
+        let (.., parent) = self.query.get(entity).ok()?;
+        parent
+            .map(|parent| parent.get())
+            .filter(|parent| self.query.contains(*parent))
+    }
+
+    /// Rotation of the line in the world, in radians
+    pub fn world_rotation(&self, entity: Entity) -> f32 {
+        let Ok((line, rotation, ..)) = self.query.get(entity) else {
+            return 0.0;
+        };
+        match self.parent(entity) {
+            Some(parent) if line.inheritance.rotation => rotation.0 + self.world_rotation(parent),
+            _ => rotation.0,
+        }
+    }
+
+    /// Rotation of the line relative to its parent entity, used for its transform
+    pub fn local_rotation(&self, entity: Entity) -> f32 {
+        let Ok((line, rotation, ..)) = self.query.get(entity) else {
+            return 0.0;
+        };
+        match self.parent(entity) {
+            // cancel the rotation of the parent applied by the transform hierarchy
+            Some(parent) if !line.inheritance.rotation => rotation.0 - self.world_rotation(parent),
+            _ => rotation.0,
+        }
+    }
+
+    /// Opacity of the line after applying the opacity of its parents
+    pub fn opacity(&self, entity: Entity) -> f32 {
+        let Ok((line, _, opacity, ..)) = self.query.get(entity) else {
+            return 0.0;
+        };
+        match self.parent(entity) {
+            Some(parent) if line.inheritance.opacity => opacity.0 * self.opacity(parent),
+            _ => opacity.0,
+        }
+    }
+
+    /// Scale of the line after applying the scale of its parents
+    pub fn scale(&self, entity: Entity) -> Vec2 {
+        let Ok((line, _, _, scale, _)) = self.query.get(entity) else {
+            return Vec2::ONE;
+        };
+        match self.parent(entity) {
+            Some(parent) if line.inheritance.scale => scale.0 * self.scale(parent),
+            _ => scale.0,
+        }
+    }
+
+    /// The line and the parents whose speed is added to the line, starting with the line itself
+    pub fn speed_sources(&self, entity: Entity) -> Vec<Entity> {
+        let mut sources = vec![entity];
+        let mut current = entity;
+        while let Ok((line, ..)) = self.query.get(current) {
+            match self.parent(current) {
+                Some(parent) if line.inheritance.speed => {
+                    sources.push(parent);
+                    current = parent;
+                }
+                _ => break,
+            }
+        }
+        sources
+    }
+}
@@ -1,3 +1,8 @@
+pub const LINE_LAYER: f32 = 1.0;
+/// Offset of z for each [`phichain_chart::line::Line::z_order`], small enough to keep lines below notes
+pub const LINE_Z_ORDER_STEP: f32 = 0.01;
+/// Z-orders are clamped into this range to keep lines between the illustration and notes
+pub const MAX_LINE_Z_ORDER: i32 = 50;
 pub const HOLD_LAYER: f32 = 10.0;
 pub const NOTE_LAYER: f32 = 20.0;
 pub const HIT_EFFECT_LAYER: f32 = 30.0;
@@ -2,7 +2,9 @@ pub mod constants;
 pub mod core;
 pub mod highlight;
 mod hit_effect;
+mod id;
 pub mod illustration;
+pub mod inheritance;
 mod layer;
 mod loader;
 pub mod scale;
@@ -14,6 +16,7 @@ pub use crate::loader::load_project;
 use crate::core::CoreGamePlugin;
 use crate::highlight::HighlightPlugin;
 use crate::hit_effect::HitEffectPlugin;
+use crate::id::IdPlugin;
 use crate::illustration::IllustrationPlugin;
 use crate::scale::ScalePlugin;
 use crate::score::ScorePlugin;
@@ -76,6 +79,7 @@ pub struct GameSet;
 /// - Updating translations for entities with [`Line`]s and [`Note`]s
 /// - If [`GameConfig::multi_highlight`] is true, attach [`Highlighted`] for all notes with multi highlight
 /// - Hit effects (including animations and particles)
+/// - Assigning persistent ids to created lines, notes and events
 ///
 /// [`Line`]: phichain_chart::line::Line
 /// [`Note`]: phichain_chart::note::Note
@@ -93,6 +97,7 @@ impl Plugin for GamePlugin {
             .add_plugins(CoreGamePlugin)
             .add_plugins(ShapePlugin)
             .add_plugins(HitEffectPlugin)
+            .add_plugins(IdPlugin)
             .add_plugins(ScorePlugin)
             .add_plugins(GameUiPlugin)
             .add_plugins(IllustrationPlugin);
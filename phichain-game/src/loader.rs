@@ -20,6 +20,7 @@ use std::fs::File;
 ///
 /// - [phichain_chart::offset::Offset] will be inserted into the world
 /// - [phichain_chart::bpm_list::BpmList] will be inserted into the world
+/// - [phichain_chart::time_signature::TimeSignatureList] will be inserted into the world
 /// - Entities with components [`LineBundle`] and [`NoteBundle`] will be spawned into the world, with parent-child relationship
 pub fn load_project(project: &Project, commands: &mut Commands) -> anyhow::Result<()> {
     let file = File::open(project.path.chart_path())?;
@@ -65,6 +66,7 @@ fn load(file: File, commands: &mut Commands) -> anyhow::Result<()> {
 
     commands.insert_resource(chart.offset);
     commands.insert_resource(chart.bpm_list);
+    commands.insert_resource(chart.time_signatures);
 
     let mut first_line_id: Option<Entity> = None;
     for line in chart.lines {
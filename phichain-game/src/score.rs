@@ -41,7 +41,8 @@ fn update_score_system(
     time: Res<ChartTime>,
     bpm_list: Res<BpmList>,
 ) {
-    let notes: Vec<_> = note_query.iter().collect();
+    // fake notes can not be hit, they are not counted into the score
+    let notes: Vec<_> = note_query.iter().filter(|note| !note.fake).collect();
     score.combo = notes
         .iter()
         .filter(|note| bpm_list.time_at(note.beat) <= time.0)